@@ -12,3 +12,20 @@ Licensed under
 
 [`tested_fixture`]: https://docs.rs/tested-fixture/latest/tested_fixture/attr.tested_fixture.html "attr tested_fixture::tested_fixture"
 [`std::process::Termination`]: https://doc.rust-lang.org/nightly/std/process/trait.Termination.html "trait std::process::Termination"
+[`RuntimeFixture`]: https://docs.rs/tested-fixture/latest/tested_fixture/struct.RuntimeFixture.html "struct tested_fixture::RuntimeFixture"
+[`IntoFixtureResult`]: https://docs.rs/tested-fixture/latest/tested_fixture/trait.IntoFixtureResult.html "trait tested_fixture::IntoFixtureResult"
+[`std::borrow::Borrow`]: https://doc.rust-lang.org/nightly/std/borrow/trait.Borrow.html "trait std::borrow::Borrow"
+[`Into`]: https://doc.rust-lang.org/nightly/core/convert/trait.Into.html "trait core::convert::Into"
+[`FixtureError`]: https://docs.rs/tested-fixture/latest/tested_fixture/struct.FixtureError.html "struct tested_fixture::FixtureError"
+[`std::sync::OnceLock`]: https://doc.rust-lang.org/nightly/std/sync/struct.OnceLock.html "struct std::sync::OnceLock"
+[`std::sync::Mutex`]: https://doc.rust-lang.org/nightly/std/sync/struct.Mutex.html "struct std::sync::Mutex"
+[`std::sync::RwLock`]: https://doc.rust-lang.org/nightly/std/sync/struct.RwLock.html "struct std::sync::RwLock"
+[`registry`]: https://docs.rs/tested-fixture/latest/tested_fixture/fn.registry.html "fn tested_fixture::registry"
+[`RegisteredFixture::force`]: https://docs.rs/tested-fixture/latest/tested_fixture/struct.RegisteredFixture.html#method.force "method tested_fixture::RegisteredFixture::force"
+[`init_all`]: https://docs.rs/tested-fixture/latest/tested_fixture/fn.init_all.html "fn tested_fixture::init_all"
+[`warm_up_parallel`]: https://docs.rs/tested-fixture/latest/tested_fixture/fn.warm_up_parallel.html "fn tested_fixture::warm_up_parallel"
+[`force_parallel`]: https://docs.rs/tested-fixture/latest/tested_fixture/fn.force_parallel.html "fn tested_fixture::force_parallel"
+[`FixtureHandle`]: https://docs.rs/tested-fixture/latest/tested_fixture/struct.FixtureHandle.html "struct tested_fixture::FixtureHandle"
+[`FixtureState`]: https://docs.rs/tested-fixture/latest/tested_fixture/enum.FixtureState.html "enum tested_fixture::FixtureState"
+[`AlreadyInitialized`]: https://docs.rs/tested-fixture/latest/tested_fixture/struct.AlreadyInitialized.html "struct tested_fixture::AlreadyInitialized"
+[`get`]: https://docs.rs/tested-fixture/latest/tested_fixture/fn.get.html "fn tested_fixture::get"
@@ -0,0 +1,6 @@
+fn main() {
+    // Set by `cargo llvm-cov` on nightly, and used by the `coverage` feature.
+    // Declared here rather than in `[lints]`, which needs Cargo 1.74
+    println!("cargo:rustc-check-cfg=cfg(coverage_nightly)");
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -1,24 +1,183 @@
-use std::mem::replace;
+use std::{
+    mem::{replace, take},
+    time::Duration,
+};
 
 use proc_macro::TokenStream;
-use proc_macro2::Span;
+use proc_macro2::{Group, Span, TokenTree};
 use proc_macro_crate::{crate_name, FoundCrate};
-use quote::quote;
+use quote::{format_ident, quote, quote_spanned};
 use syn::{
     ext::IdentExt,
+    parenthesized,
     parse::{Parse, ParseStream},
-    parse_macro_input, parse_quote, Attribute, Ident, ItemFn, Result, ReturnType, Token, Type,
+    parse_macro_input, parse_quote, parse_quote_spanned,
+    punctuated::Punctuated,
+    spanned::Spanned,
+    token::Paren,
+    Attribute, Error, Expr, File, GenericArgument, Ident, Index, Item, ItemFn, LitInt, LitStr,
+    Meta, Path, PathArguments, Result, ReturnType, Token, TraitBoundModifier, Type, TypeArray,
+    TypeGroup, TypeImplTrait, TypeParamBound, TypeParen, TypePath, TypeReference, TypeSlice,
     TypeTuple, Visibility,
 };
 
 /// Attribute macro applied to a function to turn it into a unit test which is cached
 /// as a fixture
 ///
-/// The syntax supported by this macro is:  `attr* vis? ident (: ty)?`
+/// The syntax supported by this macro is:  `fixture (, option)*`, where
+/// `fixture` is either `attr* vis? mut? ident (: ty)?` or a list of them
+/// (optionally parenthesized) to split a returned tuple into one fixture per
+/// element. Without parentheses, a later fixture written as a bare lower case
+/// `ident` is parsed as an option instead. With no arguments at all, the
+/// fixture is named after the function in upper snake case, so
+/// `fn build_database` declares `BUILD_DATABASE`.
 ///
 /// All attributes and the visibilty level will be applied to the newly declared
-/// static fixture `ident`. The type can either be explicitly specified or will
-/// be inferred from the return type of the function being annotated.
+/// static fixture `ident`, while attributes on the function apply to the
+/// generated test (other than a leftover `#[test]`, which is dropped in favor
+/// of the generated one). Unless given docs of its own, the static is
+/// documented with the function's doc comment. Fixtures may also be named in
+/// lower case (e.g. `step_1_fixture`) without `non_upper_case_globals`
+/// warnings. A fixture declared `mut ident` is stored in a `std::sync::Mutex`
+/// (or a `std::sync::RwLock` with the `rwlock` flag), so that tests can lock it
+/// to mutate the fixture. The type can either be explicitly specified or will
+/// be inferred from the return type of the function being annotated. When the
+/// return type is spelled `Result<T, ...>`, the inferred type is `T` and only
+/// `Ok` values are captured, failing the test otherwise. Specifying the type
+/// likewise captures only the `Ok` or `Some` values of other `Result`s and
+/// `Option`s, or can be used to store an entire `Result`. It may also be an
+/// unsized type borrowed from the returned value, such as `str` for a `String`,
+/// or `_` for the `Ok` type of a returned `Result`. A specified type which
+/// can't be captured from the return type is reported as an error on the type.
+///
+/// The annotated function may be `async`, in which case its body is driven to
+/// completion inside the generated test before being cached. The fixtures of
+/// `async` functions can additionally be awaited with `ident.get_async().await`.
+/// Since the macro generates its own test, it cannot be combined with
+/// attributes like `#[tokio::test]`; use the `runtime` option instead.
+///
+/// The supported options are:
+/// * `runtime = "tokio"`: drive an `async` body on a Tokio runtime (requires
+///   the `tokio` feature)
+///   * `flavor = "current_thread"` (the default) or `flavor = "multi_thread"`:
+///     the flavor of the Tokio runtime
+///   * `worker_threads = n`: the number of worker threads of a
+///     `"multi_thread"` Tokio runtime
+/// * `block_on = path`: drive an `async` body with a custom executor function
+///   such as `fn block_on<F: Future>(future: F) -> F::Output`
+/// * `runtime = "tokio-local"`: drive a `!Send` `async` body on a Tokio
+///   `LocalSet` (requires the `tokio` feature)
+/// * `runtime = "async-std"`: drive an `async` body with async-std (requires
+///   the `async-std` feature)
+/// * `error_display`: report errors which implement `Display` but not `Debug`
+/// * `capture = "err"`: capture the `Err` value of a `Result` as the fixture,
+///   failing the test if the function returns `Ok`
+/// * `full`: store the entire return value, even if it is a `Result`, and pass
+///   the test unless the function panics
+///   * `pass_if = predicate`: a `fn(&T) -> bool` deciding whether the test
+///     passes instead
+/// * `error = Type`: the error type of the returned `Result`, checked against
+///   the function's signature and used to infer the fixture type when the
+///   `Result` is hidden behind a type alias
+/// * `store_as = Type`: convert the captured value with `Into` before storing
+///   it, so the fixture has type `Type`
+/// * `map = |value| ...`: transform the captured value before storing it,
+///   which requires the fixture's type to be specified
+/// * `no_catch_unwind`: don't catch panics in a non-`async` body, so a panic
+///   fails only the generated test and the fixture is retried by dependents
+/// * `rwlock`: store a `mut` fixture in a `std::sync::RwLock` rather than a
+///   `std::sync::Mutex`
+/// * `clone`: expose the fixture only through `ident.cloned()`, giving each
+///   dependent its own copy of the cached value
+/// * `fresh`: run the function again for every dependent, which receives
+///   the owned value through `ident.get()` instead of a cached reference
+/// * `once`: expose the fixture only through `ident.take()`, moving the
+///   value out to the single dependent which owns it
+/// * `arc`: store the fixture in a `std::sync::Arc`, which can be shared
+///   with spawned threads and tasks through `ident.share()`
+/// * `releasable`: allow the fixture to be dropped before the test binary
+///   exits, borrowing it through `ident.read()` and dropping it with
+///   `ident.release()` from its last dependent, after which any other access
+///   panics
+/// * `thread_local`: build the fixture once per thread, for types which
+///   aren't `Sync`, exposing it through `ident.with(|value| ...)`
+/// * `local`: build the fixture once for types which aren't `Sync`, which
+///   dereferences like any other fixture but panics when used from any
+///   thread but the one which built it (requiring `--test-threads=1`)
+/// * `teardown = |value| ...`: clean up the fixture when the test binary
+///   exits, in the reverse order that fixtures were initialized
+/// * `verify_injected = predicate`: a `fn(&T) -> bool` deciding whether the
+///   test passes when the fixture's value was injected with `ident.set(value)`,
+///   rather than passing without running the function
+/// * `fallback = expr`: the value of the fixture when the function is
+///   compiled out, rather than failing to compile dependents
+///   * `cfg(predicate)`: compile the function only if `predicate` holds, like
+///     `#[cfg(predicate)]`
+///   * `default`: always use the fallback value, even when the function is
+///     compiled in
+/// * `eager`: start initializing the fixture on a background thread as soon
+///   as the test binary launches (requires the `eager` feature)
+/// * `expect_panic`: expect the function to panic, capturing its panic
+///   message as a `String` fixture and failing the test if it returns
+/// * `retries = N`: run a non-`async` function again, up to `N` more times,
+///   whenever it returns an error or panics, caching only the final failure
+/// * `retry_delay = "5s"`: wait between the attempts of a fixture with
+///   `retries`, accepting the same durations as `timeout`
+/// * `crate = path`: the path this crate is re-exported from, for use
+///   through a facade crate which doesn't depend on it directly
+/// * `exposed(predicate)`: also compile the fixture outside of `cfg(test)`
+///   when `predicate` holds, e.g. `exposed(feature = "test-fixtures")`, so
+///   that integration tests and other crates can use it
+/// * `expose_fn = ident`: also declare a public function `ident` running the
+///   function's body without caching its value, for use outside of tests
+///   (compiled under the same conditions as the fixture)
+/// * `export_to = path`: check that the module at `path` re-exports this very
+///   fixture (e.g. with `pub(crate) use crate::steps::*;`), so fixtures
+///   declared across modules can be imported from one place. Nothing is
+///   exported by the option: the re-export must be written by hand, and the
+///   fixture declared visible enough for it, as in `pub(crate) STEP_1`. A
+///   different item of the same name fails to compile, or fails the test if
+///   it's a static of the same type
+/// * `static_attrs(attr, ...)`: attributes (without their `#[...]`) applied to
+///   every fixture static, which may also precede the fixtures
+/// * `test_attrs(attr, ...)`: attributes applied only to the generated test,
+///   such as `ignore`, which may also precede the fixtures
+/// * `no_test`: don't generate a test, only caching the fixture for its
+///   dependents (who still report its failures)
+/// * `test_name = "ident"`: the name of the generated test, in place of the
+///   function's. The function keeps its name and body, and is called by the
+///   fixture
+/// * `order = N`: prefix the generated test's name with `N` (from 0 to 999)
+///   padded to three digits, e.g. `_000_step_1`, so that it sorts before
+///   the other tests of its module
+/// * `strict`: panic in dependents which find the fixture uninitialized,
+///   rather than initializing it in place of its test (as if
+///   `TESTED_FIXTURE_STRICT` were set)
+/// * `test_attr = path`: the attribute marking the generated test, such as
+///   `test_log::test`, in place of `#[test]`. It's placed after the
+///   function's own attributes, so attribute macros among them are expanded
+///   first
+/// * `invariant = |v: &T| ...`: in debug builds, check that the fixture
+///   satisfies the predicate once it's initialized and on every access,
+///   panicking if it doesn't (e.g. because a test mutated it)
+/// * `verify_unchanged`: in debug builds, fingerprint the fixture by its
+///   `Hash` implementation when it's initialized, and panic on any access
+///   after it changes. `verify_unchanged = |v: &T| ...` fingerprints it by
+///   the `Debug` output of the function instead, which is shown when it
+///   changes
+/// * `alternative(cfg(predicate), function)`: call `function` (e.g. a path
+///   or a closure) in place of the fixture function whenever the predicate
+///   holds, such as `alternative(cfg(miri), in_memory_server)`. Its return
+///   type must match the fixture function's
+/// * `requires_env = "VAR"`: skip the fixture if the environment variable
+///   `VAR` isn't set, passing its test and failing dependents with a single
+///   line. Repeated for each variable the fixture requires
+/// * `stack_size = expr`: run the function on a helper thread with a stack
+///   of `expr` bytes, e.g. `stack_size = 16 * 1024 * 1024` for a deeply
+///   recursive body
+/// * `timeout = "30s"`: fail the fixture if its body doesn't complete in
+///   time, accepting durations such as `"500ms"` or `"1m 30s"`. Synchronous
+///   bodies are run on a helper thread, which is abandoned if it times out
 #[proc_macro_attribute]
 pub fn tested_fixture(attr: TokenStream, item: TokenStream) -> TokenStream {
     tested_fixture_helper(attr, item, false)
@@ -30,30 +189,39 @@ pub fn tested_fixture_doctest(attr: TokenStream, item: TokenStream) -> TokenStre
     tested_fixture_helper(attr, item, true)
 }
 
-struct Attr {
+/// A fixture static declared by the attribute
+struct Binding {
     pub attrs: Vec<Attribute>,
     pub vis: Visibility,
+    pub mutability: Option<Token![mut]>,
     pub ident: Ident,
     #[allow(unused)]
     pub colon: Option<Token![:]>,
     pub ty: Option<Type>,
 }
 
-impl Parse for Attr {
+impl Parse for Binding {
     fn parse(input: ParseStream) -> Result<Self> {
         let attrs = input.call(Attribute::parse_outer)?;
         let vis: Visibility = input.parse()?;
+        let mutability = input.parse()?;
         let ident = input.call(Ident::parse_any)?;
 
         let (colon, ty) = if input.peek(Token![:]) {
-            (Some(input.parse()?), Some(input.parse()?))
+            (Some(input.parse()?), Some(input.parse::<Type>()?))
         } else {
             (None, None)
         };
-
-        Ok(Attr {
+        if let Some(opaque) = ty.as_ref().and_then(opaque_type) {
+            return Err(Error::new_spanned(
+                opaque,
+                "`impl Trait` can't be part of a fixture's type, since statics can't have opaque types; use `dyn Trait` instead",
+            ));
+        }
+        Ok(Binding {
             attrs,
             vis,
+            mutability,
             ident,
             colon,
             ty,
@@ -61,80 +229,4137 @@ impl Parse for Attr {
     }
 }
 
-fn tested_fixture_helper(attr: TokenStream, item: TokenStream, doctest: bool) -> TokenStream {
-    let found_crate =
-        crate_name("tested-fixture").expect("tested-fixture is present in `Cargo.toml`");
-    let found_crate = match found_crate {
-        FoundCrate::Name(name) => Ident::new(&name, Span::call_site()),
-        FoundCrate::Itself if doctest => Ident::new("tested_fixture", Span::call_site()),
-        FoundCrate::Itself => <Token![crate]>::default().into(),
+/// The fixture statics declared by the attribute
+enum Fixtures {
+    /// A single fixture holding the entire value
+    Single(Box<Binding>),
+    /// One fixture per element of a returned tuple, with the span to report
+    /// arity mismatches at
+    Tuple(Span, Vec<Binding>),
+}
+
+impl Fixtures {
+    /// A human readable name for the fixtures
+    fn name(&self) -> String {
+        match self {
+            Fixtures::Single(binding) => binding.ident.unraw().to_string(),
+            Fixtures::Tuple(_, bindings) => {
+                let names: Vec<_> = bindings
+                    .iter()
+                    .map(|b| b.ident.unraw().to_string())
+                    .collect();
+                format!("({})", names.join(", "))
+            }
+        }
+    }
+}
+
+impl Fixtures {
+    /// The statics declared for the fixtures
+    fn bindings(&self) -> &[Binding] {
+        match self {
+            Fixtures::Single(binding) => std::slice::from_ref(&**binding),
+            Fixtures::Tuple(_, bindings) => bindings,
+        }
+    }
+
+    /// The statics declared for the fixtures, mutably
+    fn bindings_mut(&mut self) -> &mut [Binding] {
+        match self {
+            Fixtures::Single(binding) => std::slice::from_mut(&mut **binding),
+            Fixtures::Tuple(_, bindings) => bindings,
+        }
+    }
+
+    /// The `mut` keyword of a fixture stored in a `Mutex`
+    fn mutability(&self) -> Option<Token![mut]> {
+        match self {
+            Fixtures::Single(binding) => binding.mutability,
+            Fixtures::Tuple(..) => None,
+        }
+    }
+
+    /// The explicitly specified type of the whole fixture, if there is one
+    fn ty(&self) -> Option<Type> {
+        match self {
+            Fixtures::Single(binding) => binding.ty.clone(),
+            Fixtures::Tuple(_, bindings) => Some(Type::Tuple(TypeTuple {
+                paren_token: Default::default(),
+                elems: bindings
+                    .iter()
+                    .map(|b| b.ty.clone())
+                    .collect::<Option<_>>()?,
+            })),
+        }
+    }
+}
+
+/// Whether the input is a `,` followed by another fixture rather than an
+/// option, which is lower case unlike the conventional name of a static
+fn peek_binding(input: ParseStream) -> bool {
+    let fork = input.fork();
+    if fork.parse::<Token![,]>().is_err() {
+        return false;
+    }
+    if fork.peek(Token![#]) || fork.peek(Token![pub]) || fork.peek(Token![mut]) {
+        return true;
+    }
+    match fork.call(Ident::parse_any) {
+        Ok(_) if fork.peek(Token![:]) => true,
+        Ok(key) => !key.unraw().to_string().chars().any(char::is_lowercase),
+        Err(_) => false,
+    }
+}
+
+impl Parse for Fixtures {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let (span, bindings) = if input.peek(Paren) {
+            let content;
+            let paren = parenthesized!(content in input);
+            let bindings = Punctuated::<Binding, Token![,]>::parse_terminated(&content)?;
+            if bindings.is_empty() {
+                return Err(Error::new(
+                    paren.span.join(),
+                    "expected at least one fixture",
+                ));
+            }
+            (paren.span.join(), bindings.into_iter().collect::<Vec<_>>())
+        } else {
+            let mut bindings = vec![input.parse::<Binding>()?];
+            while peek_binding(input) {
+                input.parse::<Token![,]>()?;
+                bindings.push(input.parse()?);
+            }
+            if bindings.len() == 1 {
+                return Ok(Fixtures::Single(Box::new(bindings.remove(0))));
+            }
+            (Span::call_site(), bindings)
+        };
+
+        if let Some(mutability) = bindings.iter().find_map(|b| b.mutability) {
+            return Err(Error::new(
+                mutability.span,
+                "`mut` isn't supported for tuple fixtures",
+            ));
+        }
+        Ok(Fixtures::Tuple(span, bindings))
+    }
+}
+
+struct Attr {
+    pub fixtures: Fixtures,
+    pub runtime: Option<Runtime>,
+    pub timeout: Option<Timeout>,
+    pub error_display: bool,
+    pub full: bool,
+    pub pass_if: Option<Expr>,
+    pub capture: Capture,
+    pub error: Option<Type>,
+    pub store_as: Option<Type>,
+    pub map: Option<Expr>,
+    pub no_catch_unwind: Option<Span>,
+    pub expect_panic: bool,
+    pub eager: Option<Span>,
+    pub rwlock: bool,
+    pub clone: Option<Span>,
+    pub fresh: Option<Span>,
+    pub once: Option<Span>,
+    pub arc: Option<Span>,
+    pub thread_local: Option<Span>,
+    pub local: Option<Span>,
+    pub releasable: Option<Span>,
+    pub retries: Option<(Span, u32)>,
+    pub retry_delay: Option<Duration>,
+    pub teardown: Option<Expr>,
+    pub verify_injected: Option<Expr>,
+    pub fallback: Option<Expr>,
+    pub default: bool,
+    pub cfg: Option<proc_macro2::TokenStream>,
+    pub test_attr: Option<Path>,
+    pub no_test: bool,
+    pub test_attrs: Vec<Meta>,
+    pub krate: Option<Path>,
+    pub export_to: Option<Path>,
+    pub exposed: Option<proc_macro2::TokenStream>,
+    pub expose_fn: Option<Ident>,
+    pub test_name: Option<Ident>,
+    pub order: Option<u16>,
+    pub strict: bool,
+    pub stack_size: Option<Expr>,
+    pub invariant: Option<Expr>,
+    pub verify_unchanged: Option<VerifyUnchanged>,
+    pub requires_env: Vec<LitStr>,
+    pub alternative: Option<Alternative>,
+}
+
+/// A function run in place of the fixture function under a `cfg`
+struct Alternative {
+    predicate: proc_macro2::TokenStream,
+    function: Expr,
+}
+
+impl Parse for Alternative {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let invalid = |span| {
+            Error::new(
+                span,
+                "`alternative` requires a `cfg` and a function, e.g. `alternative(cfg(miri), cheap_builder)`",
+            )
+        };
+        let key = input.call(Ident::parse_any)?;
+        if key != "cfg" || !input.peek(Paren) {
+            return Err(invalid(key.span()));
+        }
+        let content;
+        parenthesized!(content in input);
+        let predicate = content.parse()?;
+        if input.is_empty() {
+            return Err(invalid(key.span()));
+        }
+        input.parse::<Token![,]>()?;
+        let function = input.parse()?;
+        input.parse::<Option<Token![,]>>()?;
+        Ok(Alternative {
+            predicate,
+            function,
+        })
+    }
+}
+
+/// How a `verify_unchanged` fixture is fingerprinted
+enum VerifyUnchanged {
+    /// By its `Hash` implementation
+    Hash(Span),
+    /// By the `Debug` output of a function of it
+    Fingerprint(Box<Expr>),
+}
+
+/// Which arm of a `Result` is captured as the fixture
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Capture {
+    Ok,
+    Err,
+}
+
+impl Parse for Capture {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lit: LitStr = input.parse()?;
+        match lit.value().as_str() {
+            "ok" => Ok(Capture::Ok),
+            "err" => Ok(Capture::Err),
+            _ => Err(Error::new(
+                lit.span(),
+                "unsupported capture, expected `\"ok\"` or `\"err\"`",
+            )),
+        }
+    }
+}
+
+/// The options which take a `= value`
+const VALUE_OPTIONS: &[&str] = &[
+    "runtime",
+    "block_on",
+    "flavor",
+    "worker_threads",
+    "timeout",
+    "pass_if",
+    "capture",
+    "error",
+    "store_as",
+    "map",
+    "teardown",
+    "verify_injected",
+    "fallback",
+    "retries",
+    "retry_delay",
+    "test_attr",
+    "crate",
+    "export_to",
+    "expose_fn",
+    "test_name",
+    "order",
+    "stack_size",
+    "invariant",
+    "requires_env",
+];
+
+/// The options which are bare flags
+const FLAG_OPTIONS: &[&str] = &[
+    "error_display",
+    "full",
+    "no_catch_unwind",
+    "expect_panic",
+    "eager",
+    "rwlock",
+    "clone",
+    "fresh",
+    "once",
+    "arc",
+    "thread_local",
+    "local",
+    "releasable",
+    "default",
+    "no_test",
+    "strict",
+];
+
+/// The executor used to drive `async` fixtures
+enum Runtime {
+    Tokio { span: Span, flavor: Flavor },
+    TokioLocal(Span),
+    AsyncStd(Span),
+    Custom(Path),
+}
+
+impl Runtime {
+    fn span(&self) -> Span {
+        match self {
+            Runtime::Tokio { span, .. } | Runtime::TokioLocal(span) | Runtime::AsyncStd(span) => {
+                *span
+            }
+            Runtime::Custom(path) => path.span(),
+        }
+    }
+
+    fn option(&self) -> &'static str {
+        match self {
+            Runtime::Tokio { .. } | Runtime::TokioLocal(_) | Runtime::AsyncStd(_) => "runtime",
+            Runtime::Custom(_) => "block_on",
+        }
+    }
+}
+
+impl Parse for Runtime {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lit: LitStr = input.parse()?;
+        match lit.value().as_str() {
+            "tokio" => Ok(Runtime::Tokio {
+                span: lit.span(),
+                flavor: Flavor::CurrentThread,
+            }),
+            "tokio-local" => Ok(Runtime::TokioLocal(lit.span())),
+            "async-std" => Ok(Runtime::AsyncStd(lit.span())),
+            _ => Err(Error::new(
+                lit.span(),
+                "unsupported runtime, expected `\"tokio\"`, `\"tokio-local\"`, or `\"async-std\"`",
+            )),
+        }
+    }
+}
+
+/// The flavor of Tokio runtime used to drive `async` fixtures
+enum Flavor {
+    CurrentThread,
+    MultiThread { worker_threads: Option<usize> },
+}
+
+impl Parse for Flavor {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lit: LitStr = input.parse()?;
+        match lit.value().as_str() {
+            "current_thread" => Ok(Flavor::CurrentThread),
+            "multi_thread" => Ok(Flavor::MultiThread {
+                worker_threads: None,
+            }),
+            _ => Err(Error::new(
+                lit.span(),
+                "unsupported flavor, expected `\"current_thread\"` or `\"multi_thread\"`",
+            )),
+        }
+    }
+}
+
+/// The maximum duration of an `async` fixture's initialization
+struct Timeout {
+    span: Span,
+    text: String,
+    duration: Duration,
+}
+
+impl Parse for Timeout {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lit: LitStr = input.parse()?;
+        let text = lit.value().trim().to_owned();
+        match parse_duration(&text) {
+            Some(duration) if duration > Duration::ZERO => Ok(Timeout {
+                span: lit.span(),
+                text,
+                duration,
+            }),
+            Some(_) => Err(Error::new(
+                lit.span(),
+                "`timeout` must be greater than zero",
+            )),
+            None => Err(Error::new(lit.span(), INVALID_DURATION)),
+        }
+    }
+}
+
+/// The error reported for a duration which can't be parsed
+const INVALID_DURATION: &str =
+    "invalid duration, expected a value such as `\"500ms\"`, `\"30s\"`, or `\"1m 30s\"`";
+
+/// Parses a humantime-style duration made up of one or more `<number><unit>`
+/// components, such as `"1h 30m"`
+fn parse_duration(text: &str) -> Option<Duration> {
+    let mut rest = text.trim_start();
+    if rest.is_empty() {
+        return None;
+    }
+
+    let mut total = Duration::ZERO;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let value: u32 = rest[..digits].parse().ok()?;
+        rest = rest[digits..].trim_start();
+
+        let letters = rest
+            .find(|c: char| !c.is_alphabetic())
+            .unwrap_or(rest.len());
+        let unit = match &rest[..letters] {
+            "ns" | "nsec" | "nanos" => Duration::from_nanos(1),
+            "us" | "µs" | "usec" | "micros" => Duration::from_micros(1),
+            "ms" | "msec" | "millis" => Duration::from_millis(1),
+            "s" | "sec" | "secs" | "second" | "seconds" => Duration::from_secs(1),
+            "m" | "min" | "mins" | "minute" | "minutes" => Duration::from_secs(60),
+            "h" | "hr" | "hrs" | "hour" | "hours" => Duration::from_secs(60 * 60),
+            "d" | "day" | "days" => Duration::from_secs(24 * 60 * 60),
+            _ => return None,
+        };
+        total = total.checked_add(unit.checked_mul(value)?)?;
+        rest = rest[letters..].trim_start();
+    }
+    Some(total)
+}
+
+/// Gets the `n`th type argument of a type spelled `Result<T, E>`, such as the
+/// `T` of `std::io::Result<T>`
+///
+/// Type aliases with other names can't be seen through, and are left alone.
+fn result_type_arg(ty: &Type, n: usize) -> Option<&Type> {
+    let path = match ty {
+        Type::Path(TypePath { qself: None, path }) => path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    if segment.ident != "Result" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.iter().nth(n)? {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Gets the `Ok` type of a type spelled `Result<T, E>` mutably, see
+/// [`result_type_arg`]
+fn result_ok_type_mut(ty: &mut Type) -> Option<&mut Type> {
+    let path = match ty {
+        Type::Path(TypePath { qself: None, path }) => path,
+        _ => return None,
     };
+    let segment = path.segments.last_mut()?;
+    if segment.ident != "Result" {
+        return None;
+    }
+    match &mut segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first_mut()? {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
 
-    let attr = parse_macro_input!(attr as Attr);
-    let mut func = parse_macro_input!(item as ItemFn);
+/// Gets the `T` of a type spelled `&'static T`, `Cow<'static, T>` or
+/// `Pin<Box<T>>`, which can be exposed directly rather than behind another
+/// reference
+fn static_borrow_target(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::Reference(TypeReference {
+            lifetime: Some(lifetime),
+            mutability: None,
+            elem,
+            ..
+        }) if lifetime.ident == "static" => Some(elem),
+        Type::Path(TypePath { qself: None, path }) => {
+            let segment = path.segments.last()?;
+            let args = match &segment.arguments {
+                PathArguments::AngleBracketed(args) => &args.args,
+                _ => return None,
+            };
+            if segment.ident == "Pin" {
+                return match args.first()? {
+                    GenericArgument::Type(ty) => boxed_type(ty),
+                    _ => None,
+                };
+            }
+            if segment.ident != "Cow" {
+                return None;
+            }
+            match (args.first()?, args.iter().nth(1)?) {
+                (GenericArgument::Lifetime(lifetime), GenericArgument::Type(ty))
+                    if lifetime.ident == "static" =>
+                {
+                    Some(ty)
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
 
-    let func_attrs = &func.attrs;
-    let func_vis = &func.vis;
-    let func_ident = &func.sig.ident;
-    let func_body = &func.block;
-    let func_out = match replace(&mut func.sig.output, ReturnType::Default) {
-        ReturnType::Default => Type::Tuple(TypeTuple {
-            paren_token: Default::default(),
-            elems: Default::default(),
-        }),
-        ReturnType::Type(_, ty) => *ty,
+/// Finds an `impl Trait` within a fixture's specified type, which statics
+/// can't have
+fn opaque_type(ty: &Type) -> Option<&TypeImplTrait> {
+    match ty {
+        Type::ImplTrait(opaque) => Some(opaque),
+        Type::Path(TypePath { qself, path }) => {
+            if let Some(opaque) = qself.as_ref().and_then(|qself| opaque_type(&qself.ty)) {
+                return Some(opaque);
+            }
+            path.segments
+                .iter()
+                .find_map(|segment| match &segment.arguments {
+                    PathArguments::AngleBracketed(args) => {
+                        args.args.iter().find_map(|arg| match arg {
+                            GenericArgument::Type(ty) => opaque_type(ty),
+                            _ => None,
+                        })
+                    }
+                    _ => None,
+                })
+        }
+        Type::Reference(TypeReference { elem, .. })
+        | Type::Array(TypeArray { elem, .. })
+        | Type::Slice(TypeSlice { elem, .. })
+        | Type::Paren(TypeParen { elem, .. })
+        | Type::Group(TypeGroup { elem, .. }) => opaque_type(elem),
+        Type::Tuple(tuple) => tuple.elems.iter().find_map(opaque_type),
+        _ => None,
+    }
+}
+
+/// Finds a borrow which isn't `'static` (e.g. `&Config` or `Ref<'_, T>`)
+/// within a fixture function's return type, along with the owned type to
+/// suggest in its place
+fn borrowed_type(ty: &Type) -> Option<(&Type, String)> {
+    let spelling = |ty: &Type| quote!(#ty).to_string().replace(' ', "");
+    match ty {
+        Type::Reference(TypeReference { lifetime, elem, .. }) => match lifetime {
+            Some(lifetime) if lifetime.ident == "static" => borrowed_type(elem),
+            _ => {
+                let owned = match &**elem {
+                    Type::Path(TypePath { qself: None, path }) if path.is_ident("str") => {
+                        "String".to_owned()
+                    }
+                    Type::Slice(TypeSlice { elem, .. }) => format!("Vec<{}>", spelling(elem)),
+                    elem => spelling(elem),
+                };
+                Some((ty, owned))
+            }
+        },
+        Type::Path(TypePath { qself, path }) => {
+            if let Some(borrowed) = qself.as_ref().and_then(|qself| borrowed_type(&qself.ty)) {
+                return Some(borrowed);
+            }
+            let borrows = path.segments.iter().any(|segment| {
+                match &segment.arguments {
+                PathArguments::AngleBracketed(args) => args.args.iter().any(|arg| {
+                    matches!(arg, GenericArgument::Lifetime(lifetime) if lifetime.ident != "static")
+                }),
+                _ => false,
+            }
+            });
+            if !borrows {
+                return path
+                    .segments
+                    .iter()
+                    .find_map(|segment| match &segment.arguments {
+                        PathArguments::AngleBracketed(args) => {
+                            args.args.iter().find_map(|arg| match arg {
+                                GenericArgument::Type(ty) => borrowed_type(ty),
+                                _ => None,
+                            })
+                        }
+                        _ => None,
+                    });
+            }
+            let mut owned = path.clone();
+            for segment in owned.segments.iter_mut() {
+                if let PathArguments::AngleBracketed(args) = &mut segment.arguments {
+                    args.args = take(&mut args.args)
+                        .into_iter()
+                        .filter(|arg| !matches!(arg, GenericArgument::Lifetime(_)))
+                        .collect();
+                    if args.args.is_empty() {
+                        segment.arguments = PathArguments::None;
+                    }
+                }
+            }
+            Some((ty, quote!(#owned).to_string().replace(' ', "")))
+        }
+        Type::Array(TypeArray { elem, .. })
+        | Type::Slice(TypeSlice { elem, .. })
+        | Type::Paren(TypeParen { elem, .. })
+        | Type::Group(TypeGroup { elem, .. }) => borrowed_type(elem),
+        Type::Tuple(tuple) => tuple.elems.iter().find_map(borrowed_type),
+        _ => None,
+    }
+}
+
+/// Gets the `T` of a type spelled `Box<T>`
+fn boxed_type(ty: &Type) -> Option<&Type> {
+    let segment = match ty {
+        Type::Path(TypePath { qself: None, path }) => path.segments.last()?,
+        _ => return None,
     };
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if segment.ident == "Box" => match args.args.first()? {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
 
-    let fixture_attrs = &attr.attrs;
-    let fixture_vis = &attr.vis;
-    let fixture_ident = &attr.ident;
-    let fixture_ty = attr.ty.as_ref().unwrap_or(&func_out);
+/// Traits which commonly appear in `impl Trait` bounds, but can't be made into
+/// trait objects
+const NOT_OBJECT_SAFE: &[&str] = &[
+    "Clone",
+    "Copy",
+    "Default",
+    "Eq",
+    "Hash",
+    "Ord",
+    "PartialEq",
+    "PartialOrd",
+    "Sized",
+];
 
-    func.sig.output = ReturnType::Type(
-        Default::default(),
-        Box::new(
-            parse_quote!(std::result::Result<impl #found_crate::helpers::Unwrap::<#fixture_ty>, impl std::fmt::Debug>),
-        ),
+/// Auto traits, which can be added to any trait object
+const AUTO_TRAITS: &[&str] = &["Send", "Sync", "Unpin", "UnwindSafe", "RefUnwindSafe"];
+
+/// Replaces an `impl Trait` type (possibly wrapped in a `Result` or `Option`)
+/// with the equivalent `Box<dyn Trait + Send + Sync>`, returning the boxed type
+/// and whether it was wrapped
+fn box_impl_trait(ty: &mut Type) -> Result<Option<(Type, bool)>> {
+    let (target, nested) = match ty {
+        Type::ImplTrait(_) => (ty, false),
+        Type::Path(TypePath { qself: None, path }) => {
+            let segment = match path.segments.last_mut() {
+                Some(segment) if segment.ident == "Result" || segment.ident == "Option" => segment,
+                _ => return Ok(None),
+            };
+            match &mut segment.arguments {
+                PathArguments::AngleBracketed(args) => match args.args.first_mut() {
+                    Some(GenericArgument::Type(ty @ Type::ImplTrait(_))) => (ty, true),
+                    _ => return Ok(None),
+                },
+                _ => return Ok(None),
+            }
+        }
+        _ => return Ok(None),
+    };
+    let bounds = match &*target {
+        Type::ImplTrait(TypeImplTrait { bounds, .. }) => bounds,
+        _ => unreachable!(),
+    };
+
+    let mut principal = None;
+    let (mut send, mut sync) = (false, false);
+    for bound in bounds.iter() {
+        let trait_bound = match bound {
+            TypeParamBound::Trait(trait_bound) => trait_bound,
+            _ => continue,
+        };
+        let name = match trait_bound.path.segments.last() {
+            Some(segment) => segment.ident.to_string(),
+            None => continue,
+        };
+        if !matches!(trait_bound.modifier, TraitBoundModifier::None)
+            || NOT_OBJECT_SAFE.contains(&name.as_str())
+        {
+            let message = format!(
+                "fixtures returned as `impl Trait` are stored as `Box<dyn Trait>`, but `{}` can't be made into a trait object; return a concrete type instead",
+                quote!(#trait_bound).to_string().replace(' ', ""),
+            );
+            return Err(Error::new(trait_bound.span(), message));
+        }
+        send |= name == "Send";
+        sync |= name == "Sync";
+        if AUTO_TRAITS.contains(&name.as_str()) {
+            continue;
+        }
+        if let Some(principal) = principal.replace(trait_bound) {
+            let message = format!(
+                "fixtures returned as `impl Trait` are stored as `Box<dyn Trait>`, which can't combine `{}` and `{}`; return a concrete type instead",
+                quote!(#principal).to_string().replace(' ', ""),
+                quote!(#trait_bound).to_string().replace(' ', ""),
+            );
+            return Err(Error::new(trait_bound.span(), message));
+        }
+    }
+
+    let send = (!send).then(|| quote!(+ ::std::marker::Send));
+    let sync = (!sync).then(|| quote!(+ ::std::marker::Sync));
+    // Spanned at the `impl Trait`, so errors about the boxed value (e.g. it not
+    // being `Send`) point at the return type rather than the attribute
+    let boxed: Type = parse_quote_spanned!(target.span()=>
+        ::std::boxed::Box<dyn #bounds #send #sync>
     );
-    let func_sig = &func.sig;
+    *target = boxed.clone();
+    Ok(Some((boxed, nested)))
+}
 
-    let v = quote!(
-        #(#fixture_attrs)*
-        #[cfg(test)]
-        #fixture_vis static #fixture_ident: #found_crate::helpers::Lazy<&#fixture_ty> =
-            #found_crate::helpers::Lazy::new(|| #found_crate::helpers::unwrap(#func_ident));
-
-        #(#func_attrs)*
-        #[test]
-        #func_vis #func_sig {
-            static CELL: #found_crate::helpers::OnceCell<
-                std::result::Result<
-                    #func_out,
-                    &str,
-                    // std::sync::Mutex<Box<dyn std::any::Any + Send + 'static>>,
-                >
-            > = #found_crate::helpers::OnceCell::new();
-
-            let result = CELL.get_or_init(|| {
-                std::panic::catch_unwind(|| #func_body).map_err(|_| "panicked")
-                // std::panic::catch_unwind(|| #func_body).map_err(std::sync::Mutex::new)
-            });
+/// The name of the test generated for `func`, which is its `test_name` (or
+/// `func`) prefixed by `_NNN_` for an `order = NNN`
+fn test_ident(attr: &Attr, func: &Ident) -> Ident {
+    let name = attr.test_name.as_ref().unwrap_or(func);
+    match attr.order {
+        Some(order) => format_ident!("_{:03}_{}", order, name.unraw(), span = name.span()),
+        None => name.clone(),
+    }
+}
 
-            {
-                #[allow(unused_imports)]
-                use #found_crate::helpers::{Fixer, Fix};
+/// The name of the fixture declared by a bare `#[tested_fixture]` on `func`,
+/// which is `func` in upper snake case (keeping any leading underscores)
+fn static_name(func: &Ident) -> Ident {
+    let text = func.unraw().to_string();
+    let mut name = String::with_capacity(text.len());
+    let mut lower = false;
+    for c in text.chars() {
+        if c.is_uppercase() && lower {
+            name.push('_');
+        }
+        lower = c.is_lowercase() || c.is_ascii_digit();
+        name.extend(c.to_uppercase());
+    }
+    Ident::new(&name, func.span())
+}
+
+/// Moves every token onto `span`, so that errors involving them are reported
+/// there instead
+fn respan(tokens: proc_macro2::TokenStream, span: Span) -> proc_macro2::TokenStream {
+    tokens
+        .into_iter()
+        .map(|mut token| {
+            if let TokenTree::Group(group) = &token {
+                let mut respanned = Group::new(group.delimiter(), respan(group.stream(), span));
+                respanned.set_span(span);
+                return TokenTree::Group(respanned);
+            }
+            token.set_span(span);
+            token
+        })
+        .collect()
+}
+
+/// Parses the attributes listed in a `static_attrs(...)` or `test_attrs(...)`
+/// section, without their `#[...]`
+fn parse_section(input: ParseStream) -> Result<Vec<Meta>> {
+    let content;
+    parenthesized!(content in input);
+    let metas = Punctuated::<Meta, Token![,]>::parse_terminated(&content)?;
+    Ok(metas.into_iter().collect())
+}
+
+/// The key of an option, if it was specified
+fn key<T>(option: &Option<(Ident, T)>) -> Option<&Ident> {
+    option.as_ref().map(|(key, _)| key)
+}
+
+/// Rejects the first specified option in `conflicts`, which can't be
+/// combined with `with`
+fn reject_conflicts(conflicts: &[Option<&Ident>], with: &str) -> Result<()> {
+    match conflicts.iter().flatten().next() {
+        Some(key) => Err(Error::new(
+            key.span(),
+            format!("`{}` cannot be combined with {}", key, with),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Stores an option, rejecting duplicates
+fn set_option<T>(slot: &mut Option<(Ident, T)>, key: Ident, value: T) -> Result<()> {
+    if slot.is_some() {
+        return Err(Error::new(
+            key.span(),
+            format!("duplicate `{}` option", key),
+        ));
+    }
+    *slot = Some((key, value));
+    Ok(())
+}
+
+impl Parse for Attr {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut static_attrs = None;
+        let mut test_attrs = None;
+        let section = |input: ParseStream| {
+            let fork = input.fork();
+            let key = fork.call(Ident::parse_any).ok()?;
+            (key == "static_attrs" || key == "test_attrs").then_some(key)
+        };
+        let mut krate = None;
+        let mut export_to = None;
+        let mut exposed = None;
+        let mut expose_fn = None;
+        // Sections and the crate's path may precede the fixtures, as well as
+        // follow them like other options
+        loop {
+            if input.peek(Token![crate]) && input.peek2(Token![=]) {
+                let key = input.call(Ident::parse_any)?;
+                input.parse::<Token![=]>()?;
+                set_option(&mut krate, key, input.call(Path::parse_mod_style)?)?;
+            } else if section(input).is_some() && input.peek2(Paren) {
+                let key = input.call(Ident::parse_any)?;
+                let slot = match key == "static_attrs" {
+                    true => &mut static_attrs,
+                    false => &mut test_attrs,
+                };
+                set_option(slot, key, input.call(parse_section)?)?;
+            } else {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+
+        let mut fixtures: Fixtures = input.parse()?;
 
-                result.as_ref().map(|x|
-                    Fixer(x).fix().map(|x|
-                        Fixer(x).fix().map(|x|
-                            Fixer(x).fix().map(|x| Fixer(x).fix())
+        let mut runtime = None;
+        let mut block_on = None;
+        let mut flavor = None;
+        let mut worker_threads = None;
+        let mut timeout = None;
+        let mut error_display = None;
+        let mut full = None;
+        let mut pass_if = None;
+        let mut capture = None;
+        let mut error = None;
+        let mut store_as = None;
+        let mut map = None;
+        let mut no_catch_unwind = None;
+        let mut expect_panic = None;
+        let mut eager = None;
+        let mut rwlock = None;
+        let mut clone = None;
+        let mut fresh = None;
+        let mut once = None;
+        let mut arc = None;
+        let mut thread_local = None;
+        let mut local = None;
+        let mut releasable = None;
+        let mut retries = None;
+        let mut retry_delay = None;
+        let mut teardown = None;
+        let mut verify_injected = None;
+        let mut fallback = None;
+        let mut default = None;
+        let mut cfg = None;
+        let mut test_attr = None;
+        let mut no_test = None;
+        let mut test_name = None;
+        let mut order = None;
+        let mut strict = None;
+        let mut stack_size = None;
+        let mut invariant = None;
+        let mut verify_unchanged = None;
+        let mut requires_env: Vec<LitStr> = Vec::new();
+        let mut alternative = None;
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+
+            let key = input.call(Ident::parse_any)?;
+            if key == "static_attrs" || key == "test_attrs" {
+                if !input.peek(Paren) {
+                    return Err(Error::new(
+                        key.span(),
+                        format!(
+                            "`{}` requires a list of attributes, e.g. `{}(ignore)`",
+                            key, key
+                        ),
+                    ));
+                }
+                let slot = match key == "static_attrs" {
+                    true => &mut static_attrs,
+                    false => &mut test_attrs,
+                };
+                set_option(slot, key, input.call(parse_section)?)?;
+                continue;
+            }
+            if key == "cfg" || key == "exposed" {
+                if !input.peek(Paren) {
+                    let feature = match key == "cfg" {
+                        true => "postgres",
+                        false => "test-fixtures",
+                    };
+                    return Err(Error::new(
+                        key.span(),
+                        format!(
+                            "`{}` requires a predicate, e.g. `{}(feature = \"{}\")`",
+                            key, key, feature,
+                        ),
+                    ));
+                }
+                let content;
+                parenthesized!(content in input);
+                let slot = match key == "cfg" {
+                    true => &mut cfg,
+                    false => &mut exposed,
+                };
+                set_option(slot, key, content.parse::<proc_macro2::TokenStream>()?)?;
+                continue;
+            }
+            if key == "alternative" {
+                if !input.peek(Paren) {
+                    return Err(Error::new(
+                        key.span(),
+                        "`alternative` requires a `cfg` and a function, e.g. `alternative(cfg(miri), cheap_builder)`",
+                    ));
+                }
+                let content;
+                parenthesized!(content in input);
+                set_option(&mut alternative, key, content.parse::<Alternative>()?)?;
+                continue;
+            }
+            if !input.peek(Token![=]) {
+                match key.to_string().as_str() {
+                    "error_display" => set_option(&mut error_display, key, ())?,
+                    "full" => set_option(&mut full, key, ())?,
+                    "no_catch_unwind" => set_option(&mut no_catch_unwind, key, ())?,
+                    "expect_panic" => set_option(&mut expect_panic, key, ())?,
+                    "eager" => set_option(&mut eager, key, ())?,
+                    "rwlock" => set_option(&mut rwlock, key, ())?,
+                    "clone" => set_option(&mut clone, key, ())?,
+                    "fresh" => set_option(&mut fresh, key, ())?,
+                    "once" => set_option(&mut once, key, ())?,
+                    "arc" => set_option(&mut arc, key, ())?,
+                    "thread_local" => set_option(&mut thread_local, key, ())?,
+                    "local" => set_option(&mut local, key, ())?,
+                    "releasable" => set_option(&mut releasable, key, ())?,
+                    "default" => set_option(&mut default, key, ())?,
+                    "no_test" => set_option(&mut no_test, key, ())?,
+                    "strict" => set_option(&mut strict, key, ())?,
+                    "verify_unchanged" => {
+                        let span = key.span();
+                        set_option(&mut verify_unchanged, key, VerifyUnchanged::Hash(span))?
+                    }
+                    k if VALUE_OPTIONS.contains(&k) => {
+                        return Err(Error::new(
+                            key.span(),
+                            format!("`{}` requires a value", key),
+                        ))
+                    }
+                    _ => return Err(Error::new(key.span(), format!("unknown option `{}`", key))),
+                }
+                continue;
+            }
+            input.parse::<Token![=]>()?;
+            match key.to_string().as_str() {
+                "runtime" => set_option(&mut runtime, key, input.parse()?)?,
+                "block_on" => set_option(&mut block_on, key, input.parse()?)?,
+                "flavor" => set_option(&mut flavor, key, input.parse()?)?,
+                "timeout" => set_option(&mut timeout, key, input.parse()?)?,
+                "worker_threads" => {
+                    let lit: LitInt = input.parse()?;
+                    match lit.base10_parse()? {
+                        0 => {
+                            return Err(Error::new(
+                                lit.span(),
+                                "`worker_threads` must be at least 1",
+                            ))
+                        }
+                        n => set_option(&mut worker_threads, key, (lit.span(), n))?,
+                    }
+                }
+                "pass_if" => set_option(&mut pass_if, key, input.parse()?)?,
+                "capture" => set_option(&mut capture, key, input.parse()?)?,
+                "error" => set_option(&mut error, key, input.parse()?)?,
+                "store_as" => set_option(&mut store_as, key, input.parse()?)?,
+                "map" => set_option(&mut map, key, input.parse()?)?,
+                "teardown" => set_option(&mut teardown, key, input.parse()?)?,
+                "stack_size" => set_option(&mut stack_size, key, input.parse()?)?,
+                "invariant" => set_option(&mut invariant, key, input.parse()?)?,
+                // Repeated for each variable, rather than rejected as a duplicate
+                "requires_env" => {
+                    let lit: LitStr = input.parse()?;
+                    if lit.value().is_empty() {
+                        return Err(Error::new(
+                            lit.span(),
+                            "`requires_env` requires a variable name, e.g. `requires_env = \"DATABASE_URL\"`",
+                        ));
+                    }
+                    if requires_env.iter().any(|var| var.value() == lit.value()) {
+                        return Err(Error::new(
+                            lit.span(),
+                            format!("duplicate `requires_env` variable `{}`", lit.value()),
+                        ));
+                    }
+                    requires_env.push(lit);
+                }
+                "verify_unchanged" => {
+                    let fingerprint = VerifyUnchanged::Fingerprint(Box::new(input.parse()?));
+                    set_option(&mut verify_unchanged, key, fingerprint)?
+                }
+                "verify_injected" => set_option(&mut verify_injected, key, input.parse()?)?,
+                "fallback" => set_option(&mut fallback, key, input.parse()?)?,
+                "test_attr" => set_option(&mut test_attr, key, input.call(Path::parse_mod_style)?)?,
+                "crate" => set_option(&mut krate, key, input.call(Path::parse_mod_style)?)?,
+                "expose_fn" => set_option(&mut expose_fn, key, input.call(Ident::parse_any)?)?,
+                "test_name" => {
+                    let lit: LitStr = input.parse()?;
+                    let ident = lit.parse::<Ident>().map_err(|_| {
+                        Error::new(
+                            lit.span(),
+                            "`test_name` must be a valid identifier, e.g. `test_name = \"fixture_step_1\"`",
                         )
-                    )
-                )
+                    })?;
+                    set_option(&mut test_name, key, ident)?
+                }
+                "export_to" => {
+                    let path = input.call(Path::parse_mod_style).map_err(|e| {
+                        Error::new(
+                            e.span(),
+                            "`export_to` requires a module path, e.g. `export_to = crate::test_fixtures`",
+                        )
+                    })?;
+                    set_option(&mut export_to, key, path)?
+                }
+                "order" => {
+                    let lit: LitInt = input.parse()?;
+                    match lit.base10_parse()? {
+                        n if n > 999 => {
+                            return Err(Error::new(lit.span(), "`order` must be at most 999"))
+                        }
+                        n => set_option(&mut order, key, n)?,
+                    }
+                }
+                "retries" => {
+                    let lit: LitInt = input.parse()?;
+                    match lit.base10_parse()? {
+                        0 => return Err(Error::new(lit.span(), "`retries` must be at least 1")),
+                        n => set_option(&mut retries, key, (lit.span(), n))?,
+                    }
+                }
+                "retry_delay" => {
+                    let lit: LitStr = input.parse()?;
+                    match parse_duration(&lit.value()) {
+                        Some(delay) => set_option(&mut retry_delay, key, delay)?,
+                        None => return Err(Error::new(lit.span(), INVALID_DURATION)),
+                    }
+                }
+                k if FLAG_OPTIONS.contains(&k) => {
+                    return Err(Error::new(
+                        key.span(),
+                        format!("`{}` doesn't take a value", key),
+                    ))
+                }
+                _ => return Err(Error::new(key.span(), format!("unknown option `{}`", key))),
             }
         }
 
-    );
+        let mut runtime = match (runtime, block_on) {
+            (Some(_), Some((key, _))) => {
+                return Err(Error::new(
+                    key.span(),
+                    "`block_on` cannot be combined with `runtime`",
+                ))
+            }
+            (runtime, block_on) => runtime
+                .map(|(_, runtime)| runtime)
+                .or_else(|| block_on.map(|(_, path)| Runtime::Custom(path))),
+        };
+        match (&mut runtime, flavor) {
+            (Some(Runtime::Tokio { flavor, .. }), Some((_, v))) => *flavor = v,
+            (_, Some((key, _))) => {
+                return Err(Error::new(
+                    key.span(),
+                    "`flavor` requires `runtime = \"tokio\"`",
+                ))
+            }
+            (_, None) => {}
+        }
+        match (&mut runtime, worker_threads) {
+            (
+                Some(Runtime::Tokio {
+                    flavor: Flavor::MultiThread { worker_threads },
+                    ..
+                }),
+                Some((_, (_, v))),
+            ) => *worker_threads = Some(v),
+            (Some(Runtime::Tokio { .. }), Some((_, (span, _)))) => {
+                return Err(Error::new(
+                    span,
+                    "`worker_threads` requires `flavor = \"multi_thread\"`",
+                ))
+            }
+            (_, Some((key, _))) => {
+                return Err(Error::new(
+                    key.span(),
+                    "`worker_threads` requires `runtime = \"tokio\"`",
+                ))
+            }
+            (_, None) => {}
+        }
+
+        match (&full, &error_display, &pass_if) {
+            (Some(_), Some((key, _)), _) => {
+                return Err(Error::new(
+                    key.span(),
+                    "`error_display` cannot be combined with `full`, which never reports errors",
+                ))
+            }
+            (None, _, Some((key, _))) => {
+                return Err(Error::new(key.span(), "`pass_if` requires `full`"))
+            }
+            _ => {}
+        }
+        match (&capture, &full, &error_display, &store_as, &map) {
+            (Some((_, Capture::Err)), Some((key, _)), _, _, _)
+            | (Some((_, Capture::Err)), _, Some((key, _)), _, _)
+            | (Some((_, Capture::Err)), _, _, Some((key, _)), _)
+            | (Some((_, Capture::Err)), _, _, _, Some((key, _))) => {
+                return Err(Error::new(
+                    key.span(),
+                    format!("`{}` cannot be combined with `capture = \"err\"`", key),
+                ))
+            }
+            _ => {}
+        }
+
+        if expect_panic.is_some() {
+            let conflicts = [
+                key(&full),
+                key(&error_display),
+                key(&no_catch_unwind),
+                key(&capture),
+                key(&store_as),
+                key(&map),
+            ];
+            reject_conflicts(&conflicts, "`expect_panic`")?;
+        }
+
+        // These fixtures are exposed through their own handles rather than
+        // statics of the stored type
+        let handles = [
+            key(&clone),
+            key(&fresh),
+            key(&once),
+            key(&arc),
+            key(&thread_local),
+            key(&local),
+            key(&releasable),
+        ];
+        if let (Fixtures::Tuple(..), Some(key)) = (&fixtures, handles.iter().flatten().next()) {
+            return Err(Error::new(
+                key.span(),
+                format!("`{}` isn't supported for tuple fixtures", key),
+            ));
+        }
+        let conversions = [
+            key(&full),
+            key(&capture),
+            key(&store_as),
+            key(&map),
+            key(&expect_panic),
+        ];
+        if fresh.is_some() {
+            let conflicts = [
+                key(&eager),
+                key(&clone),
+                key(&error_display),
+                key(&error),
+                key(&teardown),
+                key(&verify_injected),
+                key(&strict),
+                key(&invariant),
+                key(&verify_unchanged),
+            ];
+            reject_conflicts(&conflicts, "`fresh`")?;
+            reject_conflicts(&conversions, "`fresh`")?;
+        }
+        if let (None, Some((key, _))) = (fixtures.mutability(), &rwlock) {
+            return Err(Error::new(
+                key.span(),
+                "`rwlock` requires a `mut` fixture, e.g. `mut CONFIG`",
+            ));
+        }
+        let thread_local_conflicts = [
+            key(&clone),
+            key(&fresh),
+            key(&once),
+            key(&arc),
+            key(&eager),
+            key(&error_display),
+            key(&error),
+            key(&teardown),
+            key(&verify_injected),
+            key(&strict),
+            key(&invariant),
+            key(&verify_unchanged),
+        ];
+        // `local` fixtures must only be touched by the thread which built them
+        let local_conflicts = [
+            key(&clone),
+            key(&fresh),
+            key(&once),
+            key(&arc),
+            key(&thread_local),
+            key(&eager),
+            key(&teardown),
+            key(&verify_injected),
+            key(&invariant),
+            key(&verify_unchanged),
+        ];
+        let releasable_conflicts = [
+            key(&clone),
+            key(&fresh),
+            key(&once),
+            key(&arc),
+            key(&thread_local),
+            key(&local),
+            key(&teardown),
+            key(&invariant),
+            key(&verify_unchanged),
+        ];
+        for (mode, conflicts) in [
+            (
+                &once,
+                &[
+                    key(&clone),
+                    key(&fresh),
+                    key(&teardown),
+                    key(&invariant),
+                    key(&verify_unchanged),
+                ][..],
+            ),
+            (
+                &arc,
+                &[
+                    key(&clone),
+                    key(&fresh),
+                    key(&once),
+                    key(&invariant),
+                    key(&verify_unchanged),
+                ][..],
+            ),
+            (&thread_local, &thread_local_conflicts[..]),
+            (&local, &local_conflicts[..]),
+            (&releasable, &releasable_conflicts[..]),
+        ]
+        .iter()
+        {
+            let mode = match mode {
+                Some((mode, _)) => format!("`{}`", mode),
+                None => continue,
+            };
+            if let Some(mutability) = fixtures.mutability() {
+                return Err(Error::new(
+                    mutability.span,
+                    format!("`mut` cannot be combined with {}", mode),
+                ));
+            }
+            reject_conflicts(&conversions, &mode)?;
+            reject_conflicts(conflicts, &mode)?;
+        }
+        if fixtures.mutability().is_some() {
+            reject_conflicts(&conversions, "a `mut` fixture")?;
+            let conflicts = [
+                key(&clone),
+                key(&fresh),
+                key(&invariant),
+                key(&verify_unchanged),
+            ];
+            reject_conflicts(&conflicts, "a `mut` fixture")?;
+        }
+        if let Fixtures::Tuple(..) = fixtures {
+            if let Some(key) = [key(&invariant), key(&verify_unchanged)]
+                .iter()
+                .flatten()
+                .next()
+            {
+                return Err(Error::new(
+                    key.span(),
+                    format!("`{}` isn't supported for tuple fixtures", key),
+                ));
+            }
+        }
+        if !requires_env.is_empty() {
+            // Skipping unwinds through the fixture function, and must reach
+            // its test
+            let conflicts = [
+                key(&fresh),
+                key(&thread_local),
+                key(&no_catch_unwind),
+                key(&expect_panic),
+            ];
+            reject_conflicts(&conflicts, "`requires_env`")?;
+        }
+        if let (None, Some((key, _))) = (&retries, &retry_delay) {
+            return Err(Error::new(key.span(), "`retry_delay` requires `retries`"));
+        }
+        if retries.is_some() {
+            let conflicts = [key(&fresh), key(&thread_local)];
+            reject_conflicts(&conflicts, "`retries`")?;
+        }
+        if no_test.is_some() {
+            let conflicts = [
+                key(&test_attr),
+                key(&test_attrs),
+                key(&test_name),
+                key(&order),
+                key(&strict),
+                key(&default),
+                key(&verify_injected),
+            ];
+            reject_conflicts(&conflicts, "`no_test`")?;
+        }
+        if strict.is_some() {
+            // The fixture's test doesn't initialize the value dependents see
+            reject_conflicts(&[key(&default)], "`strict`")?;
+        }
+        if let (None, Some((key, _))) = (&fallback, &default) {
+            return Err(Error::new(key.span(), "`default` requires `fallback`"));
+        }
+        if let (None, Some((key, _))) = (&fallback, &cfg) {
+            return Err(Error::new(
+                key.span(),
+                "`cfg` requires `fallback`, otherwise add `#[cfg(...)]` to the function",
+            ));
+        }
+        if let Some((key, _)) = &fallback {
+            if let Fixtures::Tuple(..) = fixtures {
+                return Err(Error::new(
+                    key.span(),
+                    "`fallback` isn't supported for tuple fixtures",
+                ));
+            }
+            if fixtures.mutability().is_some() {
+                return Err(Error::new(
+                    key.span(),
+                    "`fallback` cannot be combined with a `mut` fixture",
+                ));
+            }
+            reject_conflicts(&handles, "`fallback`")?;
+        }
+        match (&store_as, &map) {
+            (Some(_), Some((key, _))) => {
+                return Err(Error::new(
+                    key.span(),
+                    "`map` cannot be combined with `store_as`",
+                ))
+            }
+            (None, Some((key, _))) if fixtures.ty().is_none() => {
+                return Err(Error::new(
+                    key.span(),
+                    "`map` requires the fixture's type, e.g. `TOKEN: String`",
+                ))
+            }
+            _ => {}
+        }
 
-    v.into()
+        let static_attrs: Vec<Attribute> = static_attrs
+            .map_or_else(Vec::new, |(_, metas)| metas)
+            .iter()
+            .map(|meta| parse_quote!(#[#meta]))
+            .collect();
+        for binding in fixtures.bindings_mut() {
+            // Private fixtures couldn't be re-exported
+            if let (Some(_), Visibility::Inherited) = (&export_to, &binding.vis) {
+                let message = format!(
+                    "`export_to` requires a fixture visible outside its module, e.g. `pub(crate) {}`",
+                    binding.ident,
+                );
+                return Err(Error::new(binding.ident.span(), message));
+            }
+            binding.attrs.extend(static_attrs.iter().cloned());
+            // Fixtures may be named like variables rather than statics
+            if binding
+                .ident
+                .unraw()
+                .to_string()
+                .chars()
+                .any(char::is_lowercase)
+            {
+                binding
+                    .attrs
+                    .push(parse_quote!(#[allow(non_upper_case_globals)]));
+            }
+        }
+
+        Ok(Attr {
+            fixtures,
+            runtime,
+            timeout: timeout.map(|(_, timeout)| timeout),
+            error_display: error_display.is_some(),
+            full: full.is_some(),
+            pass_if: pass_if.map(|(_, pass_if)| pass_if),
+            capture: capture.map_or(Capture::Ok, |(_, capture)| capture),
+            error: error.map(|(_, error)| error),
+            store_as: store_as.map(|(_, store_as)| store_as),
+            map: map.map(|(_, map)| map),
+            no_catch_unwind: no_catch_unwind.map(|(key, _)| key.span()),
+            expect_panic: expect_panic.is_some(),
+            eager: eager.map(|(key, _)| key.span()),
+            rwlock: rwlock.is_some(),
+            clone: clone.map(|(key, _)| key.span()),
+            fresh: fresh.map(|(key, _)| key.span()),
+            once: once.map(|(key, _)| key.span()),
+            arc: arc.map(|(key, _)| key.span()),
+            thread_local: thread_local.map(|(key, _)| key.span()),
+            local: local.map(|(key, _)| key.span()),
+            releasable: releasable.map(|(key, _)| key.span()),
+            retries: retries.map(|(_, retries)| retries),
+            retry_delay: retry_delay.map(|(_, delay)| delay),
+            teardown: teardown.map(|(_, teardown)| teardown),
+            verify_injected: verify_injected.map(|(_, verify)| verify),
+            fallback: fallback.map(|(_, fallback)| fallback),
+            default: default.is_some(),
+            cfg: cfg.map(|(_, cfg)| cfg),
+            test_attr: test_attr.map(|(_, path)| path),
+            no_test: no_test.is_some(),
+            test_attrs: test_attrs.map_or_else(Vec::new, |(_, metas)| metas),
+            krate: krate.map(|(_, path)| path),
+            export_to: export_to.map(|(_, path)| path),
+            exposed: exposed.map(|(_, exposed)| exposed),
+            expose_fn: expose_fn.map(|(_, ident)| ident),
+            test_name: test_name.map(|(_, ident)| ident),
+            order: order.map(|(_, order)| order),
+            strict: strict.is_some(),
+            stack_size: stack_size.map(|(_, stack_size)| stack_size),
+            invariant: invariant.map(|(_, invariant)| invariant),
+            verify_unchanged: verify_unchanged.map(|(_, verify)| verify),
+            requires_env,
+            alternative: alternative.map(|(_, alternative)| alternative),
+        })
+    }
+}
+
+fn tested_fixture_helper(attr: TokenStream, item: TokenStream, doctest: bool) -> TokenStream {
+    let func = match item_fn(parse_macro_input!(item as Item)) {
+        Ok(func) => func,
+        Err(e) => return e.into_compile_error().into(),
+    };
+    let attr = if attr.is_empty() {
+        let ident = static_name(&func.sig.ident);
+        parse_quote!(#ident)
+    } else {
+        parse_macro_input!(attr as Attr)
+    };
+
+    // Rooted with `::`, so that the crate is found even without the extern
+    // prelude (e.g. under `#![no_implicit_prelude]`) or when shadowed by a module
+    let extern_crate = |name: &str| {
+        let name = Ident::new(name, Span::call_site());
+        parse_quote!(::#name)
+    };
+    let found_crate: Path = match (&attr.krate, crate_name("tested-fixture")) {
+        (Some(path), _) => path.clone(),
+        (None, Ok(FoundCrate::Name(name))) => extern_crate(&name),
+        (None, Ok(FoundCrate::Itself)) if doctest => extern_crate("tested_fixture"),
+        (None, Ok(FoundCrate::Itself)) => Ident::from(<Token![crate]>::default()).into(),
+        (None, Err(_)) => {
+            return Error::new(
+                Span::call_site(),
+                "`tested-fixture` isn't a dependency in `Cargo.toml`; specify the path it's re-exported from with `crate = path`",
+            )
+            .into_compile_error()
+            .into()
+        }
+    };
+
+    let cfgs = item_cfgs(&func.attrs);
+    let test_ident = test_ident(&attr, &func.sig.ident);
+    expand(&found_crate, attr, func)
+        .and_then(|tokens| propagate_cfgs(tokens, &test_ident, &cfgs))
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Whether `attr` configures lints, such as `#[allow(...)]`
+fn is_lint_attr(attr: &Attribute) -> bool {
+    ["allow", "warn", "deny", "forbid", "expect"]
+        .iter()
+        .any(|lint| attr.path().is_ident(lint))
+}
+
+/// The function the attribute is applied to, rejecting any other items
+fn item_fn(item: Item) -> Result<ItemFn> {
+    const MESSAGE: &str = "`tested_fixture` can only be applied to functions";
+    let (span, suggestion) = match item {
+        Item::Fn(func) => return Ok(func),
+        Item::Static(item) => (
+            item.static_token.span,
+            "; to share a value between tests, declare the static directly",
+        ),
+        Item::Const(item) => (
+            item.const_token.span,
+            "; to share a constant between tests, declare it directly",
+        ),
+        Item::Impl(item) => (
+            item.impl_token.span,
+            ", so apply it to a function outside of the `impl` block instead",
+        ),
+        Item::Mod(item) => (
+            item.mod_token.span,
+            ", so apply it to each function in the module instead",
+        ),
+        item => (item.span(), ""),
+    };
+    Err(Error::new(span, format!("{}{}", MESSAGE, suggestion)))
+}
+
+/// The attributes of the function which can compile it out, namely `cfg` and
+/// any `cfg_attr` which only expands to `cfg`
+fn item_cfgs(attrs: &[Attribute]) -> Vec<Attribute> {
+    let only_cfgs = |attr: &Attribute| {
+        let metas = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated);
+        metas.map_or(false, |metas| {
+            metas.len() > 1 && metas.iter().skip(1).all(|meta| meta.path().is_ident("cfg"))
+        })
+    };
+    attrs
+        .iter()
+        .filter(|attr| {
+            attr.path().is_ident("cfg") || (attr.path().is_ident("cfg_attr") && only_cfgs(attr))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Adds `cfgs` to every item generated alongside the test function `func`,
+/// so the whole fixture is compiled out along with it
+fn propagate_cfgs(
+    tokens: proc_macro2::TokenStream,
+    func: &Ident,
+    cfgs: &[Attribute],
+) -> Result<proc_macro2::TokenStream> {
+    if cfgs.is_empty() {
+        return Ok(tokens);
+    }
+    let mut file: File = syn::parse2(tokens)?;
+    for item in &mut file.items {
+        let attrs = match item {
+            Item::Fn(item) if item.sig.ident == *func => continue,
+            Item::Fn(item) => &mut item.attrs,
+            Item::Static(item) => &mut item.attrs,
+            Item::Const(item) => &mut item.attrs,
+            Item::Macro(item) => &mut item.attrs,
+            Item::Struct(item) => &mut item.attrs,
+            Item::Impl(item) => &mut item.attrs,
+            Item::Use(item) => &mut item.attrs,
+            _ => continue,
+        };
+        attrs.splice(0..0, cfgs.iter().cloned());
+    }
+    Ok(quote!(#file))
+}
+
+/// Known attributes which generate their own (async) test harness
+const ASYNC_TEST_ATTRS: &[(&str, &str)] = &[
+    ("tokio", "`runtime = \"tokio\"`"),
+    ("async_std", "`runtime = \"async-std\"`"),
+    ("actix_rt", "`block_on = ...`"),
+    ("actix_web", "`block_on = ...`"),
+    ("smol_potat", "`block_on = ...`"),
+];
+
+/// Rejects attributes which would generate a second test harness
+fn check_func_attrs(attrs: &[Attribute]) -> Result<()> {
+    for attr in attrs {
+        let path = attr.path();
+        if path.is_ident("should_panic") {
+            return Err(Error::new_spanned(
+                attr,
+                "`#[should_panic]` cannot be combined with `tested_fixture`, which catches panics; use `expect_panic` instead",
+            ));
+        }
+        if path.segments.len() != 2 || path.segments[1].ident != "test" {
+            continue;
+        }
+        let krate = &path.segments[0].ident;
+        if let Some((_, option)) = ASYNC_TEST_ATTRS.iter().find(|(name, _)| krate == name) {
+            return Err(Error::new_spanned(
+                attr,
+                format!(
+                    "`#[{}::test]` cannot be combined with `tested_fixture`, which generates its own test; use {} instead",
+                    krate, option,
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn expand(
+    found_crate: &Path,
+    mut attr: Attr,
+    mut func: ItemFn,
+) -> Result<proc_macro2::TokenStream> {
+    check_func_attrs(&func.attrs)?;
+    // A `#[test]` left over from before the function became a fixture would
+    // duplicate the generated one
+    func.attrs
+        .retain(|attr| !matches!(&attr.meta, Meta::Path(path) if path.is_ident("test")));
+    if !func.sig.inputs.is_empty() {
+        return Err(Error::new(
+            func.sig.paren_token.span.join(),
+            "`tested_fixture` functions cannot take parameters; access other fixtures via their statics inside the body",
+        ));
+    }
+    let generics = &func.sig.generics;
+    if !generics.params.is_empty() {
+        let message = match generics.lifetimes().count() == generics.params.len() {
+            true => "`tested_fixture` functions cannot have lifetime parameters, since their fixtures are `'static`",
+            false => "`tested_fixture` functions cannot be generic, since each fixture is a single static; call a generic function from the body instead, specifying the fixture's type as `IDENT: Type` if needed",
+        };
+        return Err(Error::new_spanned(generics, message));
+    }
+    if let ReturnType::Type(_, ty) = &func.sig.output {
+        if let Some((borrowed, owned)) = borrowed_type(ty) {
+            let message = format!(
+                "fixtures must own their data or borrow only 'static data; consider returning an owned `{}`",
+                owned,
+            );
+            return Err(Error::new_spanned(borrowed, message));
+        }
+    }
+
+    // The statics are what dependents use, so are documented like the
+    // function unless given their own docs
+    let docs: Vec<_> = func
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .collect();
+    if !docs.is_empty() {
+        let intro = format!("Fixture produced by `{}`:", func.sig.ident.unraw());
+        for binding in attr.fixtures.bindings_mut() {
+            if !binding.attrs.iter().any(|attr| attr.path().is_ident("doc")) {
+                binding.attrs.push(parse_quote!(#[doc = #intro]));
+                binding.attrs.push(parse_quote!(#[doc = ""]));
+                binding.attrs.extend(docs.iter().map(|&doc| doc.clone()));
+            }
+        }
+    }
+
+    // `_` stands for the `Ok` type of the returned `Result`, or the element of
+    // a returned tuple which is inferred anyway
+    let tuple = matches!(attr.fixtures, Fixtures::Tuple(..));
+    for binding in attr.fixtures.bindings_mut() {
+        let span = match &binding.ty {
+            Some(Type::Infer(infer)) => infer.underscore_token.span,
+            _ => continue,
+        };
+        binding.ty = match &func.sig.output {
+            _ if tuple => None,
+            ReturnType::Type(_, ty) if result_type_arg(ty, 0).is_some() => {
+                result_type_arg(ty, 0).cloned()
+            }
+            _ => {
+                return Err(Error::new(
+                    span,
+                    "`_` can only be used when the function returns a `Result`, specify the fixture's type instead",
+                ))
+            }
+        };
+    }
+
+    // Comes after the function's own attributes, so that attribute macros
+    // among them are expanded first
+    let test_attr = match &attr.test_attr {
+        Some(path) => quote!(#[#path]),
+        None => quote!(#[test]),
+    };
+    let test_attrs = &attr.test_attrs;
+    // Fixtures are only compiled for tests, unless they're `exposed` to other
+    // crates' tests
+    let fixture_cfg = match &attr.exposed {
+        Some(exposed) => quote!(#[cfg(any(test, #exposed))]),
+        None => quote!(#[cfg(test)]),
+    };
+
+    // When the test is renamed, the body stays in a function of the original name
+    // for the fixture to call, leaving the renamed function as just the test
+    let renamed = attr.test_name.is_some() || attr.order.is_some();
+    let body_fn = renamed.then(|| {
+        let lint_attrs = func.attrs.iter().filter(|attr| is_lint_attr(attr));
+        let cfg = attr.cfg.as_ref().map(|cfg| quote!(#[cfg(#cfg)]));
+        let vis = &func.vis;
+        let sig = &func.sig;
+        let block = &func.block;
+        let ident = &sig.ident;
+        let call = match sig.asyncness {
+            Some(_) => quote!(#ident().await),
+            None => quote!(#ident()),
+        };
+        let body_fn = quote!(
+            #fixture_cfg
+            #cfg
+            #(#lint_attrs)*
+            #vis #sig #block
+        );
+        func.block = parse_quote!({ #call });
+        body_fn
+    });
+
+    // The body is moved into the companion function, which the fixture then
+    // calls, so the two can't drift apart
+    let companion = attr.expose_fn.as_ref().map(|ident| {
+        let lint_attrs = func.attrs.iter().filter(|attr| is_lint_attr(attr));
+        let cfg = attr.cfg.as_ref().map(|cfg| quote!(#[cfg(#cfg)]));
+        let mut sig = func.sig.clone();
+        sig.ident = ident.clone();
+        let block = &func.block;
+        let call = match sig.asyncness {
+            Some(_) => quote!(#ident().await),
+            None => quote!(#ident()),
+        };
+        let doc = format!(
+            "Runs the function of fixture `{}`, without caching its value",
+            attr.fixtures.name(),
+        );
+        let companion = quote!(
+            #fixture_cfg
+            #cfg
+            #[doc = #doc]
+            #(#lint_attrs)*
+            pub #sig #block
+        );
+        func.block = parse_quote!({ #call });
+        companion
+    });
+
+    let func_attrs = &func.attrs;
+    let func_vis = &func.vis;
+    let func_ident = func.sig.ident.clone();
+    func.sig.ident = test_ident(&attr, &func_ident);
+    // Checks are inserted as statements of the body, rather than wrapping it in
+    // another block, so that lints like `unused_braces` don't fire on its braces
+    let mut func_block = func.block.clone();
+    // Both functions are always compiled, so that their return types are
+    // checked against each other (reported on the attribute) under any cfg
+    if let Some(Alternative {
+        predicate,
+        function,
+    }) = &attr.alternative
+    {
+        let run = quote_spanned!(function.span()=>
+            return #found_crate::helpers::alternative(#function);
+        );
+        func_block.stmts.insert(
+            0,
+            parse_quote!(if ::std::cfg!(#predicate) {
+                #run
+            }),
+        );
+    }
+    let mut func_body = func_block.clone();
+    let func_async = func.sig.asyncness.take().is_some();
+    let block_on = match (func_async, &attr.runtime) {
+        (_, None) => quote!(|init| #found_crate::helpers::block_on(init())),
+        (true, Some(Runtime::Tokio { flavor, .. })) => {
+            let flavor = match flavor {
+                Flavor::CurrentThread => quote!(#found_crate::helpers::TokioFlavor::CurrentThread),
+                Flavor::MultiThread { worker_threads } => {
+                    let worker_threads = match worker_threads {
+                        Some(n) => quote!(::std::option::Option::Some(#n)),
+                        None => quote!(::std::option::Option::None),
+                    };
+                    quote!(#found_crate::helpers::TokioFlavor::MultiThread {
+                        worker_threads: #worker_threads,
+                    })
+                }
+            };
+            quote!(|init| #found_crate::helpers::block_on_tokio(#flavor, init))
+        }
+        (true, Some(Runtime::TokioLocal(_))) => {
+            quote!(|init| #found_crate::helpers::block_on_tokio_local(init))
+        }
+        (true, Some(Runtime::AsyncStd(_))) => {
+            quote!(|init| #found_crate::helpers::block_on_async_std(init()))
+        }
+        (true, Some(Runtime::Custom(path))) => {
+            quote_spanned!(path.span()=> |init| #found_crate::helpers::block_on_with(#path, init))
+        }
+        (false, Some(runtime)) => {
+            let message = format!("`{}` requires an `async fn`", runtime.option());
+            return Err(Error::new(runtime.span(), message));
+        }
+    };
+    // Synchronous bodies are instead run on a helper thread below
+    let timeout = match (func_async, &attr.timeout) {
+        (false, _) | (_, None) => quote!(#found_crate::helpers::no_timeout),
+        (true, Some(Timeout { text, duration, .. })) => {
+            let (secs, nanos) = (duration.as_secs(), duration.subsec_nanos());
+            let message = format!("{} timed out after {}", attr.fixtures.name(), text);
+            quote!(|body| #found_crate::helpers::timeout(
+                #found_crate::helpers::Duration::new(#secs, #nanos),
+                #message,
+                body,
+            ))
+        }
+    };
+    if let (true, Some(span)) = (func_async, attr.no_catch_unwind) {
+        return Err(Error::new(
+            span,
+            "`no_catch_unwind` isn't supported for an `async fn`",
+        ));
+    }
+    if let (true, Some((span, _))) = (func_async, attr.retries) {
+        return Err(Error::new(
+            span,
+            "`retries` isn't supported for an `async fn`",
+        ));
+    }
+    // The function may run on any of the executor's threads
+    if let (true, Some(span)) = (func_async, attr.local) {
+        return Err(Error::new(
+            span,
+            "`local` isn't supported for an `async fn`",
+        ));
+    }
+    let output_span = match &func.sig.output {
+        ReturnType::Default => func_ident.span(),
+        ReturnType::Type(_, ty) => ty.span(),
+    };
+    let mut func_out = match replace(&mut func.sig.output, ReturnType::Default) {
+        ReturnType::Default => Type::Tuple(TypeTuple {
+            paren_token: Default::default(),
+            elems: Default::default(),
+        }),
+        ReturnType::Type(_, ty) => *ty,
+    };
+
+    // Values stored as another type are converted by running the body in a
+    // nested function. Opaque types can't be named by the statics, so are
+    // likewise boxed unless stored as something else.
+    let opaque_out = func_out.clone();
+    // `mut` and `once` fixtures wrap the value they store
+    let inner = attr
+        .fixtures
+        .ty()
+        .or_else(|| result_type_arg(&func_out, 0).cloned())
+        .unwrap_or_else(|| func_out.clone());
+    let storage: Option<Type> = match (attr.fixtures.mutability(), attr.once) {
+        (Some(mutability), _) if attr.rwlock => {
+            Some(parse_quote_spanned!(mutability.span=> ::std::sync::RwLock<#inner>))
+        }
+        (Some(mutability), _) => {
+            Some(parse_quote_spanned!(mutability.span=> ::std::sync::Mutex<#inner>))
+        }
+        (None, Some(span)) => {
+            Some(parse_quote_spanned!(span=> #found_crate::helpers::TakeCell<#inner>))
+        }
+        (None, None) => match (attr.arc, attr.local, attr.releasable) {
+            (Some(span), _, _) => Some(parse_quote_spanned!(span=> ::std::sync::Arc<#inner>)),
+            (None, Some(span), _) => {
+                Some(parse_quote_spanned!(span=> #found_crate::helpers::UnsyncCell<#inner>))
+            }
+            (None, None, Some(span)) => {
+                Some(parse_quote_spanned!(span=> #found_crate::helpers::ReleaseCell<#inner>))
+            }
+            (None, None, None) => None,
+        },
+    };
+    let stored = match (storage.as_ref().or(attr.store_as.as_ref()), &attr.map) {
+        (Some(store_as), _) => {
+            let span = store_as.span();
+            Some((
+                store_as.clone(),
+                span,
+                quote_spanned!(span=> ::std::convert::Into::into(v)),
+            ))
+        }
+        (None, Some(map)) => {
+            let span = map.span();
+            Some((
+                attr.fixtures.ty().unwrap(),
+                span,
+                quote_spanned!(span=> #found_crate::helpers::map(v, #map)),
+            ))
+        }
+        (None, None) => None,
+    };
+    let conversion = match stored {
+        Some((target, span, convert)) => {
+            let nested = !attr.full && result_ok_type_mut(&mut func_out).is_some();
+            match result_ok_type_mut(&mut func_out) {
+                Some(ok) if nested => *ok = target.clone(),
+                _ => func_out = target.clone(),
+            }
+            Some((target, nested, span, convert))
+        }
+        None => box_impl_trait(&mut func_out)?.map(|(boxed, nested)| {
+            (
+                boxed,
+                nested,
+                output_span,
+                quote_spanned!(output_span=> ::std::boxed::Box::new(v)),
+            )
+        }),
+    };
+    if let Some((target, nested, span, convert)) = conversion {
+        let (asyncness, call) = match func_async {
+            true => (quote!(async), quote!(inner().await)),
+            false => (quote!(), quote!(inner())),
+        };
+        let convert = match nested {
+            true => quote_spanned!(span=> #call.map(|v| -> #target { #convert })),
+            false => quote_spanned!(span=> {
+                let v = #call;
+                let v: #target = #convert;
+                v
+            }),
+        };
+        func_body = parse_quote!({
+            #asyncness fn inner() -> #opaque_out #func_block
+            #convert
+        });
+    } else if func_async {
+        // Async blocks can't declare a return type, so coercions such as
+        // `Box<T>` to `Box<dyn Trait>` need the original signature
+        func_body = parse_quote!({
+            async fn inner() -> #func_out #func_block
+            inner().await
+        });
+    }
+    // Checked when the fixture is initialized, so its dependents are skipped
+    // along with its test
+    if !attr.requires_env.is_empty() {
+        let vars = &attr.requires_env;
+        func_body.stmts.insert(
+            0,
+            parse_quote!(#found_crate::helpers::require_env(&[#(#vars),*]);),
+        );
+    }
+    // Kept in a function of its own, so that its coverage is still measured
+    // when the generated items around it are excluded
+    func_body = match func_async {
+        true => parse_quote!({
+            #found_crate::helpers::coverage_on! {
+                async fn fixture_body() -> #func_out #func_body
+            }
+            fixture_body().await
+        }),
+        false => parse_quote!({
+            #found_crate::helpers::coverage_on! {
+                fn fixture_body() -> #func_out #func_body
+            }
+            fixture_body()
+        }),
+    };
+
+    let fixture_name = attr.fixtures.name();
+    let future = match attr.runtime {
+        Some(Runtime::TokioLocal(_)) => {
+            quote!(#found_crate::helpers::LocalFixtureFuture<#func_out>)
+        }
+        _ => quote!(#found_crate::helpers::FixtureFuture<#func_out>),
+    };
+    let catch_panic = match attr.no_catch_unwind {
+        Some(_) => quote!(#found_crate::helpers::no_catch_panic),
+        None => quote!(#found_crate::helpers::catch_panic),
+    };
+    if let (true, Some(stack_size)) = (func_async, &attr.stack_size) {
+        return Err(Error::new(
+            stack_size.span(),
+            "`stack_size` isn't supported for `async` functions",
+        ));
+    }
+    // A synchronous body can't be cancelled, so it's run on a helper thread
+    // which is abandoned if it doesn't finish in time
+    let sync_timeout = match (func_async, &attr.timeout) {
+        (false, Some(Timeout { text, duration, .. })) => {
+            let (secs, nanos) = (duration.as_secs(), duration.subsec_nanos());
+            let message = format!("{} timed out after {}", fixture_name, text);
+            Some(quote!(::std::option::Option::Some((
+                #found_crate::helpers::Duration::new(#secs, #nanos),
+                #message,
+            ))))
+        }
+        _ => None,
+    };
+    let catch_panic = match (sync_timeout, &attr.stack_size) {
+        (None, None) => catch_panic,
+        (timeout, stack_size) => {
+            let timeout = timeout.unwrap_or_else(|| quote!(::std::option::Option::None));
+            let stack_size = match stack_size {
+                Some(stack_size) => quote!(::std::option::Option::Some(#stack_size)),
+                None => quote!(::std::option::Option::None),
+            };
+            quote!((|body| #found_crate::helpers::run_on_thread(
+                #stack_size,
+                #timeout,
+                #catch_panic,
+                body,
+            )))
+        }
+    };
+
+    // `fresh` and `thread_local` fixtures run the function themselves, taking
+    // ownership of the value it produces
+    let (value_ty, extract) = match result_type_arg(&func_out, 0) {
+        Some(ok) => (ok, quote!(fresh_result)),
+        None => (&func_out, quote!(fresh_value)),
+    };
+    let value_ty = attr.fixtures.ty().unwrap_or_else(|| value_ty.clone());
+    let run = match func_async {
+        true => quote!(#found_crate::helpers::run_async(#block_on, || -> #future {
+            ::std::boxed::Box::pin((#timeout)(async move #func_body))
+        })),
+        false => quote!(#catch_panic(|| -> #func_out #func_body)),
+    };
+    let test_name = func.sig.ident.unraw().to_string();
+    let test_path = quote!(::std::concat!(::std::module_path!(), "::", #test_name));
+    let init = quote!(|| {
+        #found_crate::helpers::fresh(#fixture_name, #test_path, || {
+            #found_crate::helpers::#extract(#run)
+        })
+    });
+
+    if let (Some(span), Fixtures::Single(binding)) = (attr.thread_local, &attr.fixtures) {
+        let Binding {
+            attrs, vis, ident, ..
+        } = &**binding;
+        func.sig.output = ReturnType::Type(
+            Default::default(),
+            Box::new(
+                parse_quote_spanned!(output_span=> ::std::result::Result<(), #found_crate::helpers::LocalFailure>),
+            ),
+        );
+        let func_sig = &func.sig;
+        let test = (!attr.no_test).then(|| {
+            quote_spanned!(span=>
+                #fixture_cfg
+                #found_crate::helpers::coverage_off! {
+                    #(#func_attrs)*
+                    #(#[#test_attrs])*
+                    #test_attr
+                    #func_vis #func_sig {
+                        #ident.verify()
+                    }
+                }
+            )
+        });
+        return Ok(quote_spanned!(span=>
+            #body_fn
+            #companion
+
+            #(#attrs)*
+            #fixture_cfg
+            #vis static #ident: #found_crate::helpers::LocalFixture<#value_ty> = {
+                ::std::thread_local! {
+                    static CELL: #found_crate::helpers::LocalCell<#value_ty> =
+                        #found_crate::helpers::LocalCell::new();
+                }
+                #found_crate::helpers::LocalFixture::new(&CELL, #init)
+            };
+
+            #test
+        ));
+    }
+
+    let owned_ty;
+    let fixture_ty = match attr.fixtures.ty() {
+        _ if storage.is_some() => storage.as_ref().unwrap(),
+        Some(ty) => {
+            owned_ty = ty;
+            &owned_ty
+        }
+        _ if attr.expect_panic => {
+            owned_ty = parse_quote!(::std::string::String);
+            &owned_ty
+        }
+        _ if attr.full => &func_out,
+        _ if attr.capture == Capture::Err => {
+            owned_ty = match result_type_arg(&func_out, 1).or(attr.error.as_ref()) {
+                Some(ty) => static_borrow_target(ty).unwrap_or(ty).clone(),
+                None => {
+                    parse_quote_spanned!(output_span=> <#func_out as #found_crate::helpers::IntoFixtureResult>::Error)
+                }
+            };
+            &owned_ty
+        }
+        _ => match (result_type_arg(&func_out, 0), &attr.error) {
+            (Some(ty), _) => static_borrow_target(ty).unwrap_or(ty),
+            (None, Some(_)) => {
+                owned_ty = parse_quote_spanned!(output_span=> <#func_out as #found_crate::helpers::IntoFixtureResult>::Value);
+                &owned_ty
+            }
+            (None, None) => static_borrow_target(&func_out).unwrap_or(&func_out),
+        },
+    };
+
+    // Tuple and unit fixtures are stored in a hidden static, and exposed
+    // through one static per element or a zero-sized handle respectively
+    let hidden = format_ident!(
+        "__{}_FIXTURE",
+        func_ident.unraw().to_string().to_uppercase()
+    );
+    let mut hidden_attrs = vec![parse_quote!(#[doc(hidden)])];
+    let is_unit = matches!(fixture_ty, Type::Tuple(tuple) if tuple.elems.is_empty());
+    let (fixture_attrs, fixture_vis, fixture_ident, projections) = match &attr.fixtures {
+        Fixtures::Single(binding) if attr.fresh.is_some() => {
+            let Binding {
+                attrs, vis, ident, ..
+            } = &**binding;
+            let span = attr.fresh.unwrap();
+            let handle = quote_spanned!(span=>
+                #(#attrs)*
+                #fixture_cfg
+                #vis static #ident: #found_crate::helpers::FreshFixture<#value_ty> =
+                    #found_crate::helpers::FreshFixture::new(#init);
+            );
+            // The fixture's test still caches its own run of the function
+            hidden_attrs.push(parse_quote!(#[allow(dead_code)]));
+            (hidden_attrs, Visibility::Inherited, hidden, handle)
+        }
+        Fixtures::Single(binding) if is_unit && attr.clone.is_none() => {
+            let Binding {
+                attrs, vis, ident, ..
+            } = &**binding;
+            let ensure_async = if func_async {
+                quote!(
+                    /// Forces the fixture's setup without blocking, panicking if it failed
+                    pub async fn ensure_async(&self) {
+                        #hidden.get_async().await;
+                    }
+                )
+            } else {
+                quote!()
+            };
+            let handle = quote!(
+                #(#attrs)*
+                #fixture_cfg
+                #[allow(non_camel_case_types)]
+                #[derive(Clone, Copy, Debug)]
+                #vis struct #ident;
+
+                #fixture_cfg
+                impl #ident {
+                    /// Forces the fixture's setup, panicking if it failed
+                    pub fn ensure(&self) {
+                        let _ = &*#hidden;
+                    }
+
+                    #ensure_async
+                }
+
+                #fixture_cfg
+                impl ::std::ops::Deref for #ident {
+                    type Target = ();
+                    fn deref(&self) -> &() {
+                        &*#hidden
+                    }
+                }
+            );
+            (hidden_attrs, Visibility::Inherited, hidden, handle)
+        }
+        Fixtures::Single(binding) if attr.once.is_some() => {
+            let Binding {
+                attrs, vis, ident, ..
+            } = &**binding;
+            let span = attr.once.unwrap();
+            let handle = quote_spanned!(span=>
+                #(#attrs)*
+                #fixture_cfg
+                #vis static #ident: #found_crate::helpers::TakeFixture<#inner> =
+                    #found_crate::helpers::TakeFixture::new(#fixture_name, || #hidden.try_get());
+            );
+            (hidden_attrs, Visibility::Inherited, hidden, handle)
+        }
+        Fixtures::Single(binding) if attr.local.is_some() => {
+            let Binding {
+                attrs, vis, ident, ..
+            } = &**binding;
+            let span = attr.local.unwrap();
+            let handle = quote_spanned!(span=>
+                #(#attrs)*
+                #fixture_cfg
+                #vis static #ident: #found_crate::helpers::UnsyncFixture<#inner> =
+                    #found_crate::helpers::UnsyncFixture::new(#fixture_name, || #hidden.try_get());
+            );
+            (hidden_attrs, Visibility::Inherited, hidden, handle)
+        }
+        Fixtures::Single(binding) if attr.releasable.is_some() => {
+            let Binding {
+                attrs, vis, ident, ..
+            } = &**binding;
+            let span = attr.releasable.unwrap();
+            let handle = quote_spanned!(span=>
+                #(#attrs)*
+                #fixture_cfg
+                #vis static #ident: #found_crate::helpers::ReleasableFixture<#inner> =
+                    #found_crate::helpers::ReleasableFixture::new(
+                        #fixture_name,
+                        || #hidden.try_get(),
+                        || #hidden.tracker(),
+                    );
+            );
+            (hidden_attrs, Visibility::Inherited, hidden, handle)
+        }
+        Fixtures::Single(binding) if attr.arc.is_some() => {
+            let Binding {
+                attrs, vis, ident, ..
+            } = &**binding;
+            let span = attr.arc.unwrap();
+            let handle = quote_spanned!(span=>
+                #(#attrs)*
+                #fixture_cfg
+                #vis static #ident: #found_crate::helpers::ArcFixture<#inner> =
+                    #found_crate::helpers::ArcFixture::new(
+                        || #hidden.try_get().map(|v| &**v).map_err(|e| #found_crate::helpers::forward(#fixture_name, e)),
+                        || #hidden.try_get(),
+                        || #hidden.tracker(),
+                    );
+            );
+            (hidden_attrs, Visibility::Inherited, hidden, handle)
+        }
+        Fixtures::Single(binding) if attr.clone.is_some() => {
+            let Binding {
+                attrs, vis, ident, ..
+            } = &**binding;
+            let span = attr.clone.unwrap();
+            let handle = quote_spanned!(span=>
+                #(#attrs)*
+                #fixture_cfg
+                #vis static #ident: #found_crate::helpers::ClonedFixture<#fixture_ty> =
+                    #found_crate::helpers::ClonedFixture::new(|| #hidden.try_get());
+            );
+            (hidden_attrs, Visibility::Inherited, hidden, handle)
+        }
+        Fixtures::Single(binding) => (
+            binding.attrs.clone(),
+            binding.vis.clone(),
+            binding.ident.clone(),
+            quote!(),
+        ),
+        Fixtures::Tuple(span, bindings) => {
+            let elems = match fixture_ty {
+                Type::Tuple(tuple) if tuple.elems.len() == bindings.len() => Some(&tuple.elems),
+                Type::Tuple(tuple) => {
+                    let message = format!(
+                        "expected {} fixtures to match the returned tuple, found {}",
+                        tuple.elems.len(),
+                        bindings.len(),
+                    );
+                    return Err(Error::new(*span, message));
+                }
+                _ => None,
+            };
+            let projections = bindings.iter().enumerate().map(|(i, binding)| {
+                let Binding {
+                    attrs, vis, ident, ..
+                } = binding;
+                let ty = match (&binding.ty, elems) {
+                    (Some(ty), _) => ty,
+                    (None, Some(elems)) => &elems[i],
+                    (None, None) => {
+                        let message = format!(
+                            "cannot infer the type of `{}` from the return type, specify it as `{}: Type`",
+                            ident, ident,
+                        );
+                        return Err(Error::new(ident.span(), message));
+                    }
+                };
+                let index = Index::from(i);
+                let name = ident.unraw().to_string();
+                Ok(quote!(
+                    #(#attrs)*
+                    #fixture_cfg
+                    #vis static #ident: #found_crate::helpers::FixtureHandle<#ty> =
+                        #found_crate::helpers::FixtureHandle::projected(
+                            || {
+                                #hidden
+                                    .try_get()
+                                    .map(|v| &v.#index)
+                                    .map_err(|e| #found_crate::helpers::forward(#name, e))
+                            },
+                            || #hidden.tracker(),
+                        );
+                ))
+            });
+            let projections = projections.collect::<Result<Vec<_>>>()?;
+            (
+                hidden_attrs,
+                Visibility::Inherited,
+                hidden,
+                quote!(#(#projections)*),
+            )
+        }
+    };
+
+    func.sig.output = ReturnType::Type(
+        Default::default(),
+        Box::new(match attr.requires_env.is_empty() {
+            true => {
+                parse_quote_spanned!(output_span=> ::std::result::Result<#found_crate::helpers::Fixture<#fixture_ty>, #found_crate::helpers::FixtureError>)
+            }
+            false => {
+                parse_quote_spanned!(output_span=> ::std::result::Result<(), #found_crate::helpers::FixtureError>)
+            }
+        }),
+    );
+    let func_sig = &func.sig;
+
+    if let (true, Some(fallback)) = (func_async, &attr.fallback) {
+        return Err(Error::new(
+            fallback.span(),
+            "`fallback` isn't supported for `async` functions",
+        ));
+    }
+
+    let flatten = if attr.expect_panic {
+        quote_spanned!(output_span=> #found_crate::helpers::flatten_panic::<#fixture_ty, _>(result))
+    } else if attr.full {
+        let pass_if = match &attr.pass_if {
+            Some(pass_if) => quote!(#pass_if),
+            None => quote!(|_| true),
+        };
+        quote_spanned!(output_span=> #found_crate::helpers::flatten_full::<#fixture_ty>(result, #pass_if))
+    } else if attr.capture == Capture::Err {
+        quote_spanned!(output_span=> #found_crate::helpers::flatten_err::<#fixture_ty, _>(result))
+    } else if attr.error_display {
+        quote_spanned!(output_span=> #found_crate::helpers::flatten_display::<#fixture_ty, _, _>(result))
+    } else {
+        quote_spanned!(output_span=> #found_crate::helpers::flatten::<#fixture_ty, _, _>(result))
+    };
+
+    // Registered once the fixture is initialized, whether by its test or by a
+    // dependent
+    let teardown = attr.teardown.as_ref().map(|teardown| {
+        quote_spanned!(teardown.span()=>
+            if let ::std::result::Result::Ok(fixture) = &fixture {
+                static TEARDOWN: ::std::sync::Once = ::std::sync::Once::new();
+                #found_crate::helpers::register_teardown(&TEARDOWN, #fixture_name, fixture.0, #teardown);
+            }
+        )
+    });
+
+    // The fixture function runs in the initializer of the fixture's static,
+    // which its test and dependents both read, so keeps the function's lints
+    let lint_attrs = func_attrs.iter().filter(|attr| is_lint_attr(attr));
+    let fixture_result = quote!(::std::result::Result<
+        #found_crate::helpers::Fixture<#fixture_ty>,
+        #found_crate::helpers::FixtureError,
+    >);
+    let mut attempt = quote!({
+        let result = #found_crate::helpers::leak(#catch_panic(|| -> #func_out #func_body));
+        #flatten
+    });
+    if let Some((_, retries)) = attr.retries {
+        let delay = attr.retry_delay.unwrap_or_default();
+        let (secs, nanos) = (delay.as_secs(), delay.subsec_nanos());
+        attempt = quote!(#found_crate::helpers::retry(
+            #fixture_name,
+            #retries,
+            #found_crate::helpers::Duration::new(#secs, #nanos),
+            || #attempt,
+        ));
+    }
+    // Invariants are only checked in debug builds, compiling away otherwise
+    let invariant_check = attr.invariant.as_ref().map(|invariant| {
+        quote_spanned!(invariant.span()=>
+            #[cfg(debug_assertions)]
+            let fixture = #found_crate::helpers::check_invariant(#fixture_name, fixture, #invariant);
+        )
+    });
+    let access_checks = match (&attr.invariant, &attr.verify_unchanged) {
+        (None, None) => quote!(#found_crate::helpers::Checks::NONE),
+        (invariant, verify_unchanged) => {
+            let invariant = match invariant {
+                Some(invariant) => quote_spanned!(invariant.span()=>
+                    ::std::option::Option::Some((#invariant) as fn(&#fixture_ty) -> bool)
+                ),
+                None => quote!(::std::option::Option::None),
+            };
+            let fingerprint = match verify_unchanged {
+                Some(VerifyUnchanged::Hash(span)) => quote_spanned!(*span=>
+                    ::std::option::Option::Some(#found_crate::helpers::hash_fingerprint::<#fixture_ty>)
+                ),
+                Some(VerifyUnchanged::Fingerprint(fingerprint)) => {
+                    quote_spanned!(fingerprint.span()=>
+                        ::std::option::Option::Some(|v: &#fixture_ty| #found_crate::helpers::debug_fingerprint(v, #fingerprint))
+                    )
+                }
+                None => quote!(::std::option::Option::None),
+            };
+            quote!({
+                #[cfg(debug_assertions)]
+                let checks = #found_crate::helpers::Checks {
+                    invariant: #invariant,
+                    fingerprint: #fingerprint,
+                };
+                #[cfg(not(debug_assertions))]
+                let checks = #found_crate::helpers::Checks::NONE;
+                checks
+            })
+        }
+    };
+    let init_fn = quote!(
+        #found_crate::helpers::coverage_off! {
+            #(#lint_attrs)*
+            #[allow(clippy::type_complexity)]
+            fn init() -> #fixture_result {
+                let fixture = #attempt;
+                #teardown
+                #invariant_check
+                fixture
+            }
+        }
+    );
+
+    // Only fixtures with a test of their own can be initialized by it
+    let strictness = match attr.strict {
+        true => quote!(#found_crate::helpers::Strictness::Strict),
+        false => quote!(#found_crate::helpers::Strictness::Env),
+    };
+    let strictness = match (&attr.fallback, &attr.cfg) {
+        _ if attr.no_test || attr.default => quote!(#found_crate::helpers::Strictness::Exempt),
+        (Some(_), Some(cfg)) => quote!(match ::std::cfg!(#cfg) {
+            true => #strictness,
+            false => #found_crate::helpers::Strictness::Exempt,
+        }),
+        _ => strictness,
+    };
+
+    let (fixture, verify) = if func_async {
+        let fixture = quote!(
+            #fixture_vis static #fixture_ident: #found_crate::helpers::AsyncFixture<#fixture_ty, #func_out, #future> = {
+                #found_crate::helpers::coverage_off! {
+                    fn init() -> #future {
+                        ::std::boxed::Box::pin((#timeout)(async move #func_body))
+                    }
+                    #[allow(clippy::type_complexity)]
+                    fn finish(result: #found_crate::helpers::FixtureResult<#func_out>) -> #fixture_result {
+                        let result = #found_crate::helpers::leak(result);
+                        let fixture = #flatten;
+                        #teardown
+                        #invariant_check
+                        fixture
+                    }
+                }
+                #found_crate::helpers::AsyncFixture::new(
+                    #fixture_name,
+                    #strictness,
+                    #access_checks,
+                    init,
+                    |result| #found_crate::helpers::resolve(#fixture_name, #test_path, || finish(result)),
+                    || {
+                        #found_crate::helpers::resolve(#fixture_name, #test_path, || {
+                            finish(#found_crate::helpers::run_async(#block_on, init))
+                        })
+                    },
+                )
+            };
+        );
+        let fixture = quote!(#(#fixture_attrs)* #fixture_cfg #fixture);
+        (fixture, quote!(#fixture_ident.verify()))
+    } else {
+        let resolve = match &attr.fallback {
+            Some(fallback) => {
+                let tested = match (attr.default, &attr.cfg) {
+                    (true, _) => quote!(any()),
+                    (false, Some(cfg)) => quote!(#cfg),
+                    (false, None) => quote!(all()),
+                };
+                quote!({
+                    #[cfg(#tested)]
+                    #init_fn
+                    #found_crate::helpers::coverage_off! {
+                        #[cfg(#tested)]
+                        fn resolve() -> ::std::result::Result<&'static #fixture_ty, #found_crate::helpers::FixtureError> {
+                            #found_crate::helpers::resolve(#fixture_name, #test_path, init)
+                        }
+                        #[cfg(not(#tested))]
+                        fn resolve() -> ::std::result::Result<&'static #fixture_ty, #found_crate::helpers::FixtureError> {
+                            #found_crate::helpers::fallback(#fixture_name, #fallback)
+                        }
+                    }
+                    resolve
+                })
+            }
+            None => quote!({
+                #init_fn
+                || #found_crate::helpers::resolve(#fixture_name, #test_path, init)
+            }),
+        };
+        let fixture = quote!(
+            #(#fixture_attrs)*
+            #fixture_cfg
+            #fixture_vis static #fixture_ident: #found_crate::helpers::FixtureHandle<#fixture_ty> =
+                #found_crate::helpers::FixtureHandle::new(#fixture_name, #strictness, #access_checks, #resolve);
+        );
+        // A `default` fixture's test still runs its function, which
+        // dependents never see
+        let verify = match attr.default {
+            true => quote!({
+                #init_fn
+                init()
+            }),
+            false => quote!(#fixture_ident.verify()),
+        };
+        (fixture, verify)
+    };
+
+    // Checked separately so that a mismatch is reported on the attribute
+    let error_check = attr.error.as_ref().map(|error| {
+        let func_out = respan(quote!(#func_out), error.span());
+        quote_spanned!(error.span()=> #found_crate::helpers::expect_error::<#func_out, #error>();)
+    });
+    // Likewise an annotated type which can't be captured from the return type
+    let converted = storage.is_some() || attr.store_as.is_some() || attr.map.is_some();
+    let type_check = match &attr.fixtures {
+        Fixtures::Single(binding)
+            if !converted && !attr.expect_panic && !attr.full && attr.capture == Capture::Ok =>
+        {
+            binding.ty.as_ref().map(|ty| {
+                let func_out = respan(quote!(#func_out), ty.span());
+                quote_spanned!(ty.span()=>
+                    #found_crate::helpers::expect_fixture_type::<#func_out, #ty, _>();
+                )
+            })
+        }
+        _ => None,
+    };
+    // As is a result which can't be sent back from a helper thread
+    let helper_thread = match (func_async, &attr.timeout, &attr.stack_size) {
+        (false, Some(Timeout { span, .. }), _) => Some(*span),
+        (_, _, Some(stack_size)) => Some(stack_size.span()),
+        _ => None,
+    };
+    let send_check = helper_thread.map(|span| {
+        let func_out = respan(quote!(#func_out), span);
+        quote_spanned!(span=>
+            #found_crate::helpers::helper_thread_requires_send::<#func_out>();
+        )
+    });
+    let checks = match (error_check, type_check, send_check) {
+        (None, None, None) => None,
+        (error_check, type_check, send_check) => Some(quote!(#error_check #type_check #send_check)),
+    };
+
+    let eager = attr.eager.map(|span| {
+        quote_spanned!(span=>
+            #fixture_cfg
+            #found_crate::helpers::eager!(#fixture_ident);
+        )
+    });
+
+    // Checked up front so the offending type is reported before the bounds of
+    // the statics storing it
+    let arc_check = attr.arc.map(|span| {
+        quote_spanned!(span=>
+            #fixture_cfg
+            const _: () = #found_crate::helpers::arc_fixture_must_be_send_sync::<#inner>();
+        )
+    });
+
+    // Likewise a fixture which isn't `Sync`, by its bounds rather than by the
+    // names in its type, and reported on the attribute. `local` and `fresh`
+    // fixtures are never shared, and an `arc` fixture is checked above
+    let sync_check = match (attr.local, &attr.fresh, attr.arc) {
+        (None, None, None) => Some(quote!(
+            #fixture_cfg
+            const _: #found_crate::helpers::FixtureMustBeSync<#fixture_ty> =
+                #found_crate::helpers::FixtureMustBeSync::CHECKED;
+        )),
+        _ => None,
+    };
+
+    // Compiles out the function along with the items referring to it, leaving
+    // dependents with the fallback value
+    let cfg = attr.cfg.as_ref().map(|cfg| quote!(#[cfg(#cfg)]));
+
+    let verify_injected = match &attr.verify_injected {
+        Some(verify) => quote!(#verify),
+        None => quote!(|_| true),
+    };
+
+    // `local` fixtures can't be forced by other threads
+    let register = match attr.local {
+        Some(_) => quote!(),
+        None => quote!(
+            #fixture_cfg
+            #cfg
+            #found_crate::helpers::register!(#fixture_name, #fixture_ident);
+        ),
+    };
+
+    // Emitted ahead of the fixture, so that mismatches are reported before
+    // the errors they cause in its initialization
+    let checks = checks.map(|checks| {
+        quote!(
+            #fixture_cfg
+            #cfg
+            const _: fn() = || {
+                #checks
+            };
+        )
+    });
+
+    // The fixtures can't be placed in another module, so instead check that
+    // it re-exports these very statics, not just items of the same names
+    let export_check = attr.export_to.as_ref().map(|path| {
+        let checks = attr.fixtures.bindings().iter().map(|binding| {
+            let ident = &binding.ident;
+            let exported = respan(quote!(#path::#ident), path.span());
+            let name = exported.to_string().replace(' ', "");
+            quote!(#found_crate::helpers::assert_exported(&#ident, &#exported, #name);)
+        });
+        quote!(#(#checks)*)
+    });
+
+    let test = (!attr.no_test).then(|| {
+        let body = quote!(
+            #export_check
+            if let ::std::option::Option::Some(fixture) = #fixture_ident.injected() {
+                return #found_crate::helpers::verify_injected(fixture, #verify_injected);
+            }
+            #fixture_ident.tracker().retry_failed();
+            #verify
+        );
+        // A skipped fixture's test passes, rather than failing like its
+        // dependents
+        let body = match attr.requires_env.is_empty() {
+            true => body,
+            false => quote!(#found_crate::helpers::unless_skipped(|| { #body })),
+        };
+        // Like the fixture, so that the macro is never resolved without it
+        quote!(
+            #fixture_cfg
+            #cfg
+            #found_crate::helpers::coverage_off! {
+                #(#func_attrs)*
+                #(#[#test_attrs])*
+                #test_attr
+                #[allow(clippy::type_complexity)]
+                #func_vis #func_sig {
+                    #body
+                }
+            }
+        )
+    });
+
+    // Also checked at compile time, even without a test to run it
+    let export = export_check.as_ref().map(|export_check| {
+        quote!(
+            #fixture_cfg
+            #cfg
+            const _: fn() = || {
+                #export_check
+            };
+        )
+    });
+
+    let v = quote!(
+        #checks
+        #body_fn
+        #companion
+        #arc_check
+        #sync_check
+        #fixture
+        #projections
+        #export
+        #eager
+        #register
+        #test
+    );
+
+    Ok(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(tokens: proc_macro2::TokenStream) -> String {
+        match syn::parse2::<Attr>(tokens) {
+            Ok(_) => panic!("expected a parse error"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn expand_ok(attr: proc_macro2::TokenStream, item: proc_macro2::TokenStream) -> String {
+        let found_crate = parse_quote!(tested_fixture);
+        let attr = syn::parse2(attr).unwrap();
+        let func = syn::parse2(item).unwrap();
+        expand(&found_crate, attr, func).unwrap().to_string()
+    }
+
+    fn expand_error(attr: proc_macro2::TokenStream, item: proc_macro2::TokenStream) -> String {
+        let found_crate = parse_quote!(tested_fixture);
+        let attr = syn::parse2(attr).unwrap();
+        let func = syn::parse2(item).unwrap();
+        match expand(&found_crate, attr, func) {
+            Ok(_) => panic!("expected an expansion error"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn reject_should_panic() {
+        assert_eq!(
+            expand_error(
+                quote!(STEP_1),
+                quote!(
+                    #[should_panic(expected = "boom")]
+                    fn step_1() {}
+                )
+            ),
+            "`#[should_panic]` cannot be combined with `tested_fixture`, which catches panics; use `expect_panic` instead"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, expect_panic, full)),
+            "`full` cannot be combined with `expect_panic`"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1: String, map = |s| s, expect_panic)),
+            "`map` cannot be combined with `expect_panic`"
+        );
+    }
+
+    #[test]
+    fn reject_non_functions() {
+        let error = |item: proc_macro2::TokenStream| match item_fn(syn::parse2(item).unwrap()) {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => e.to_string(),
+        };
+        assert_eq!(
+            error(quote!(
+                static CONST_THING: u32 = 1;
+            )),
+            "`tested_fixture` can only be applied to functions; to share a value between tests, declare the static directly"
+        );
+        assert_eq!(
+            error(quote!(
+                const CONST_THING: u32 = 1;
+            )),
+            "`tested_fixture` can only be applied to functions; to share a constant between tests, declare it directly"
+        );
+        assert_eq!(
+            error(quote!(
+                impl Foo {}
+            )),
+            "`tested_fixture` can only be applied to functions, so apply it to a function outside of the `impl` block instead"
+        );
+        assert_eq!(
+            error(quote!(
+                mod steps {}
+            )),
+            "`tested_fixture` can only be applied to functions, so apply it to each function in the module instead"
+        );
+        assert_eq!(
+            error(quote!(
+                struct Foo;
+            )),
+            "`tested_fixture` can only be applied to functions"
+        );
+    }
+
+    #[test]
+    fn reject_parameters() {
+        assert_eq!(
+            expand_error(
+                quote!(STEP_2),
+                quote!(
+                    fn step_2(foo: &Foo) -> State {}
+                )
+            ),
+            "`tested_fixture` functions cannot take parameters; access other fixtures via their statics inside the body"
+        );
+    }
+
+    #[test]
+    fn reject_generics() {
+        assert_eq!(
+            expand_error(
+                quote!(SETUP),
+                quote!(
+                    fn setup<T: Default>() -> T {}
+                )
+            ),
+            "`tested_fixture` functions cannot be generic, since each fixture is a single static; call a generic function from the body instead, specifying the fixture's type as `IDENT: Type` if needed"
+        );
+        assert_eq!(
+            expand_error(
+                quote!(SETUP),
+                quote!(
+                    fn setup<'a, const N: usize>() -> [&'a str; N] {}
+                )
+            ),
+            "`tested_fixture` functions cannot be generic, since each fixture is a single static; call a generic function from the body instead, specifying the fixture's type as `IDENT: Type` if needed"
+        );
+        assert_eq!(
+            expand_error(
+                quote!(SETUP),
+                quote!(
+                    fn setup<'a>() -> &'a str {}
+                )
+            ),
+            "`tested_fixture` functions cannot have lifetime parameters, since their fixtures are `'static`"
+        );
+    }
+
+    #[test]
+    fn reject_borrowed_returns() {
+        let error = |ty: proc_macro2::TokenStream| {
+            expand_error(
+                quote!(SETUP),
+                quote!(
+                    fn setup() -> #ty {}
+                ),
+            )
+        };
+        let message = |owned: &str| {
+            format!(
+                "fixtures must own their data or borrow only 'static data; consider returning an owned `{}`",
+                owned,
+            )
+        };
+        assert_eq!(error(quote!(&Config)), message("Config"));
+        assert_eq!(error(quote!(&mut Vec<u8>)), message("Vec<u8>"));
+        assert_eq!(error(quote!(&str)), message("String"));
+        assert_eq!(error(quote!(&[u8])), message("Vec<u8>"));
+        assert_eq!(error(quote!(Result<&Config, Error>)), message("Config"));
+        assert_eq!(error(quote!((u32, &'_ Config))), message("Config"));
+        assert_eq!(error(quote!(&'static &Config)), message("Config"));
+        assert_eq!(error(quote!(Cow<'_, str>)), message("Cow<str>"));
+        assert_eq!(
+            error(quote!(Option<Ref<'_, Config>>)),
+            message("Ref<Config>")
+        );
+
+        for ty in [
+            quote!(&'static Config),
+            quote!(&'static [u8]),
+            quote!(Cow<'static, str>),
+            quote!(Result<&'static str, Error>),
+            quote!(Box<dyn Fn(&str) -> &str + Send + Sync>),
+        ] {
+            expand_ok(
+                quote!(SETUP),
+                quote!(
+                    fn setup() -> #ty {}
+                ),
+            );
+        }
+    }
+
+    #[test]
+    fn check_annotated_types() {
+        let expand = |attr: proc_macro2::TokenStream| {
+            let func = quote!(
+                fn step() -> Result<Foo, E> {}
+            );
+            expand_ok(attr, func).contains("expect_fixture_type")
+        };
+        assert!(expand(quote!(STEP: Foo)));
+        assert!(expand(quote!(STEP: Foo, error_display)));
+        assert!(!expand(quote!(STEP)));
+        assert!(!expand(quote!(STEP: Bar, store_as = Bar)));
+        assert!(!expand(quote!(STEP: Result<Foo, E>, full)));
+        assert!(!expand(quote!(STEP: E, capture = "err")));
+    }
+
+    #[test]
+    fn expose_fixtures() {
+        let func = quote!(
+            fn step_1() -> Foo {}
+        );
+        let tokens = expand_ok(quote!(pub STEP_1), func.clone());
+        assert!(tokens.contains(&quote!(#[cfg(test)]).to_string()));
+        let tokens = expand_ok(quote!(pub STEP_1, exposed(feature = "test-fixtures")), func);
+        assert!(!tokens.contains(&quote!(#[cfg(test)]).to_string()));
+        let exposed = quote!(#[cfg(any(test, feature = "test-fixtures"))]);
+        assert!(tokens.contains(&exposed.to_string()));
+
+        assert_eq!(
+            parse_error(quote!(STEP_1, exposed)),
+            "`exposed` requires a predicate, e.g. `exposed(feature = \"test-fixtures\")`"
+        );
+    }
+
+    #[test]
+    fn expose_companion_fn() {
+        let func = quote!(
+            #[allow(unused)]
+            async fn step_1() -> Foo {
+                Foo::new()
+            }
+        );
+        let tokens = expand_ok(quote!(STEP_1, expose_fn = run_step_1), func);
+        let companion = quote!(
+            #[allow(unused)]
+            pub async fn run_step_1() -> Foo {
+                Foo::new()
+            }
+        );
+        assert!(tokens.contains(&companion.to_string()));
+        assert!(tokens.contains(&quote!(run_step_1().await).to_string()));
+        // The body is only spliced into the companion
+        let body = quote!(Foo::new()).to_string();
+        assert_eq!(tokens.matches(&body).count(), 1);
+        assert_eq!(
+            parse_error(quote!(STEP_1, expose_fn)),
+            "`expose_fn` requires a value"
+        );
+    }
+
+    #[test]
+    fn rename_test_fn() {
+        let func = quote!(
+            fn step_1() -> Foo {
+                Foo::new()
+            }
+        );
+        let tokens = expand_ok(quote!(STEP_1, test_name = "fixture_step_1"), func);
+        // The body stays in the original function, which the fixture calls
+        assert!(tokens.contains(
+            &quote!(
+                fn step_1() -> Foo {
+                    Foo::new()
+                }
+            )
+            .to_string()
+        ));
+        assert!(tokens.contains(&quote!(step_1()).to_string()));
+        assert!(tokens.contains(&quote!(fn fixture_step_1() -> ::std::result::Result).to_string()));
+        assert!(tokens.contains("\"fixture_step_1\""));
+    }
+
+    #[test]
+    fn copy_doc_comments() {
+        let func = quote!(
+            /// Verifies stage 1
+            fn step_1() -> Foo {}
+        );
+        let tokens = expand_ok(quote!(STEP_1), func.clone());
+        let docs = quote!(
+            #[doc = "Fixture produced by `step_1`:"]
+            #[doc = ""]
+            #[doc = r" Verifies stage 1"]
+            #[cfg(test)]
+            static STEP_1
+        );
+        assert!(tokens.contains(&docs.to_string()), "{}", tokens);
+
+        let tokens = expand_ok(
+            quote!(
+                #[doc = "Stage 1"]
+                STEP_1
+            ),
+            func,
+        );
+        let docs = quote!(
+            #[doc = "Stage 1"]
+            #[cfg(test)]
+            static STEP_1
+        );
+        assert!(tokens.contains(&docs.to_string()), "{}", tokens);
+        assert!(!tokens.contains("Fixture produced by"));
+    }
+
+    #[test]
+    fn infer_placeholder_types() {
+        let func = quote!(
+            fn step() -> Result<HashMap<ClientId, Vec<SessionRecord>>, E> {}
+        );
+        let inferred = expand_ok(quote!(STEP: _), func.clone());
+        assert_eq!(
+            inferred,
+            expand_ok(quote!(STEP: HashMap<ClientId, Vec<SessionRecord>>), func)
+        );
+        let func = quote!(
+            fn step() -> (Foo, Bar) {}
+        );
+        assert_eq!(
+            expand_ok(quote!((LEFT: _, RIGHT)), func.clone()),
+            expand_ok(quote!((LEFT, RIGHT)), func)
+        );
+        assert_eq!(
+            expand_error(
+                quote!(STEP: _),
+                quote!(
+                    fn step() -> Foo {}
+                )
+            ),
+            "`_` can only be used when the function returns a `Result`, specify the fixture's type instead"
+        );
+    }
+
+    #[test]
+    fn reject_async_test_attrs() {
+        assert_eq!(
+            expand_error(
+                quote!(STEP_1),
+                quote!(
+                    #[tokio::test]
+                    async fn step_1() {}
+                )
+            ),
+            "`#[tokio::test]` cannot be combined with `tested_fixture`, which generates its own test; use `runtime = \"tokio\"` instead"
+        );
+        assert_eq!(
+            expand_error(
+                quote!(STEP_1),
+                quote!(
+                    #[async_std::test]
+                    async fn step_1() {}
+                )
+            ),
+            "`#[async_std::test]` cannot be combined with `tested_fixture`, which generates its own test; use `runtime = \"async-std\"` instead"
+        );
+        assert_eq!(
+            expand_error(
+                quote!(STEP_1),
+                quote!(
+                    #[actix_rt::test]
+                    async fn step_1() {}
+                )
+            ),
+            "`#[actix_rt::test]` cannot be combined with `tested_fixture`, which generates its own test; use `block_on = ...` instead"
+        );
+    }
+
+    #[test]
+    fn parse_runtime_options() {
+        let attr: Attr = syn::parse2(quote!(
+            STEP_1,
+            runtime = "tokio",
+            flavor = "multi_thread",
+            worker_threads = 4,
+        ))
+        .unwrap();
+        assert!(matches!(
+            attr.runtime,
+            Some(Runtime::Tokio {
+                flavor: Flavor::MultiThread {
+                    worker_threads: Some(4)
+                },
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn reject_invalid_runtime_options() {
+        assert_eq!(
+            parse_error(quote!(STEP_1, runtime = "tokio", flavour = "multi_thread")),
+            "unknown option `flavour`"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, runtime = "tokio", runtime = "tokio")),
+            "duplicate `runtime` option"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, runtime = "tokio", flavor = "multi")),
+            "unsupported flavor, expected `\"current_thread\"` or `\"multi_thread\"`"
+        );
+        assert_eq!(
+            parse_error(quote!(
+                STEP_1,
+                runtime = "async-std",
+                flavor = "multi_thread"
+            )),
+            "`flavor` requires `runtime = \"tokio\"`"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, runtime = "tokio", worker_threads = 4)),
+            "`worker_threads` requires `flavor = \"multi_thread\"`"
+        );
+        assert_eq!(
+            parse_error(quote!(
+                STEP_1,
+                runtime = "tokio",
+                flavor = "multi_thread",
+                worker_threads = 0
+            )),
+            "`worker_threads` must be at least 1"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, runtime = "tokio", block_on = my::block_on)),
+            "`block_on` cannot be combined with `runtime`"
+        );
+    }
+
+    #[test]
+    fn parse_timeout_durations() {
+        assert_eq!(parse_duration("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("1m 30s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("1h30min"), Some(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("2 days"), Some(Duration::from_secs(172_800)));
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("30"), None);
+        assert_eq!(parse_duration("s"), None);
+        assert_eq!(parse_duration("30 fortnights"), None);
+        assert_eq!(parse_duration("-1s"), None);
+    }
+
+    #[test]
+    fn reject_invalid_timeouts() {
+        assert_eq!(
+            parse_error(quote!(STEP_1, timeout = "soon")),
+            "invalid duration, expected a value such as `\"500ms\"`, `\"30s\"`, or `\"1m 30s\"`"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, timeout = "0s")),
+            "`timeout` must be greater than zero"
+        );
+    }
+
+    #[test]
+    fn sync_timeout() {
+        let tokens = expand_ok(
+            quote!(STEP_1, timeout = "2m"),
+            quote!(
+                fn step_1() -> Foo {}
+            ),
+        );
+        assert!(tokens.contains("run_on_thread"));
+        assert!(tokens.contains("\"STEP_1 timed out after 2m\""));
+        assert!(tokens.contains(&quote!(helper_thread_requires_send::<Foo>()).to_string()));
+    }
+
+    #[test]
+    fn parse_stack_size() {
+        let attr: Attr = syn::parse2(quote!(STEP_1, stack_size = 16 * 1024 * 1024)).unwrap();
+        let stack_size = attr.stack_size.as_ref().unwrap();
+        assert_eq!(*stack_size, parse_quote!(16 * 1024 * 1024));
+        let tokens = expand_ok(
+            quote!(STEP_1, stack_size = 1 << 24),
+            quote!(
+                fn step_1() -> Foo {}
+            ),
+        );
+        let stack_size = quote!(::std::option::Option::Some(1 << 24)).to_string();
+        assert!(tokens.contains(&stack_size));
+        assert!(tokens.contains(&quote!(helper_thread_requires_send::<Foo>()).to_string()));
+        assert_eq!(
+            expand_error(
+                quote!(STEP_1, stack_size = 1 << 24),
+                quote!(
+                    async fn step_1() -> Foo {}
+                )
+            ),
+            "`stack_size` isn't supported for `async` functions"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, stack_size)),
+            "`stack_size` requires a value"
+        );
+    }
+
+    #[test]
+    fn parse_flags() {
+        let attr: Attr = syn::parse2(quote!(STEP_1: Foo, error_display)).unwrap();
+        assert!(attr.error_display);
+        let attr: Attr = syn::parse2(quote!(STEP_1: Foo, no_catch_unwind)).unwrap();
+        assert!(attr.no_catch_unwind.is_some());
+        let attr: Attr = syn::parse2(quote!(STEP_1: Foo, eager)).unwrap();
+        assert!(attr.eager.is_some());
+        assert_eq!(
+            expand_error(
+                quote!(STEP_1, no_catch_unwind),
+                quote!(
+                    async fn step_1() {}
+                )
+            ),
+            "`no_catch_unwind` isn't supported for an `async fn`"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, error_display = true)),
+            "`error_display` doesn't take a value"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, error_display, error_display)),
+            "duplicate `error_display` option"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, timeout)),
+            "`timeout` requires a value"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, error_debug)),
+            "unknown option `error_debug`"
+        );
+
+        let attr: Attr = syn::parse2(quote!(STEP_1, error = io::Error)).unwrap();
+        let error = attr.error.unwrap();
+        assert_eq!(quote!(#error).to_string(), "io :: Error");
+        assert_eq!(
+            parse_error(quote!(STEP_1, error)),
+            "`error` requires a value"
+        );
+    }
+
+    #[test]
+    fn infer_static_borrow_targets() {
+        use syn::parse_quote;
+
+        let target = |ty: Type| static_borrow_target(&ty).map(|ty| quote!(#ty).to_string());
+        assert_eq!(target(parse_quote!(&'static str)), Some("str".to_owned()));
+        assert_eq!(
+            target(parse_quote!(std::borrow::Cow<'static, [u8]>)),
+            Some("[u8]".to_owned())
+        );
+        assert_eq!(
+            target(parse_quote!(Pin<Box<SelfRef>>)),
+            Some("SelfRef".to_owned())
+        );
+        assert_eq!(target(parse_quote!(Pin<&'static SelfRef>)), None);
+        assert_eq!(target(parse_quote!(&'static mut str)), None);
+        assert_eq!(target(parse_quote!(&'a str)), None);
+        assert_eq!(target(parse_quote!(Cow<'a, str>)), None);
+        assert_eq!(target(parse_quote!(String)), None);
+    }
+
+    #[test]
+    fn infer_result_type_args() {
+        use syn::parse_quote;
+
+        let ok = |ty: Type| result_type_arg(&ty, 0).map(|ty| quote!(#ty).to_string());
+        let err = |ty: Type| result_type_arg(&ty, 1).map(|ty| quote!(#ty).to_string());
+        assert_eq!(ok(parse_quote!(Result<Foo, E>)), Some("Foo".to_owned()));
+        assert_eq!(
+            ok(parse_quote!(std::result::Result<Vec<u8>, E>)),
+            Some("Vec < u8 >".to_owned())
+        );
+        assert_eq!(ok(parse_quote!(io::Result<Foo>)), Some("Foo".to_owned()));
+        assert_eq!(ok(parse_quote!(SetupResult)), None);
+        assert_eq!(ok(parse_quote!(Option<Foo>)), None);
+        assert_eq!(ok(parse_quote!(<T as Trait>::Result<Foo>)), None);
+        assert_eq!(
+            err(parse_quote!(Result<Foo, MyError>)),
+            Some("MyError".to_owned())
+        );
+        assert_eq!(err(parse_quote!(io::Result<Foo>)), None);
+    }
+
+    #[test]
+    fn reject_contradictory_full_options() {
+        let attr: Attr = syn::parse2(quote!(STEP_1, full, pass_if = |r| r.is_err())).unwrap();
+        assert!(attr.full && attr.pass_if.is_some());
+        assert_eq!(
+            parse_error(quote!(STEP_1, full, error_display)),
+            "`error_display` cannot be combined with `full`, which never reports errors"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, pass_if = Result::is_ok)),
+            "`pass_if` requires `full`"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, full = true)),
+            "`full` doesn't take a value"
+        );
+    }
+
+    #[test]
+    fn parse_conversions() {
+        let attr: Attr = syn::parse2(quote!(TOKEN: String, map = |r| r.token())).unwrap();
+        assert!(attr.map.is_some());
+        let attr: Attr = syn::parse2(quote!(CONFIG, store_as = Arc<Config>)).unwrap();
+        assert!(attr.store_as.is_some());
+        assert_eq!(
+            parse_error(quote!(TOKEN, map = |r| r.token())),
+            "`map` requires the fixture's type, e.g. `TOKEN: String`"
+        );
+        let attr: Attr = syn::parse2(quote!(SERVER, teardown = |s| s.shutdown())).unwrap();
+        assert!(attr.teardown.is_some());
+        assert_eq!(
+            parse_error(quote!(TEMP_DIR, fresh, teardown = |d| d.close())),
+            "`teardown` cannot be combined with `fresh`"
+        );
+        assert_eq!(
+            parse_error(quote!(CONFIG: Arc<Config>, store_as = Arc<Config>, map = Arc::new)),
+            "`map` cannot be combined with `store_as`"
+        );
+    }
+
+    #[test]
+    fn parse_mut() {
+        let attr: Attr = syn::parse2(quote!(pub mut DB: Db)).unwrap();
+        assert!(attr.fixtures.mutability().is_some());
+        assert_eq!(attr.fixtures.name(), "DB");
+        assert_eq!(
+            parse_error(quote!((mut DB, LOG))),
+            "`mut` isn't supported for tuple fixtures"
+        );
+        let attr: Attr = syn::parse2(quote!(mut CONFIG, rwlock)).unwrap();
+        assert!(attr.rwlock);
+        assert_eq!(
+            parse_error(quote!(CONFIG, rwlock)),
+            "`rwlock` requires a `mut` fixture, e.g. `mut CONFIG`"
+        );
+        assert_eq!(
+            parse_error(quote!(mut DB, store_as = Arc<Db>)),
+            "`store_as` cannot be combined with a `mut` fixture"
+        );
+        assert_eq!(
+            parse_error(quote!(mut DB, clone)),
+            "`clone` cannot be combined with a `mut` fixture"
+        );
+        assert_eq!(
+            parse_error(quote!((CONFIG, LOG), clone)),
+            "`clone` isn't supported for tuple fixtures"
+        );
+        assert_eq!(
+            parse_error(quote!(mut DB, expect_panic)),
+            "`expect_panic` cannot be combined with a `mut` fixture"
+        );
+    }
+
+    #[test]
+    fn parse_fresh() {
+        let attr: Attr = syn::parse2(quote!(TEMP_DIR, fresh)).unwrap();
+        assert!(attr.fresh.is_some());
+        assert_eq!(
+            parse_error(quote!(TEMP_DIR, fresh, eager)),
+            "`eager` cannot be combined with `fresh`"
+        );
+        assert_eq!(
+            parse_error(quote!(mut TEMP_DIR, fresh)),
+            "`fresh` cannot be combined with a `mut` fixture"
+        );
+        assert_eq!(
+            parse_error(quote!((TEMP_DIR, LOG), fresh)),
+            "`fresh` isn't supported for tuple fixtures"
+        );
+        assert_eq!(
+            parse_error(quote!(TEMP_DIR, fresh, verify_injected = |_| true)),
+            "`verify_injected` cannot be combined with `fresh`"
+        );
+    }
+
+    #[test]
+    fn parse_fallback() {
+        let attr: Attr = syn::parse2(quote!(PG, fallback = 1, default)).unwrap();
+        assert!(attr.fallback.is_some() && attr.default);
+        let attr: Attr = syn::parse2(quote!(PG, fallback = 1, cfg(feature = "postgres"))).unwrap();
+        assert_eq!(
+            attr.cfg.unwrap().to_string(),
+            quote!(feature = "postgres").to_string()
+        );
+        assert_eq!(
+            parse_error(quote!(PG, default)),
+            "`default` requires `fallback`"
+        );
+        assert_eq!(
+            parse_error(quote!(PG, cfg(test))),
+            "`cfg` requires `fallback`, otherwise add `#[cfg(...)]` to the function"
+        );
+        assert_eq!(
+            parse_error(quote!((PG, URL), fallback = (1, 2))),
+            "`fallback` isn't supported for tuple fixtures"
+        );
+        assert_eq!(
+            parse_error(quote!(PG, once, fallback = 1)),
+            "`once` cannot be combined with `fallback`"
+        );
+        assert_eq!(
+            expand_error(
+                quote!(PG, fallback = 1),
+                quote!(
+                    async fn pg() -> u32 {
+                        1
+                    }
+                )
+            ),
+            "`fallback` isn't supported for `async` functions"
+        );
+    }
+
+    #[test]
+    fn parse_once() {
+        let attr: Attr = syn::parse2(quote!(STEP_1, once)).unwrap();
+        assert!(attr.once.is_some());
+        assert_eq!(
+            parse_error(quote!(mut STEP_1, once)),
+            "`mut` cannot be combined with `once`"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, once, clone)),
+            "`clone` cannot be combined with `once`"
+        );
+    }
+
+    #[test]
+    fn parse_arc() {
+        let attr: Attr = syn::parse2(quote!(POOL, arc)).unwrap();
+        assert!(attr.arc.is_some());
+        assert_eq!(
+            parse_error(quote!(mut POOL, arc)),
+            "`mut` cannot be combined with `arc`"
+        );
+        assert_eq!(
+            parse_error(quote!(POOL, arc, once)),
+            "`once` cannot be combined with `arc`"
+        );
+        assert_eq!(
+            parse_error(quote!((POOL, LOG), arc)),
+            "`arc` isn't supported for tuple fixtures"
+        );
+    }
+
+    #[test]
+    fn parse_thread_local() {
+        let attr: Attr = syn::parse2(quote!(HANDLE, thread_local)).unwrap();
+        assert!(attr.thread_local.is_some());
+        assert_eq!(
+            parse_error(quote!(mut HANDLE, thread_local)),
+            "`mut` cannot be combined with `thread_local`"
+        );
+        assert_eq!(
+            parse_error(quote!(HANDLE, thread_local, arc)),
+            "`arc` cannot be combined with `thread_local`"
+        );
+    }
+
+    #[test]
+    fn parse_local() {
+        let attr: Attr = syn::parse2(quote!(LEGACY, local)).unwrap();
+        assert!(attr.local.is_some());
+        assert_eq!(
+            parse_error(quote!(mut LEGACY, local)),
+            "`mut` cannot be combined with `local`"
+        );
+        assert_eq!(
+            parse_error(quote!(LEGACY, local, eager)),
+            "`eager` cannot be combined with `local`"
+        );
+        assert_eq!(
+            parse_error(quote!(LEGACY, local, thread_local)),
+            "`thread_local` cannot be combined with `local`"
+        );
+        assert_eq!(
+            expand_error(
+                quote!(LEGACY, local),
+                quote!(
+                    async fn legacy() -> Legacy {}
+                )
+            ),
+            "`local` isn't supported for an `async fn`"
+        );
+    }
+
+    #[test]
+    fn check_sync_fixtures() {
+        let check = |attr: proc_macro2::TokenStream, ty: proc_macro2::TokenStream| {
+            let tokens = expand_ok(
+                attr,
+                quote!(
+                    fn board() -> #ty {}
+                ),
+            );
+            let check = quote!(tested_fixture::helpers::FixtureMustBeSync<#ty>);
+            tokens.contains(&check.to_string())
+        };
+        // Checked by its bounds, so a type merely named like a standard type
+        // which isn't `Sync` is accepted
+        assert!(check(quote!(BOARD), quote!(Vec<Cell>)));
+        assert!(check(quote!(BOARD), quote!(Rc<Legacy>)));
+        assert!(!check(quote!(BOARD, local), quote!(Rc<Legacy>)));
+        assert!(!check(quote!(BOARD, fresh), quote!(Rc<Legacy>)));
+    }
+
+    #[test]
+    fn parse_releasable() {
+        let attr: Attr = syn::parse2(quote!(BUFFER, releasable)).unwrap();
+        assert!(attr.releasable.is_some());
+        assert_eq!(
+            parse_error(quote!(mut BUFFER, releasable)),
+            "`mut` cannot be combined with `releasable`"
+        );
+        assert_eq!(
+            parse_error(quote!(BUFFER, releasable, arc)),
+            "`arc` cannot be combined with `releasable`"
+        );
+        assert_eq!(
+            parse_error(quote!((BUFFER, LEN), releasable)),
+            "`releasable` isn't supported for tuple fixtures"
+        );
+    }
+
+    #[test]
+    fn parse_retries() {
+        let attr: Attr = syn::parse2(quote!(SANDBOX, retries = 2, retry_delay = "5s")).unwrap();
+        assert_eq!(attr.retries.map(|(_, n)| n), Some(2));
+        assert_eq!(attr.retry_delay, Some(Duration::from_secs(5)));
+        assert_eq!(
+            parse_error(quote!(SANDBOX, retries = 0)),
+            "`retries` must be at least 1"
+        );
+        assert_eq!(
+            parse_error(quote!(SANDBOX, retry_delay = "5s")),
+            "`retry_delay` requires `retries`"
+        );
+        assert_eq!(
+            parse_error(quote!(SANDBOX, retries = 1, fresh)),
+            "`fresh` cannot be combined with `retries`"
+        );
+        assert_eq!(
+            expand_error(
+                quote!(SANDBOX, retries = 1),
+                quote!(
+                    async fn sandbox() -> Sandbox {}
+                )
+            ),
+            "`retries` isn't supported for an `async fn`"
+        );
+    }
+
+    #[test]
+    fn parse_capture() {
+        let attr: Attr = syn::parse2(quote!(FAILURE, capture = "err")).unwrap();
+        assert_eq!(attr.capture, Capture::Err);
+        assert_eq!(
+            parse_error(quote!(FAILURE, capture = "error")),
+            "unsupported capture, expected `\"ok\"` or `\"err\"`"
+        );
+        assert_eq!(
+            parse_error(quote!(FAILURE, capture = "err", full)),
+            "`full` cannot be combined with `capture = \"err\"`"
+        );
+        assert_eq!(
+            parse_error(quote!(FAILURE, error_display, capture = "err")),
+            "`error_display` cannot be combined with `capture = \"err\"`"
+        );
+        assert_eq!(
+            parse_error(quote!(FAILURE: E, capture = "err", map = |e| e)),
+            "`map` cannot be combined with `capture = \"err\"`"
+        );
+        assert_eq!(
+            parse_error(quote!(FAILURE, capture = "err", store_as = Arc<E>)),
+            "`store_as` cannot be combined with `capture = \"err\"`"
+        );
+    }
+
+    #[test]
+    fn derive_static_names() {
+        let name = |func: proc_macro2::TokenStream| static_name(&syn::parse2(func).unwrap());
+        assert_eq!(name(quote!(build_database)), "BUILD_DATABASE");
+        assert_eq!(name(quote!(step_1)), "STEP_1");
+        assert_eq!(name(quote!(r#type)), "TYPE");
+        assert_eq!(name(quote!(_private_setup)), "_PRIVATE_SETUP");
+        assert_eq!(name(quote!(loadConfig)), "LOAD_CONFIG");
+    }
+
+    #[test]
+    fn parse_test_attr() {
+        let attr: Attr = syn::parse2(quote!(STEP_1, test_attr = test_log::test)).unwrap();
+        let path = attr.test_attr.unwrap();
+        assert_eq!(
+            quote!(#path).to_string(),
+            quote!(test_log::test).to_string()
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, test_attr)),
+            "`test_attr` requires a value"
+        );
+        assert!(syn::parse2::<Attr>(quote!(STEP_1, test_attr = "test")).is_err());
+        assert!(syn::parse2::<Attr>(quote!(STEP_1, test_attr = test::<T>)).is_err());
+    }
+
+    #[test]
+    fn parse_no_test() {
+        let attr: Attr = syn::parse2(quote!(REFERENCE_DATA, no_test)).unwrap();
+        assert!(attr.no_test);
+        assert_eq!(
+            parse_error(quote!(REFERENCE_DATA, no_test, test_attr = test_log::test)),
+            "`test_attr` cannot be combined with `no_test`"
+        );
+        assert_eq!(
+            parse_error(quote!(REFERENCE_DATA, no_test, verify_injected = |_| true)),
+            "`verify_injected` cannot be combined with `no_test`"
+        );
+        assert_eq!(
+            parse_error(quote!(REFERENCE_DATA, no_test, fallback = 1, default)),
+            "`default` cannot be combined with `no_test`"
+        );
+    }
+
+    #[test]
+    fn parse_test_name() {
+        let attr: Attr = syn::parse2(quote!(STEP_1, test_name = "fixture_step_1")).unwrap();
+        assert_eq!(attr.test_name.unwrap(), "fixture_step_1");
+        let invalid =
+            "`test_name` must be a valid identifier, e.g. `test_name = \"fixture_step_1\"`";
+        assert_eq!(parse_error(quote!(STEP_1, test_name = "step 1")), invalid);
+        assert_eq!(parse_error(quote!(STEP_1, test_name = "fn")), invalid);
+        assert_eq!(
+            parse_error(quote!(STEP_1, test_name = fixture_step_1)),
+            "expected string literal"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, no_test, test_name = "fixture_step_1")),
+            "`test_name` cannot be combined with `no_test`"
+        );
+    }
+
+    #[test]
+    fn parse_order() {
+        let attr: Attr = syn::parse2(quote!(STEP_1, order = 7)).unwrap();
+        assert_eq!(attr.order, Some(7));
+        let func = parse_quote!(step_1);
+        assert_eq!(test_ident(&attr, &func), "_007_step_1");
+        let attr: Attr =
+            syn::parse2(quote!(STEP_1, order = 12, test_name = "fixture_step_1")).unwrap();
+        assert_eq!(test_ident(&attr, &func), "_012_fixture_step_1");
+        let func = parse_quote!(r#final);
+        assert_eq!(test_ident(&attr, &func), "_012_fixture_step_1");
+        let attr: Attr = syn::parse2(quote!(FINAL, order = 999)).unwrap();
+        assert_eq!(test_ident(&attr, &func), "_999_final");
+        assert_eq!(
+            parse_error(quote!(STEP_1, order = 1000)),
+            "`order` must be at most 999"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, no_test, order = 1)),
+            "`order` cannot be combined with `no_test`"
+        );
+    }
+
+    #[test]
+    fn parse_strict() {
+        let attr: Attr = syn::parse2(quote!(STEP_1, strict)).unwrap();
+        assert!(attr.strict);
+        let tokens = expand_ok(
+            quote!(STEP_1, strict),
+            quote!(
+                fn step_1() -> Foo {}
+            ),
+        );
+        assert!(tokens.contains(&quote!(tested_fixture::helpers::Strictness::Strict).to_string()));
+        let tokens = expand_ok(
+            quote!(STEP_1),
+            quote!(
+                fn step_1() -> Foo {}
+            ),
+        );
+        assert!(tokens.contains(&quote!(tested_fixture::helpers::Strictness::Env).to_string()));
+        let tokens = expand_ok(
+            quote!(STEP_1, no_test),
+            quote!(
+                fn step_1() -> Foo {}
+            ),
+        );
+        assert!(tokens.contains(&quote!(tested_fixture::helpers::Strictness::Exempt).to_string()));
+        assert_eq!(
+            parse_error(quote!(STEP_1, strict, fallback = Foo, default)),
+            "`default` cannot be combined with `strict`"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, fresh, strict)),
+            "`strict` cannot be combined with `fresh`"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, no_test, strict)),
+            "`strict` cannot be combined with `no_test`"
+        );
+    }
+
+    #[test]
+    fn parse_invariant() {
+        let attr: Attr =
+            syn::parse2(quote!(STEP_1, invariant = |f: &Foo| f.is_consistent())).unwrap();
+        assert!(attr.invariant.is_some());
+        let tokens = expand_ok(
+            quote!(STEP_1, invariant = |f: &Foo| f.is_consistent()),
+            quote!(
+                fn step_1() -> Foo {}
+            ),
+        );
+        // Both the initialized value and every access are checked
+        let check = quote!(
+            #[cfg(debug_assertions)]
+            let fixture = tested_fixture::helpers::check_invariant("STEP_1", fixture, |f: &Foo| f.is_consistent());
+        );
+        assert!(tokens.contains(&check.to_string()));
+        assert!(
+            tokens.contains(&quote!((|f: &Foo| f.is_consistent()) as fn(&Foo) -> bool).to_string())
+        );
+        assert_eq!(
+            parse_error(quote!((LEFT, RIGHT), invariant = |_| true)),
+            "`invariant` isn't supported for tuple fixtures"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, fresh, invariant = |_| true)),
+            "`invariant` cannot be combined with `fresh`"
+        );
+        assert_eq!(
+            parse_error(quote!(mut STEP_1, invariant = |_| true)),
+            "`invariant` cannot be combined with a `mut` fixture"
+        );
+    }
+
+    #[test]
+    fn parse_verify_unchanged() {
+        let attr: Attr = syn::parse2(quote!(STEP_1, verify_unchanged)).unwrap();
+        assert!(matches!(
+            attr.verify_unchanged,
+            Some(VerifyUnchanged::Hash(_))
+        ));
+        let attr: Attr = syn::parse2(quote!(STEP_1, verify_unchanged = |v: &Foo| v.len())).unwrap();
+        assert!(matches!(
+            attr.verify_unchanged,
+            Some(VerifyUnchanged::Fingerprint(_))
+        ));
+        let tokens = expand_ok(
+            quote!(STEP_1, verify_unchanged),
+            quote!(
+                fn step_1() -> Foo {}
+            ),
+        );
+        let fingerprint = quote!(tested_fixture::helpers::hash_fingerprint::<Foo>).to_string();
+        assert!(tokens.contains(&fingerprint));
+        assert_eq!(
+            parse_error(quote!((LEFT, RIGHT), verify_unchanged)),
+            "`verify_unchanged` isn't supported for tuple fixtures"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, thread_local, verify_unchanged)),
+            "`verify_unchanged` cannot be combined with `thread_local`"
+        );
+    }
+
+    #[test]
+    fn parse_requires_env() {
+        let attr: Attr = syn::parse2(quote!(
+            STEP_1,
+            requires_env = "DATABASE_URL",
+            requires_env = "REDIS_URL"
+        ))
+        .unwrap();
+        let vars: Vec<_> = attr.requires_env.iter().map(LitStr::value).collect();
+        assert_eq!(vars, ["DATABASE_URL", "REDIS_URL"]);
+        let tokens = expand_ok(
+            quote!(STEP_1, requires_env = "DATABASE_URL"),
+            quote!(
+                fn step_1() -> Foo {}
+            ),
+        );
+        // The variables are checked by the fixture, and a skip passes its test
+        let check = quote!(tested_fixture::helpers::require_env(&["DATABASE_URL"]);).to_string();
+        assert!(tokens.contains(&check));
+        assert!(tokens.contains("tested_fixture :: helpers :: unless_skipped"));
+        assert_eq!(
+            parse_error(quote!(STEP_1, requires_env = "A", requires_env = "A")),
+            "duplicate `requires_env` variable `A`"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, requires_env = "")),
+            "`requires_env` requires a variable name, e.g. `requires_env = \"DATABASE_URL\"`"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, fresh, requires_env = "A")),
+            "`fresh` cannot be combined with `requires_env`"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, no_catch_unwind, requires_env = "A")),
+            "`no_catch_unwind` cannot be combined with `requires_env`"
+        );
+    }
+
+    #[test]
+    fn parse_alternative() {
+        let attr: Attr =
+            syn::parse2(quote!(STEP_1, alternative(cfg(miri), stubs::cheap_step_1))).unwrap();
+        let alternative = attr.alternative.unwrap();
+        let function = &alternative.function;
+        assert_eq!(alternative.predicate.to_string(), "miri");
+        assert_eq!(
+            quote!(#function).to_string(),
+            quote!(stubs::cheap_step_1).to_string()
+        );
+        let tokens = expand_ok(
+            quote!(STEP_1, alternative(cfg(miri), cheap_step_1)),
+            quote!(
+                fn step_1() -> Foo {}
+            ),
+        );
+        let run = quote!(if ::std::cfg!(miri) {
+            return tested_fixture::helpers::alternative(cheap_step_1);
+        });
+        assert!(tokens.contains(&run.to_string()));
+        let message =
+            "`alternative` requires a `cfg` and a function, e.g. `alternative(cfg(miri), cheap_builder)`";
+        assert_eq!(parse_error(quote!(STEP_1, alternative)), message);
+        assert_eq!(
+            parse_error(quote!(STEP_1, alternative(miri, cheap_step_1))),
+            message
+        );
+        assert_eq!(parse_error(quote!(STEP_1, alternative(cfg(miri)))), message);
+        assert_eq!(
+            parse_error(quote!(
+                STEP_1,
+                alternative(cfg(miri), a),
+                alternative(cfg(miri), b)
+            )),
+            "duplicate `alternative` option"
+        );
+    }
+
+    #[test]
+    fn coverage_exempt_glue() {
+        let tokens = expand_ok(
+            quote!(STEP_1),
+            quote!(
+                fn step_1() -> u32 {
+                    compute()
+                }
+            ),
+        );
+        // The body is spliced once, into a function which is still measured
+        let body = quote!(tested_fixture::helpers::coverage_on! {
+            fn fixture_body() -> u32 {
+                compute()
+            }
+        });
+        assert_eq!(tokens.matches("compute ()").count(), 1);
+        assert!(tokens.contains(&body.to_string()));
+        // While the generated functions around it aren't
+        let off = quote!(tested_fixture::helpers::coverage_off!).to_string();
+        let init = format!(
+            "{} {{ # [allow (clippy :: type_complexity)] fn init ()",
+            off
+        );
+        assert!(tokens.contains(&init));
+        let test = format!(
+            "{} {{ # [test] # [allow (clippy :: type_complexity)] fn step_1 ()",
+            off
+        );
+        assert!(tokens.contains(&test));
+        assert_eq!(tokens.matches(&off).count(), 2);
+    }
+
+    /// The line of the first token in `tokens` (recursively) spelled `text`
+    fn line_of(tokens: proc_macro2::TokenStream, text: &str) -> Option<usize> {
+        tokens.into_iter().find_map(|token| match token {
+            TokenTree::Group(group) => line_of(group.stream(), text),
+            token if token.to_string() == text => Some(token.span().start().line),
+            _ => None,
+        })
+    }
+
+    /// The line opening the innermost group around the first token in
+    /// `tokens` spelled `text`
+    fn group_line_of(tokens: proc_macro2::TokenStream, text: &str) -> Option<usize> {
+        tokens.into_iter().find_map(|token| match token {
+            TokenTree::Group(group) => {
+                let direct = group
+                    .stream()
+                    .into_iter()
+                    .any(|token| token.to_string() == text);
+                match direct {
+                    true => Some(group.span_open().start().line),
+                    false => group_line_of(group.stream(), text),
+                }
+            }
+            _ => None,
+        })
+    }
+
+    // Generated tokens are spanned at the call site, which the fallback
+    // implementation of spans (used outside of a proc macro) puts on line 1,
+    // so the user's tokens are written below it
+    #[test]
+    fn body_spans() {
+        let expand = |attr: &str, item: &str| {
+            let found_crate = parse_quote!(tested_fixture);
+            let attr = syn::parse_str(attr).unwrap();
+            let func = syn::parse_str(item).unwrap();
+            expand(&found_crate, attr, func).unwrap()
+        };
+        let item = "fn setup()\n    -> u32\n{\n    let x: u32 = \"oops\";\n    x\n}";
+        let async_item = format!("async {}", item);
+        for (attr, item) in [
+            ("SETUP", item),
+            ("SETUP", &async_item),
+            ("SETUP, expose_fn = run_setup", item),
+            ("SETUP, test_name = \"setup_test\"", item),
+            ("SETUP: u32, full", item),
+            ("SETUP, requires_env = \"PATH\"", item),
+            ("SETUP, alternative(cfg(miri), || 1)", item),
+        ] {
+            let tokens = expand(attr, item);
+            assert_eq!(group_line_of(tokens, "\"oops\""), Some(3), "{}", attr);
+        }
+
+        // The boxed storage of an `impl Trait` is reported at the return type
+        let item = "fn setup()\n    -> impl std::fmt::Debug\n{\n    1\n}";
+        let tokens = expand("SETUP", item);
+        assert_eq!(line_of(tokens, "Box"), Some(2));
+    }
+
+    #[test]
+    fn parse_sections() {
+        let attr: Attr = syn::parse2(quote!(
+            static_attrs(allow(dead_code), doc = "step"),
+            test_attrs(ignore),
+            #[cfg(unix)]
+            STEP_1,
+        ))
+        .unwrap();
+        let Fixtures::Single(binding) = attr.fixtures else {
+            panic!("expected a single fixture")
+        };
+        let attrs = &binding.attrs;
+        assert_eq!(
+            quote!(#(#attrs)*).to_string(),
+            quote!(#[cfg(unix)] #[allow(dead_code)] #[doc = "step"]).to_string()
+        );
+        let test_attrs = &attr.test_attrs;
+        assert_eq!(
+            quote!(#(#test_attrs)*).to_string(),
+            quote!(ignore).to_string()
+        );
+
+        let attr: Attr =
+            syn::parse2(quote!((LEFT, RIGHT), static_attrs(allow(dead_code)))).unwrap();
+        let Fixtures::Tuple(_, bindings) = attr.fixtures else {
+            panic!("expected tuple fixtures")
+        };
+        assert!(bindings.iter().all(|binding| binding.attrs.len() == 1));
+
+        assert_eq!(
+            parse_error(quote!(STEP_1, test_attrs)),
+            "`test_attrs` requires a list of attributes, e.g. `test_attrs(ignore)`"
+        );
+        assert_eq!(
+            parse_error(quote!(test_attrs(ignore), STEP_1, test_attrs(ignore))),
+            "duplicate `test_attrs` option"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, no_test, test_attrs(ignore))),
+            "`test_attrs` cannot be combined with `no_test`"
+        );
+    }
+
+    #[test]
+    fn allow_lowercase_fixtures() {
+        let attr: Attr = syn::parse2(quote!((step_1_fixture, STEP_1_LOG, r#type))).unwrap();
+        let Fixtures::Tuple(_, bindings) = attr.fixtures else {
+            panic!("expected tuple fixtures")
+        };
+        let allowed: Vec<_> = bindings
+            .iter()
+            .map(|binding| {
+                let attrs = &binding.attrs;
+                quote!(#(#attrs)*).to_string()
+                    == quote!(#[allow(non_upper_case_globals)]).to_string()
+            })
+            .collect();
+        assert_eq!(allowed, [true, false, true]);
+    }
+
+    #[test]
+    fn parse_crate_path() {
+        let path = |attr: proc_macro2::TokenStream| {
+            let attr: Attr = syn::parse2(attr).unwrap();
+            let krate = attr.krate.unwrap();
+            quote!(#krate).to_string()
+        };
+        let expected = quote!(testkit::fixtures).to_string();
+        assert_eq!(path(quote!(crate = testkit::fixtures, STEP_1)), expected);
+        assert_eq!(path(quote!(STEP_1, crate = testkit::fixtures)), expected);
+        assert_eq!(
+            path(quote!(crate = crate::fixtures, (A, B))),
+            quote!(crate::fixtures).to_string()
+        );
+        assert_eq!(
+            parse_error(quote!(crate = a, STEP_1, crate = b)),
+            "duplicate `crate` option"
+        );
+        assert_eq!(
+            parse_error(quote!(STEP_1, crate)),
+            "`crate` requires a value"
+        );
+    }
+
+    #[test]
+    fn parse_export_to() {
+        let attr: Attr =
+            syn::parse2(quote!(pub(crate) STEP_1, export_to = crate::test_fixtures)).unwrap();
+        let path = attr.export_to.as_ref().unwrap();
+        assert_eq!(
+            quote!(#path).to_string(),
+            quote!(crate::test_fixtures).to_string()
+        );
+        let binding = &attr.fixtures.bindings()[0];
+        assert!(matches!(binding.vis, Visibility::Restricted(_)));
+
+        assert_eq!(
+            parse_error(quote!(STEP_1, export_to = crate::test_fixtures)),
+            "`export_to` requires a fixture visible outside its module, e.g. `pub(crate) STEP_1`"
+        );
+        assert_eq!(
+            parse_error(quote!((pub(crate) LEFT, RIGHT), export_to = crate::test_fixtures)),
+            "`export_to` requires a fixture visible outside its module, e.g. `pub(crate) RIGHT`"
+        );
+
+        let attr: Attr = syn::parse2(quote!(pub STEP_1, export_to = fixtures)).unwrap();
+        assert!(matches!(
+            attr.fixtures.bindings()[0].vis,
+            Visibility::Public(_)
+        ));
+
+        assert_eq!(
+            parse_error(quote!(pub STEP_1, export_to = "fixtures")),
+            "`export_to` requires a module path, e.g. `export_to = crate::test_fixtures`"
+        );
+        assert_eq!(
+            parse_error(quote!(pub STEP_1, export_to = 1)),
+            "`export_to` requires a module path, e.g. `export_to = crate::test_fixtures`"
+        );
+    }
+
+    #[test]
+    fn propagate_cfg_attrs() {
+        let func: ItemFn = parse_quote!(
+            #[cfg(feature = "integration")]
+            #[cfg_attr(miri, ignore)]
+            #[cfg_attr(not(unix), cfg(any()))]
+            #[allow(unused)]
+            fn step_1() {}
+        );
+        let cfgs = item_cfgs(&func.attrs);
+        assert_eq!(cfgs, [func.attrs[0].clone(), func.attrs[2].clone()]);
+
+        let tokens = propagate_cfgs(
+            quote!(
+                static STEP_1: Foo = Foo;
+                const _: () = ();
+                register!(STEP_1);
+                #[test]
+                fn step_1() {}
+            ),
+            &func.sig.ident,
+            &cfgs,
+        )
+        .unwrap();
+        let file: File = syn::parse2(tokens).unwrap();
+        let cfg_counts: Vec<_> = file
+            .items
+            .iter()
+            .map(|item| match item {
+                Item::Static(item) => item.attrs.len(),
+                Item::Const(item) => item.attrs.len(),
+                Item::Macro(item) => item.attrs.len(),
+                Item::Fn(item) => item.attrs.len() - 1,
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(cfg_counts, [2, 2, 2, 0]);
+    }
+
+    #[test]
+    fn parse_complex_types() {
+        for ty in [
+            quote!(<Foo as Provider>::Output),
+            quote!(Vec<Cow<'static, str>>),
+            quote!(Wrapper::<u8>),
+            quote!(HashMap<u32, Vec<Option<Box<dyn Fn(u8) -> u8 + Send + Sync>>>>),
+            quote!(&'static [(u8, <T as Trait<'static>>::Assoc)]),
+        ] {
+            let attr: Attr = syn::parse2(quote!(STEP: #ty, error_display)).unwrap();
+            assert_eq!(attr.fixtures.ty(), Some(syn::parse2(ty).unwrap()));
+            assert!(attr.error_display);
+        }
+        assert_eq!(
+            parse_error(quote!(STEP: impl Display)),
+            "`impl Trait` can't be part of a fixture's type, since statics can't have opaque types; use `dyn Trait` instead"
+        );
+        assert_eq!(
+            parse_error(quote!((LEFT, RIGHT: Vec<impl Display>))),
+            "`impl Trait` can't be part of a fixture's type, since statics can't have opaque types; use `dyn Trait` instead"
+        );
+    }
+
+    #[test]
+    fn parse_tuple_fixtures() {
+        let attr: Attr =
+            syn::parse2(quote!((CONN, pub SCHEMA: SchemaInfo,), runtime = "tokio")).unwrap();
+        assert_eq!(attr.fixtures.name(), "(CONN, SCHEMA)");
+        assert_eq!(parse_error(quote!(())), "expected at least one fixture");
+        let attr: Attr = syn::parse2(quote!((r#static, r#MATCH: u32))).unwrap();
+        assert_eq!(attr.fixtures.name(), "(static, MATCH)");
+
+        let attr: Attr = syn::parse2(quote!(pub FOO: Foo, BAR_LOG: Vec<Entry>,)).unwrap();
+        assert_eq!(attr.fixtures.name(), "(FOO, BAR_LOG)");
+        let attr: Attr = syn::parse2(quote!(
+            FOO,
+            #[doc = "log"]
+            BAR_LOG,
+            error_display
+        ))
+        .unwrap();
+        assert_eq!(attr.fixtures.name(), "(FOO, BAR_LOG)");
+        assert!(attr.error_display);
+        let attr: Attr = syn::parse2(quote!(FOO, bar_log: Vec<Entry>)).unwrap();
+        assert_eq!(attr.fixtures.name(), "(FOO, bar_log)");
+        let attr: Attr = syn::parse2(quote!(FOO, fallback = Foo, cfg(unix))).unwrap();
+        assert_eq!(attr.fixtures.name(), "FOO");
+        assert_eq!(
+            parse_error(quote!(FOO, mut BAR)),
+            "`mut` isn't supported for tuple fixtures"
+        );
+        assert_eq!(
+            expand_error(
+                quote!(FOO, BAR),
+                quote!(
+                    fn step_2() -> (Foo, Vec<Entry>, Log) {}
+                )
+            ),
+            "expected 3 fixtures to match the returned tuple, found 2"
+        );
+        assert_eq!(
+            expand_error(
+                quote!((CONN, SCHEMA)),
+                quote!(
+                    fn step_2() -> (Connection, SchemaInfo, Log) {}
+                )
+            ),
+            "expected 3 fixtures to match the returned tuple, found 2"
+        );
+        assert_eq!(
+            expand_error(
+                quote!((CONN, SCHEMA)),
+                quote!(
+                    fn step_2() -> Stage2 {}
+                )
+            ),
+            "cannot infer the type of `CONN` from the return type, specify it as `CONN: Type`"
+        );
+    }
+
+    #[test]
+    fn reject_non_object_safe_impl_trait() {
+        assert_eq!(
+            expand_error(
+                quote!(RECORDS),
+                quote!(fn records() -> impl Iterator<Item = Record> + Clone {})
+            ),
+            "fixtures returned as `impl Trait` are stored as `Box<dyn Trait>`, but `Clone` can't be made into a trait object; return a concrete type instead"
+        );
+        assert_eq!(
+            expand_error(
+                quote!(RECORDS),
+                quote!(fn records() -> Result<impl Display + Debug + Send, E> {})
+            ),
+            "fixtures returned as `impl Trait` are stored as `Box<dyn Trait>`, which can't combine `Display` and `Debug`; return a concrete type instead"
+        );
+    }
 }
@@ -119,221 +119,6042 @@
 //! `step_1` test should still succeed (or fail) regardless of if it is run
 //! first or not.
 //!
+//! The name of the fixture can also be left out, in which case it's the name
+//! of the function in upper snake case, so `#[tested_fixture] fn step_1()`
+//! declares `STEP_1`.
+//!
 //! ## Advanced usage
 //!
 //! The [`tested_fixture`] attribute supports attributes and a visibility level
-//! prefixing the identifier, as well as an optional `: type` suffix. This
-//! optional suffix can be used on tests returning a `Result` to specify that
-//! only `Ok` return values should be captured. For example:
+//! prefixing the identifier, as well as an optional `: type` suffix. For tests
+//! whose return type is spelled `Result<Foo, E>` (including paths like
+//! `std::io::Result<Foo>`), only `Ok` return values are captured by default,
+//! so `*STEP_1` below is a `Foo` (and `&*STEP_1` a `&'static Foo`):
 //!
 //! ```
 //! #[tested_fixture::tested_fixture(
 //!     /// Doc comment on the `STEP_1` global variable
-//!     pub(crate) STEP_1: Foo
+//!     pub(crate) STEP_1
 //! )]
 //! fn step_1() -> Result<Foo, &'static str> {
 //!     // ...
 //! }
 //! ```
 //!
+//! The optional suffix specifies the fixture type explicitly. This is required
+//! to capture only the `Ok` values of results hidden behind type aliases, and
+//! can also be used to store the entire `Result` (e.g. `STEP_1: Result<Foo, E>`).
+//! Alternatively naming the error type with `error = E` is enough for the `Ok`
+//! type to be inferred through an alias, and checks that the function actually
+//! returns that error type.
+//! The suffix also works for tests returning an `Option`, in which case only
+//! `Some` values are captured and `None` fails the test (and reports
+//! `returned None` to dependent tests).
+//!
+//! Values which are already `'static`, such as a returned `&'static str` or
+//! `Cow<'static, str>`, are exposed directly as a `str` fixture. Likewise a
+//! returned `Pin<Box<T>>` (e.g. a self-referential value) is exposed as a `T`
+//! fixture, since it never moves out of the fixture's storage. Any other
+//! borrow (e.g. `-> &Config`) is rejected, since fixtures must own their data.
+//!
+//! The type may also be unsized, in which case the returned value is kept
+//! alive and the fixture borrows from it (via [`std::borrow::Borrow`]). For
+//! instance `CONFIG: str` can be produced by a test returning a `String`,
+//! `BLOB: [u8]` by one returning a `Vec<u8>`, and `SERVICE: dyn Service` by one
+//! returning a `Box<dyn Service>`.
+//!
+//! A test producing several values can split a returned tuple into one fixture
+//! per element by listing the fixtures (optionally with their types), either in
+//! parentheses or directly before any options. The function still only runs
+//! once, and if it fails then every one of the fixtures reports the same
+//! failure:
+//!
+//! ```
+//! #[tested_fixture::tested_fixture((CONN, SCHEMA: SchemaInfo))]
+//! fn step_2() -> Result<(Connection, SchemaInfo), &'static str> {
+//!     // ...
+//! }
+//! ```
+//!
+//! Tests which set something up purely for its side effects can return `()`
+//! (or `Result<(), E>`), in which case the fixture is a zero-sized handle whose
+//! only purpose is to gate later tests on the setup succeeding, by calling
+//! `MIGRATED.ensure()` (or `MIGRATED.ensure_async().await` for `async`
+//! functions):
+//!
+//! ```
+//! #[tested_fixture::tested_fixture(MIGRATED)]
+//! fn migrate() -> Result<(), &'static str> {
+//!     // ...
+//! }
+//! ```
+//!
+//! Conversely a fixture which isn't worth reporting as a test of its own, such
+//! as reference data loaded by many tests, can be declared `no_test`. It's
+//! then only initialized by its dependents, which still report its failures:
+//!
+//! ```
+//! #[tested_fixture::tested_fixture(REFERENCE_DATA, no_test)]
+//! fn reference_data() -> Result<Vec<Record>, std::io::Error> {
+//!     // ...
+//! }
+//! ```
+//!
+//! Otherwise the generated test is named after the function, unless renamed
+//! with `test_name = "..."`. The test harness does start tests in alphabetical
+//! order, so to have producing tests usually populate the cache themselves
+//! (keeping per-test timings meaningful), `order = N` prefixes the test's name
+//! with `_` and `N` padded to three digits. For instance
+//! `#[tested_fixture(STEP_1, order = 0)] fn step_1()` generates the test
+//! `_000_step_1`, which sorts before the other tests of its module (and before
+//! tests with a greater `order`), and can be selected with
+//! `--exact tests::_000_step_1`. Either way the function itself keeps its name.
+//!
+//! Conversely, a fixture being initialized by dependents can hide that its
+//! test didn't run first, or at all (e.g. in filtered or partitioned runs). A
+//! `strict` fixture panics in any dependent which finds it uninitialized, with
+//! "STEP_1 was not initialized by its own test; run order/selection problem",
+//! and setting the `TESTED_FIXTURE_STRICT` environment variable makes every
+//! fixture strict. Fixtures without a test initializing them (`no_test` and
+//! `default` ones) are exempt, as are uncached `fresh` and `thread_local`
+//! fixtures. Dependents still wait for a fixture which its test is already
+//! initializing. Note that runners which run each test in its own process,
+//! such as `cargo nextest`, fail every dependent of a strict fixture, since
+//! the fixture's own test never runs in that process.
+//!
+//! Since fixtures are stored in statics, they can't have opaque types. Instead
+//! functions returning `impl Trait` (or a `Result` or `Option` of one) produce
+//! a `Box<dyn Trait + Send + Sync>` fixture. This requires the bounds to be
+//! usable as a trait object, so for instance `impl Clone` isn't supported.
+//!
+//! The captured value can also be converted before being stored, for instance
+//! with `store_as = Arc<Config>` a test returning a `Config` (or a
+//! `Result<Config, E>`) produces an `Arc<Config>` fixture, via
+//! [`Into`]. The conversion happens once, as part of the fixture's setup.
+//!
+//! Alternatively `map` can transform the captured value with a closure, as long
+//! as the fixture's type is given. Like the function's body, the closure runs
+//! once, and panicking fails the fixture:
+//!
+//! ```
+//! #[tested_fixture::tested_fixture(TOKEN: String, map = |resp| resp.session_token().to_owned())]
+//! fn log_in() -> Result<HttpResponse, &'static str> {
+//!     // ...
+//! }
+//! ```
+//!
+//! Adding the `full` flag instead stores the entire return value (so the `: type`
+//! suffix, if any, must be the function's return type), allowing later tests to
+//! inspect either arm of a `Result`. Such tests pass unless they panic, or
+//! unless they are rejected by an optional `pass_if` predicate:
+//!
+//! ```
+//! #[tested_fixture::tested_fixture(OUTCOME, full, pass_if = Result::is_err)]
+//! fn parse_invalid() -> Result<u32, std::num::ParseIntError> {
+//!     "not a number".parse()
+//! }
+//! ```
+//!
+//! Since `full` fixtures never report errors, `full` can't be combined with
+//! `error_display`:
+//!
+//! ```compile_fail
+//! #[tested_fixture::tested_fixture(OUTCOME, full, error_display)]
+//! fn parse_invalid() -> Result<u32, std::num::ParseIntError> {
+//!     "not a number".parse()
+//! }
+//! ```
+//!
+//! A test whose purpose is to fail in a specific way can instead capture
+//! its error with `capture = "err"`. The test then passes only if the function
+//! returns `Err`, and the fixture is an `E`:
+//!
+//! ```
+//! #[tested_fixture::tested_fixture(FAILURE, capture = "err")]
+//! fn parse_invalid() -> Result<u32, std::num::ParseIntError> {
+//!     "not a number".parse()
+//! }
+//! ```
+//!
+//! Dependent tests which can do useful work even if a fixture failed (or which
+//! would rather skip themselves) can call `STEP_1.try_get()`, which returns the
+//! [`FixtureError`] of the failed test instead of panicking, along with the
+//! test's name and message.
+//!
+//! Conversely a stage whose correct behavior is to panic can use the
+//! `expect_panic` flag (in place of `#[should_panic]`), which captures the
+//! panic message as a `String` fixture and fails the test if the function
+//! returns normally.
+//!
+//! When built with `panic = "abort"` panics can't be caught at all, so while
+//! returned errors are still reported to dependent tests, a panicking fixture
+//! function aborts the test binary (after printing a note explaining why).
+//!
+//! Panics can be left uncaught with the `no_catch_unwind` flag (e.g. for bodies
+//! calling into code which isn't unwind-safe), at the cost of a panicking
+//! function being run again, and panicking afresh, for each dependent test.
+//!
+//! Errors are reported to dependent tests using their `Debug` output, except
+//! for boxed `dyn Error`s, `anyhow::Error` and `eyre::Report` (with the
+//! `anyhow` or `eyre` feature enabled), which are reported using `Display`
+//! along with their chain of causes, e.g.
+//! `connect to db — caused by: connection refused`. Errors which implement
+//! `Display` but not `Debug` can be reported by adding the `error_display` flag,
+//! as in `#[tested_fixture(STEP_1: Foo, error_display)]`. Errors needn't be
+//! `Send` or `Sync` (e.g. `Box<dyn Error>`), since they're described as soon
+//! as they're caught, but must be `'static`. Likewise if the function panics
+//! (e.g. a failed `assert_eq!`), dependent tests report its
+//! panic message and location, along with its backtrace if `RUST_BACKTRACE`
+//! is set. Such panics aren't printed when they happen, so the backtrace is
+//! only reported once, by the first test to report the failure. Either way the
+//! report names the failed fixture and says whether it returned an error or
+//! panicked, e.g. `fixture STEP_1 (fn step_1) panicked: ...`. Failures which
+//! cascade through other fixtures also name the fixtures being initialized,
+//! e.g. `fixture STEP_1 (fn step_1) panicked (while initializing STEP_2,
+//! required by tests::step_3): ...`. A panic's location is the line of the
+//! fixture's body which panicked, or which called a `#[track_caller]` helper
+//! that did, while the dependent itself panics at the line which accessed the
+//! failed fixture. A dependent fixture reports that line after the original
+//! location, as in `... at src/steps.rs:4:9 (accessed at src/steps.rs:9:5)`.
+//!
+//! Fixtures can also be produced by `async` functions. The body is driven to
+//! completion by a minimal executor bundled with this crate (or by
+//! `smol::block_on` if the `smol` feature is enabled), and the resolved value
+//! is cached exactly like a synchronous fixture:
+//!
+//! ```
+//! #[tested_fixture::tested_fixture(STEP_1)]
+//! async fn step_1() -> Foo {
+//!     let foo = Foo::connect().await;
+//!     // Complicated assertions verify step 1...
+//!     foo
+//! }
+//! ```
+//!
+//! Tests which are themselves `async` can also await a fixture produced by an
+//! `async` function with `STEP_1.get_async().await`, rather than blocking the
+//! executor while the fixture is initialized.
+//!
+//! Fixtures which need a real reactor (timers, sockets, etc.) can instead be
+//! run on a Tokio runtime by enabling the `tokio` feature and specifying
+//! `runtime = "tokio"`. Such fixtures can be safely forced from within other
+//! `#[tokio::test]`s. By default a current-thread runtime is used, but similar
+//! to `#[tokio::test]` the `flavor` and `worker_threads` can also be specified.
+//!
+//! ```
+//! #[tested_fixture::tested_fixture(
+//!     STEP_1,
+//!     runtime = "tokio",
+//!     flavor = "multi_thread",
+//!     worker_threads = 4,
+//! )]
+//! async fn step_1() -> Foo {
+//!     // ...
+//! }
+//! ```
+//!
+//! Since each Tokio fixture is run on its own runtime, any tasks it spawns are
+//! stopped once it completes. When several fixtures need to share a reactor
+//! (e.g. a server spawned by one fixture and used by later tests), a
+//! [`RuntimeFixture`] can be used as a fixture itself:
+//!
+//! ```
+//! #[tested_fixture::tested_fixture(RUNTIME: tested_fixture::RuntimeFixture)]
+//! fn runtime() -> std::io::Result<tested_fixture::RuntimeFixture> {
+//!     tested_fixture::RuntimeFixture::new()
+//! }
+//!
+//! #[tested_fixture::tested_fixture(SERVER)]
+//! fn server() -> std::net::SocketAddr {
+//!     let listener = RUNTIME.block_on(tokio::net::TcpListener::bind("127.0.0.1:0")).unwrap();
+//!     let addr = listener.local_addr().unwrap();
+//!     RUNTIME.handle().spawn(serve(listener));
+//!     addr
+//! }
+//! ```
+//!
+//! Fixture functions producing `!Send` futures (e.g. holding an `Rc` across an
+//! `.await` or using `tokio::task::spawn_local`) can be run on a `LocalSet` by
+//! specifying `runtime = "tokio-local"` instead. The resulting fixture must
+//! still be `Sync` however.
+//!
+//! When one Tokio fixture is forced while initializing another (or from a
+//! multi-threaded `#[tokio::test]`), the already running multi-threaded runtime
+//! is reused rather than nesting runtimes. Inside `async` fixture functions,
+//! awaiting other fixtures with `get_async` avoids blocking altogether.
+//!
+//! Similarly async-std is supported by enabling the `async-std` feature and
+//! specifying `runtime = "async-std"`. Any other executor can be used by
+//! specifying a `block_on = path::to::block_on` function, which will be called
+//! with the future produced by the fixture function.
+//!
+//! A fixture which may hang can be given a deadline with `timeout = "30s"`
+//! (durations like `"500ms"` or `"1m 30s"` are also accepted). If its body
+//! hasn't completed in time, the fixture fails as though it had returned an
+//! error, so dependent tests report `STEP_1 timed out after 30s` rather than
+//! hanging. An `async` body is cancelled, but since the timeout can only take
+//! effect when it yields, blocking calls inside it can't be interrupted.
+//! Synchronous bodies are instead run on a helper thread (so their result must
+//! be `Send`), which is left running in the background if it times out.
+//! Likewise `stack_size = 16 * 1024 * 1024` runs a synchronous body on a
+//! helper thread with a stack of that many bytes, for deeply recursive fixture
+//! functions, while its result (or panic) is reported as usual.
+//!
+//! A fixture which is expensive to build can be started as soon as the test
+//! binary launches, so its cost overlaps with other tests, by enabling the
+//! `eager` feature and adding the `eager` flag, as in
+//! `#[tested_fixture(STEP_1, eager)]`. It is still built only once, its
+//! failure is still reported by its test and dependents, and its test still
+//! verifies it.
+//!
+//! A fixture declared with the `clone` flag, as in
+//! `#[tested_fixture(CONFIG, clone)]`, is still built once, but dependents
+//! receive their own copy using `CONFIG.cloned()` rather than sharing a
+//! reference, so that one test's changes can't leak into another.
+//!
+//! A fixture which can't be shared at all (e.g. a temporary directory which
+//! each test modifies) can be declared with the `fresh` flag, as in
+//! `#[tested_fixture(TEMP_DIR, fresh)]`. Its test still verifies the function
+//! once, but the fixture is **not cached**: each call to `TEMP_DIR.get()` runs
+//! the function again and returns the value it produced (the `Ok` value of a
+//! returned `Result`, or otherwise the whole value).
+//!
+//! A fixture which the next stage must own rather than borrow (e.g. one
+//! consumed by `finish(self)`) can be declared with the `once` flag, as in
+//! `#[tested_fixture(STEP_1, once)]`. Its test still verifies it, and a single
+//! dependent can then move it out with `STEP_1.take()`, which panics if
+//! another test has already taken it.
+//!
+//! A fixture declared with the `arc` flag, as in
+//! `#[tested_fixture(POOL, arc)]`, is stored in an [`Arc`](std::sync::Arc).
+//! It dereferences like any other fixture, but `POOL.share()` also returns an
+//! owned `Arc` which can be moved into spawned threads and tasks.
+//!
+//! Fixtures are otherwise kept alive for the entire run, which a fixture
+//! holding a large buffer can avoid by adding the `releasable` flag, as in
+//! `#[tested_fixture(BUFFER, releasable)]`. It is then borrowed with
+//! `BUFFER.read()`, and dropped by calling `BUFFER.release()` from its last
+//! dependent, after which any other access panics with "fixture BUFFER
+//! already released".
+//!
+//! Fixtures must be `Sync` to be shared between tests. A fixture which isn't
+//! (e.g. one containing `Cell`s or `Rc`s) can instead be built once per thread
+//! by adding the `thread_local` flag, as in
+//! `#[tested_fixture(HANDLE, thread_local)]`. Since it can't be borrowed for
+//! `'static`, it is accessed using `HANDLE.with(|handle| ...)`.
+//!
+//! Otherwise the first error for a fixture which isn't `Sync` is reported at
+//! the attribute, as "required by a bound in `FixtureMustBeSync`". It names
+//! the type which can't be shared between threads, such as `Rc<u32>` below,
+//! and is followed by errors about the static storing the fixture:
+//!
+//! ```compile_fail,E0277
+//! # use tested_fixture::tested_fixture_doctest as tested_fixture;
+//! pub struct Session {
+//!     pub id: std::rc::Rc<u32>,
+//! }
+//!
+//! #[tested_fixture(SESSION, exposed(all()))]
+//! fn connect() -> Session {
+//!     Session { id: std::rc::Rc::new(1) }
+//! }
+//! ```
+//!
+//! Alternatively a fixture declared `local`, as in
+//! `#[tested_fixture(LEGACY, local)]`, is built once and dereferences like any
+//! other fixture, but can only be used by the thread which built it; any other
+//! thread panics. This requires running tests on a single thread, with
+//! `cargo test -- --test-threads=1`, and such fixtures aren't listed by
+//! [`registry`].
+//!
+//! Each fixture's [`FixtureHandle`] can also describe its initialization,
+//! without triggering it, which can help to debug flaky ordering:
+//! `STEP_1.state()` returns a [`FixtureState`] (e.g. `Initializing` or
+//! `Failed`), `STEP_1.init_duration()` how long the function took, and
+//! `STEP_1.triggered_by()` the name of the test which first required it. The
+//! handle of an `async` or `arc` fixture is returned by `STEP_1.handle()`.
+//!
+//! While debugging, a fixture can also be injected with a hand-built value
+//! before it starts initializing (e.g. to point `SERVER_URL` at an already
+//! running server) using `SERVER_URL.set(value)`, which fails with
+//! [`AlreadyInitialized`] if it's too late. Its test then passes without
+//! running the function, unless the fixture is declared with a
+//! `verify_injected = |value| ...` predicate which rejects the value.
+//!
+//! A fixture whose function can't run under some configuration (e.g. under
+//! Miri, which can't spawn processes or open sockets) can instead be given an
+//! `alternative`, as in `alternative(cfg(miri), in_memory_server)`, which is
+//! called in place of the fixture function whenever the `cfg` is active. The
+//! fixture's static, type and caching are unchanged, so dependents still run
+//! against the stand-in. Both functions are always compiled, and must return
+//! the same type.
+//!
+//! A fixture whose function is compiled out with `#[cfg(...)]` (or a
+//! `#[cfg_attr(..., cfg(...))]`) is compiled out along with it, leaving its
+//! dependents referring to a missing static. Instead the condition can be
+//! passed to the fixture as `cfg(...)` along with a `fallback = expr` value,
+//! which dependents use whenever the function is compiled out (noting that
+//! the fixture wasn't tested). Adding the `default` flag always uses the
+//! fallback value, even when the function is compiled in:
+//!
+//! ```
+//! #[tested_fixture::tested_fixture(
+//!     PG_URL: String,
+//!     cfg(feature = "postgres"),
+//!     fallback = String::from("postgres://localhost"),
+//! )]
+//! fn start_postgres() -> String {
+//!     // ...
+//! }
+//! ```
+//!
+//! Fixtures are only compiled for the crate's own tests (under `cfg(test)`).
+//! To also use them from integration tests or other crates, they can be
+//! `exposed` under another condition, typically a feature enabled by those
+//! tests' `[dev-dependencies]`:
+//!
+//! ```
+//! #[tested_fixture::tested_fixture(pub STEP_1, exposed(feature = "test-fixtures"))]
+//! fn step_1() -> Foo {
+//!     // ...
+//! }
+//! ```
+//!
+//! Since fixtures are shared, a test which mutates one through interior
+//! mutability can silently break later tests. A fixture can be given an
+//! `invariant`, as in `invariant = |v: &Foo| v.is_consistent()`, which is
+//! checked once the fixture is initialized (failing its test if it doesn't
+//! hold) and again on every access, panicking with e.g. `STEP_1 invariant
+//! violated (first detected in test tests::step_3)`. The checks are only
+//! compiled into debug builds.
+//!
+//! More simply, the `verify_unchanged` flag enforces that a fixture isn't
+//! mutated at all (see below), by fingerprinting it with its `Hash`
+//! implementation once it's initialized and panicking on any later access
+//! which finds a different fingerprint (also only in debug builds). Types
+//! which aren't `Hash` can specify a fingerprint instead, as in
+//! `verify_unchanged = |v: &Foo| v.len()`, whose `Debug` output is shown
+//! before and after the change.
+//!
+//! A failed fixture's result stays cached, so retrying one of its tests in
+//! the same process (e.g. with cargo-nextest's retries) would simply fail
+//! again. `STEP_1.reset()` clears the cached result, so that the next access
+//! runs the function again. Setting the `TESTED_FIXTURE_RETRY` environment
+//! variable makes this automatic, by resetting a failed fixture whenever its
+//! own test is run again. Resetting a fixture which is being initialized
+//! doesn't interrupt it, and since earlier dependents may still be borrowing
+//! the previous value, it is leaked rather than dropped.
+//!
+//! A fixture function prone to transient failures (e.g. provisioning a cloud
+//! sandbox) can instead be retried as part of a single initialization with
+//! `retries = 2`, which runs it again whenever it returns an error or panics,
+//! up to twice, optionally waiting `retry_delay = "5s"` in between. Only the
+//! final failure is cached, and its message notes how many attempts were made.
+//! Retries aren't supported for `async` functions.
+//!
+//! A fixture which needs an external service (e.g. a database) can declare
+//! the environment variables it depends on with `requires_env = "DATABASE_URL"`
+//! (repeated for each variable). When one isn't set, the fixture is skipped
+//! rather than failed: its test passes, noting `skipped: DATABASE_URL not
+//! set`. Its dependents (including the tests of fixtures which depend on it)
+//! fail with just that line, rather than a panic message and backtrace each,
+//! or can check [`FixtureError::skipped`] on the result of `try_get` to
+//! return early themselves.
+//!
+//! Since fixtures are stored in statics, they are never dropped. Fixtures
+//! holding resources which need to be cleaned up (e.g. child processes or
+//! temporary schemas) can be given a `teardown`, as in
+//! `#[tested_fixture(SERVER, teardown = |server| server.shutdown())]`, which
+//! is run when the test binary exits. Teardowns only run for fixtures which
+//! were successfully initialized, in the reverse order that they were
+//! initialized.
+//!
+//! Enabling the `registry` feature keeps track of every fixture in the test
+//! binary (other than `thread_local` and `local` fixtures), which are listed
+//! by [`registry`] along with their module paths. Each can be initialized with
+//! [`RegisteredFixture::force`], or all of them at once with [`init_all`],
+//! e.g. as a single warm-up step at the start of a benchmark harness.
+//! Independent fixtures which are slow to build can instead be initialized
+//! concurrently with [`warm_up_parallel`] (or [`force_parallel`] for a subset
+//! of the registry), which runs each on its own thread.
+//!
+//! Shared helpers can also look up a fixture by its type rather than its name,
+//! using [`get`] (e.g. `tested_fixture::get::<DatabasePool>()`), which
+//! returns `None` if no fixture has that type and panics if several do.
+//!
+//! Since fixtures are declared in test files, the code generated for them is
+//! measured by coverage tools along with the tests. Enabling the `coverage`
+//! feature excludes it with `#[coverage(off)]` whenever `cfg(coverage_nightly)`
+//! is set (as by `cargo llvm-cov` on a nightly toolchain), while the body of
+//! each fixture function is still measured. The crate's root then needs
+//! `#![cfg_attr(coverage_nightly, feature(coverage_attribute))]`.
+//!
+//! Fixtures are stored using `once_cell` by default. Enabling the `std-sync`
+//! feature (which requires Rust 1.70) stores them using [`std::sync::OnceLock`]
+//! instead, allowing `once_cell` to be dropped with `default-features = false`.
+//! Either way a panicking fixture function is caught and cached as a failure,
+//! so the cell is never left poisoned or uninitialized.
+//!
 //! ## Limitations
 //!
 //! Ordinary `#[test]` functions are able to return anything which implements
-//! [`std::process::Termination`], including unlimited nestings of `Result`s.
-//! While this crate does support returning nested `Result` wrappings, it only
-//! does so up to a fixed depth. Additionally it does not support returning any
-//! other `Termination` implementations besides `Result`.
+//! [`std::process::Termination`]. Fixture functions may return any nesting of
+//! `Result`s and `Option`s, but other `Termination` implementations are only
+//! supported if they implement [`IntoFixtureResult`].
 //!
 //! As with all testing-related global state, it is recommended that tests don't
 //! mutate the state, as doing so will increase the risk of flaky tests due to
 //! changes in execution order or timing. Thankfully this is the default
 //! behavior, as all fixtures defined by this crate are only accessible by
-//! non-mutable reference.
-//!
-//! Right now this crate does not support async tests.
+//! non-mutable reference (and `verify_unchanged` catches mutation through
+//! interior mutability). Where a shared resource genuinely needs to be
+//! mutated, declaring the fixture `mut` (as in `#[tested_fixture(mut DB)]`)
+//! stores it in a [`std::sync::Mutex`], so the fixture is a
+//! `Mutex<T>` which tests can `lock()`. Fixtures which are mostly
+//! read can add the `rwlock` flag to be stored in a [`std::sync::RwLock`]
+//! instead, as in `#[tested_fixture(mut CONFIG, rwlock)]`.
 
 #![warn(missing_docs)]
 #![allow(clippy::test_attr_in_doctest)]
+// `std-sync` documents that it raises the MSRV
+#![cfg_attr(feature = "std-sync", allow(clippy::incompatible_msrv))]
+// `cargo llvm-cov` sets `coverage_nightly` for the `coverage` feature
+#![cfg_attr(coverage_nightly, feature(coverage_attribute))]
+
+#[cfg(not(any(feature = "once_cell", feature = "std-sync")))]
+compile_error!("either the `once_cell` (default) or `std-sync` feature must be enabled");
 
 pub use tested_fixture_macros::tested_fixture;
 
+#[cfg(feature = "tokio")]
+pub use helpers::RuntimeFixture;
+#[cfg(feature = "registry")]
+pub use helpers::{force_parallel, get, init_all, registry, warm_up_parallel, RegisteredFixture};
+pub use helpers::{
+    AlreadyInitialized, FixtureError, FixtureHandle, FixtureState, IntoFixtureResult,
+};
+
 #[doc(hidden)]
 pub use tested_fixture_macros::tested_fixture_doctest;
 
 #[doc(hidden)]
 pub mod helpers {
     use std::{
-        convert::Infallible,
-        fmt::Debug,
+        any::Any,
+        backtrace::{Backtrace, BacktraceStatus},
+        borrow::Borrow,
+        cell::{Cell, RefCell},
+        error::Error,
+        fmt::{Debug, Display},
+        future::{poll_fn, Future},
+        hash::{Hash, Hasher},
+        iter::successors,
+        marker::PhantomData,
+        ops::Deref,
+        pin::Pin,
         process::{ExitCode, Termination},
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            Arc, Condvar, Mutex, MutexGuard, Once, PoisonError, RwLock, RwLockReadGuard,
+        },
+        task::{Poll, Waker},
+        thread::{self, LocalKey, ThreadId},
     };
 
+    #[cfg(feature = "registry")]
+    use std::any::TypeId;
+
     // Re-exports
-    pub use once_cell::sync::{Lazy, OnceCell};
+    pub use crate::__coverage_off as coverage_off;
+    pub use crate::__coverage_on as coverage_on;
+    pub use crate::__eager as eager;
+    pub use crate::__register as register;
+    #[cfg(any(feature = "eager", feature = "registry"))]
+    pub use ctor;
+    #[cfg(not(feature = "std-sync"))]
+    pub use once_cell::sync::OnceCell;
+    #[cfg(feature = "std-sync")]
+    pub use std::sync::OnceLock as OnceCell;
+    pub use std::time::{Duration, Instant};
 
-    /// A helper trait to unify `Result` fixtures types
-    pub trait MakeResultRef {
-        type Output;
-        fn make(self) -> Self::Output;
+    /// A return type which either produces a fixture or fails the test
+    ///
+    /// This is implemented for `Result` and `Option`, and can be implemented for custom
+    /// [`Termination`] types so that functions returning them can be used as
+    /// fixtures.
+    ///
+    /// ```
+    /// use tested_fixture::IntoFixtureResult;
+    ///
+    /// #[derive(Debug)]
+    /// enum TestOutcome<T> {
+    ///     Passed(T),
+    ///     Failed(String),
+    /// }
+    /// # impl<T> std::process::Termination for TestOutcome<T> {
+    /// #     fn report(self) -> std::process::ExitCode {
+    /// #         std::process::ExitCode::SUCCESS
+    /// #     }
+    /// # }
+    ///
+    /// impl<T> IntoFixtureResult for TestOutcome<T> {
+    ///     type Value = T;
+    ///     type Error = String;
+    ///     fn as_fixture_result(&self) -> Result<&T, &String> {
+    ///         match self {
+    ///             TestOutcome::Passed(v) => Ok(v),
+    ///             TestOutcome::Failed(e) => Err(e),
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub trait IntoFixtureResult {
+        /// The fixture produced on success
+        type Value;
+        /// The error reported by the test and its dependents on failure, which
+        /// must implement `Debug` (or `Display` with the `error_display` flag)
+        type Error;
+        /// Borrows either the fixture or the error
+        fn as_fixture_result(&self) -> Result<&Self::Value, &Self::Error>;
     }
 
-    impl<T, E: Debug> MakeResultRef for &'static Result<T, E> {
-        type Output = Result<&'static T, &'static E>;
-        fn make(self) -> Self::Output {
+    impl<T, E> IntoFixtureResult for Result<T, E> {
+        type Value = T;
+        type Error = E;
+        fn as_fixture_result(&self) -> Result<&T, &E> {
             self.as_ref()
         }
     }
 
-    /// A helper struct for wrapping fixtures
-    pub struct ReportSuccess<T>(pub T);
+    /// The error reported when an `Option` fixture function returns `None`
+    pub struct ReturnedNone;
 
-    impl<T> Termination for ReportSuccess<T> {
+    impl Debug for ReturnedNone {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("returned None")
+        }
+    }
+
+    impl<T> IntoFixtureResult for Option<T> {
+        type Value = T;
+        type Error = ReturnedNone;
+        fn as_fixture_result(&self) -> Result<&T, &ReturnedNone> {
+            self.as_ref().ok_or(&ReturnedNone)
+        }
+    }
+
+    /// Reports an error which implements `Display` but not `Debug`
+    pub struct DisplayError<E>(pub E);
+
+    impl<E: Display> Debug for DisplayError<E> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            Display::fmt(&self.0, f)
+        }
+    }
+
+    /// A fixture produced by a passing test
+    pub struct Fixture<T: ?Sized + 'static>(pub &'static T);
+
+    impl<T: ?Sized> Termination for Fixture<T> {
         fn report(self) -> ExitCode {
             ExitCode::SUCCESS
         }
     }
 
-    /// Helper trait for unwrapping fixtures
-    pub trait StaticallyBorrow {
-        type T;
-        fn static_borrow(&self) -> Self::T;
+    /// The error which failed a fixture test, reported again by its dependents
+    ///
+    /// This is returned by `try_get` on a fixture, allowing dependents to
+    /// handle the failure themselves rather than panicking.
+    pub struct FixtureError {
+        fixture: &'static str,
+        test: &'static str,
+        kind: FailureKind,
+        error: Box<dyn Describe + Send + Sync>,
+    }
+
+    /// How a fixture function failed
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum FailureKind {
+        /// It returned an error
+        Returned,
+        /// It panicked
+        Panicked,
+        /// Its result was rejected, or it timed out
+        Failed,
+        /// An environment variable it `requires_env` wasn't set
+        Skipped,
     }
 
-    impl<T> StaticallyBorrow for &'static T {
-        type T = &'static T;
-        fn static_borrow(&self) -> Self::T {
-            self
+    impl FixtureError {
+        fn new<E: Debug + Send + Sync + 'static>(kind: FailureKind, error: E) -> Self {
+            FixtureError {
+                fixture: "",
+                test: "",
+                kind,
+                error: Box::new(error),
+            }
+        }
+
+        fn from_failure<F>(failure: F) -> Self
+        where
+            F: Borrow<Failure> + Debug + Send + Sync + 'static,
+        {
+            let failure_ref = failure.borrow();
+            let kind = match (failure_ref.skipped, failure_ref.panicked) {
+                (true, _) => FailureKind::Skipped,
+                (false, true) => FailureKind::Panicked,
+                (false, false) => FailureKind::Failed,
+            };
+            FixtureError::new(kind, failure)
+        }
+
+        /// The name of the fixture which failed
+        pub fn fixture_name(&self) -> &'static str {
+            self.fixture
+        }
+
+        /// The path of the test which failed to produce the fixture
+        pub fn test_name(&self) -> &'static str {
+            self.test
+        }
+
+        /// Whether the test failed by panicking, rather than returning an error
+        pub fn panicked(&self) -> bool {
+            self.kind == FailureKind::Panicked
+        }
+
+        /// Whether the fixture was skipped because an environment variable it
+        /// `requires_env` wasn't set, in which case dependents may want to
+        /// return early too
+        pub fn skipped(&self) -> bool {
+            self.kind == FailureKind::Skipped
+        }
+
+        /// The failure as reported to dependent tests, e.g. the returned error
+        /// or panic message
+        pub fn message(&self) -> String {
+            Describe::describe(&*self.error)
         }
     }
 
-    impl<T: StaticallyBorrow> StaticallyBorrow for Result<T, Infallible> {
-        type T = T::T;
-        fn static_borrow(&self) -> Self::T {
-            match self.as_ref() {
-                Ok(v) => v.static_borrow(),
-                Err(_) => unreachable!(),
+    impl Debug for FixtureError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            if self.skipped() {
+                return write!(f, "skipped: {}", self.message());
             }
+            Debug::fmt(&*self.error, f)
+        }
+    }
+
+    impl FixtureError {
+        fn fmt_cause(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let function = self.test.rsplit("::").next().unwrap_or(self.test);
+            let kind = match self.kind {
+                FailureKind::Returned => "returned an error",
+                FailureKind::Panicked => "panicked",
+                FailureKind::Failed => "failed",
+                FailureKind::Skipped => "was skipped",
+            };
+            write!(f, "fixture {} (fn {}) {}", self.fixture, function, kind)
         }
     }
 
-    impl<T: StaticallyBorrow> StaticallyBorrow for ReportSuccess<T> {
-        type T = T::T;
-        fn static_borrow(&self) -> Self::T {
-            self.0.static_borrow()
+    impl Display for FixtureError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            self.fmt_cause(f)?;
+            write!(f, ": {}", self.message())
         }
     }
 
-    /// Helper trait for unwrapping fixtures
-    pub trait Unwrap<T>: Termination {
-        fn unwrap(self, context: &str) -> &'static T;
+    /// The name of the running test, which is the name of its thread unless
+    /// tests are run on the main thread
+    fn current_test() -> Option<String> {
+        std::thread::current()
+            .name()
+            .filter(|name| *name != "main")
+            .map(str::to_owned)
     }
 
-    impl<T: 'static, R: StaticallyBorrow<T = &'static T>> Unwrap<T> for ReportSuccess<R> {
-        fn unwrap(self, _context: &str) -> &'static T {
-            self.static_borrow()
+    /// A failed fixture reported along with the fixtures being initialized
+    /// on this thread when it was required (if any), and the test which
+    /// required them
+    struct Provenance<'a>(&'a FixtureError);
+
+    impl Display for Provenance<'_> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            self.0.fmt_cause(f)?;
+            let test = current_test();
+            INITIALIZING.with(|initializing| {
+                let initializing = initializing.borrow();
+                let mut chain = initializing.iter().rev();
+                let fixture = match chain.next() {
+                    Some(fixture) => fixture,
+                    None => return Ok(()),
+                };
+                write!(f, " (while initializing {}", fixture)?;
+                for fixture in chain {
+                    write!(f, " for {}", fixture)?;
+                }
+                match test {
+                    Some(test) => write!(f, ", required by {})", test),
+                    None => write!(f, ")"),
+                }
+            })?;
+            write!(f, ": {}", self.0.message())
         }
     }
 
-    impl<T, R: Unwrap<T>, E: Debug> Unwrap<T> for Result<R, E> {
-        fn unwrap(self, context: &str) -> &'static T {
+    impl Error for FixtureError {}
+
+    /// Fails a dependent of a failed fixture, or skips it (with a single line
+    /// of output) if the fixture was skipped
+    ///
+    /// The panic is reported at the dependent's access, rather than in this
+    /// crate, as are other panics on behalf of dependents.
+    #[track_caller]
+    fn fail(error: &FixtureError) -> ! {
+        Report::new(error).raise()
+    }
+
+    /// How a dependent reports a failed fixture, which can be raised after
+    /// leaving a closure which would hide the dependent's location
+    enum Report {
+        Skip(String),
+        Panic(String),
+    }
+
+    impl Report {
+        fn new(error: &FixtureError) -> Self {
+            match error.kind {
+                FailureKind::Skipped => Report::Skip(error.message()),
+                _ => Report::Panic(Provenance(error).to_string()),
+            }
+        }
+
+        #[track_caller]
+        fn raise(self) -> ! {
             match self {
-                Ok(v) => v.unwrap(context),
-                Err(e) => panic!("{} failed: {:?}", context, e),
+                Report::Skip(reason) => skip(reason),
+                Report::Panic(message) => {
+                    // Only read by the panic hook, which is installed when
+                    // catching
+                    if CATCHING.with(Cell::get) {
+                        PROPAGATING.with(|p| p.set(true));
+                    }
+                    panic!("{}", message)
+                }
+            }
+        }
+    }
+
+    /// An error which remembers how to describe itself after being erased
+    trait Describe: Debug {
+        fn describe(&self) -> String;
+    }
+
+    impl<E: Debug + 'static> Describe for E {
+        fn describe(&self) -> String {
+            describe(self)
+        }
+    }
+
+    /// An error described as soon as it's caught, so that it can be reported
+    /// on other threads even if it isn't `Sync`
+    struct Rendered {
+        debug: String,
+        message: String,
+    }
+
+    impl Rendered {
+        fn new<E: Debug + 'static>(error: E) -> Self {
+            Rendered {
+                debug: format!("{:?}", error),
+                message: describe(&error),
             }
         }
     }
 
-    /// A helper struct to unify non-`Result` fixtures types
-    pub struct Fixer<T>(pub T);
-    impl<T: MakeResultRef> Fixer<T> {
-        pub fn fix(self) -> T::Output {
-            self.0.make()
+    impl Debug for Rendered {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(&self.debug)
         }
     }
 
-    /// A helper trait to unify non-`Result` fixtures types
+    /// Selects a [`Flatten`] impl for a reference to the fixture itself
+    pub struct Here;
+
+    /// Selects a [`Flatten`] impl for a reference to a result wrapping the
+    /// fixture
+    pub struct There<I>(PhantomData<I>);
+
+    /// Selects a [`Flatten`] impl for a reference to the pinned fixture
+    pub struct Pinned;
+
+    /// Unwraps any number of [`IntoFixtureResult`] layers down to the fixture
+    ///
+    /// The index `I` is inferred from the fixture type, so nested `Result`s
+    /// of any depth are supported.
+    pub trait Flatten<T: ?Sized + 'static, I> {
+        fn flatten(self) -> Result<Fixture<T>, FixtureError>;
+    }
 
-    pub trait Fix {
-        type Fixed;
-        fn fix(self) -> Self::Fixed;
+    impl<T: ?Sized + 'static, O: Borrow<T>> Flatten<T, Here> for &'static O {
+        fn flatten(self) -> Result<Fixture<T>, FixtureError> {
+            Ok(Fixture(self.borrow()))
+        }
     }
 
-    impl<T: 'static> Fix for Fixer<T> {
-        type Fixed = Result<ReportSuccess<T>, Infallible>;
-        fn fix(self) -> Self::Fixed {
-            Ok(ReportSuccess(self.0))
+    // Pinned values never move out of the fixture, so can be borrowed for
+    // `'static`
+    impl<T: ?Sized + 'static> Flatten<T, Pinned> for &'static Pin<Box<T>> {
+        fn flatten(self) -> Result<Fixture<T>, FixtureError> {
+            Ok(Fixture(self.as_ref().get_ref()))
         }
     }
 
-    /// A helper function to get fixtures from test functions
-    pub fn unwrap<T, R, F>(f: F) -> &'static T
+    impl<T: ?Sized + 'static, R, I> Flatten<T, There<I>> for &'static R
     where
-        T: 'static,
-        R: Unwrap<T>,
-        F: FnOnce() -> R,
+        R: IntoFixtureResult,
+        R::Error: Debug + 'static,
+        &'static R::Value: Flatten<T, I>,
     {
-        let context = core::any::type_name::<F>();
-        f().unwrap(context)
+        fn flatten(self) -> Result<Fixture<T>, FixtureError> {
+            match self.as_fixture_result() {
+                Ok(v) => v.flatten(),
+                Err(e) => Err(FixtureError::new(FailureKind::Returned, Rendered::new(e))),
+            }
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    struct HeavySetup(u32);
+    /// Implemented when the annotated type `T` of a fixture can be captured
+    /// from its function's return type, mirroring [`Flatten`] without its
+    /// bounds on errors
+    pub trait ReturnTypeMatchesAnnotation<T: ?Sized, I> {}
 
-    impl HeavySetup {
-        fn build(v: u32) -> Self {
-            HeavySetup(v)
+    impl<T: ?Sized, O: Borrow<T>> ReturnTypeMatchesAnnotation<T, Here> for O {}
+
+    impl<T: ?Sized> ReturnTypeMatchesAnnotation<T, Pinned> for Pin<Box<T>> {}
+
+    impl<T: ?Sized, R, I> ReturnTypeMatchesAnnotation<T, There<I>> for R
+    where
+        R: IntoFixtureResult,
+        R::Value: ReturnTypeMatchesAnnotation<T, I>,
+    {
+    }
+
+    /// Checks the annotated type of a fixture against its function's return
+    /// type, so that a mismatch is reported on the annotation
+    pub fn expect_fixture_type<R: ReturnTypeMatchesAnnotation<T, I>, T: ?Sized, I>() {}
+
+    /// Flattens the result of a fixture function
+    pub fn flatten<T: ?Sized + 'static, R, I>(
+        result: &'static FixtureResult<R>,
+    ) -> Result<Fixture<T>, FixtureError>
+    where
+        &'static R: Flatten<T, I>,
+    {
+        match result {
+            Ok(v) => v.flatten(),
+            Err(e) => Err(FixtureError::from_failure(e)),
         }
     }
 
-    #[tested_fixture(
-        /// This is a test fixture
-        pub(crate) SETUP_0
-    )]
-    fn setup_with_attributes() -> HeavySetup {
-        HeavySetup::build(1)
+    /// The error reported when a `capture = "err"` fixture function succeeds
+    pub struct UnexpectedOk;
+
+    impl Debug for UnexpectedOk {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("unexpectedly returned Ok")
+        }
     }
 
-    #[tested_fixture(SETUP_1)]
-    fn setup() -> HeavySetup {
-        HeavySetup::build(1)
+    /// Captures the error of a `capture = "err"` fixture function
+    pub fn flatten_err<T: 'static, R>(
+        result: &'static FixtureResult<R>,
+    ) -> Result<Fixture<T>, FixtureError>
+    where
+        R: IntoFixtureResult<Error = T>,
+    {
+        match result {
+            Ok(v) => match v.as_fixture_result() {
+                Ok(_) => Err(FixtureError::new(FailureKind::Failed, UnexpectedOk)),
+                Err(e) => Ok(Fixture(e)),
+            },
+            Err(e) => Err(FixtureError::from_failure(e)),
+        }
     }
 
-    #[tested_fixture(SETUP_2: HeavySetup)]
-    fn try_setup() -> Result<HeavySetup, &'static str> {
-        Ok(HeavySetup::build(2))
+    /// The error reported when an `expect_panic` fixture function doesn't panic
+    pub struct DidNotPanic;
+
+    impl Debug for DidNotPanic {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("returned without panicking")
+        }
     }
 
-    #[tested_fixture(SETUP_3: HeavySetup)]
-    #[ignore = "fails"]
-    fn fail_setup() -> Result<HeavySetup, &'static str> {
-        Err("failed due to reticulated splines")
+    /// Captures the panic message of an `expect_panic` fixture function
+    pub fn flatten_panic<T: ?Sized + 'static, O>(
+        result: &'static FixtureResult<O>,
+    ) -> Result<Fixture<T>, FixtureError>
+    where
+        String: Borrow<T>,
+    {
+        match result {
+            Err(e) if e.panicked => Ok(Fixture(e.message.borrow())),
+            Err(e) => Err(FixtureError::from_failure(e)),
+            Ok(_) => Err(FixtureError::new(FailureKind::Failed, DidNotPanic)),
+        }
     }
 
-    #[tested_fixture(SETUP_4)]
-    #[ignore = "fails"]
-    fn panic_setup() -> HeavySetup {
-        panic!("failed due to normalized social network")
+    /// The error reported when a `full` fixture is rejected by its `pass_if`
+    /// predicate
+    pub struct Rejected;
+
+    impl Debug for Rejected {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("rejected by `pass_if`")
+        }
     }
 
-    #[test]
-    fn combine_setup() {
-        let _ = HeavySetup::build(SETUP_1.0 + SETUP_2.0);
+    /// The error reported when a fixture's `invariant` doesn't hold, naming
+    /// the test which first detected it (if known)
+    struct InvariantViolated {
+        fixture: &'static str,
+        test: Option<String>,
     }
 
-    #[test]
-    #[should_panic(
-        expected = r#"tested_fixture::tests::fail_setup failed: "failed due to reticulated splines""#
-    )]
-    fn combine_fail() {
-        let _ = HeavySetup::build(SETUP_1.0 + SETUP_3.0);
+    impl Debug for InvariantViolated {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{} invariant violated", self.fixture)?;
+            match &self.test {
+                Some(test) => write!(f, " (first detected in test {})", test),
+                None => Ok(()),
+            }
+        }
     }
 
-    #[test]
-    #[should_panic(expected = r#"tested_fixture::tests::panic_setup failed: "panicked""#)]
-    fn combine_panic() {
-        let _ = HeavySetup::build(SETUP_1.0 + SETUP_4.0);
+    /// The checks run on every access of a fixture (in debug builds)
+    #[doc(hidden)]
+    pub struct Checks<T: ?Sized> {
+        /// A predicate the fixture must satisfy
+        pub invariant: Option<fn(&T) -> bool>,
+        /// Describes the fixture's contents, which mustn't change after it's
+        /// initialized
+        pub fingerprint: Option<fn(&T) -> String>,
+    }
+
+    impl<T: ?Sized> Checks<T> {
+        pub const NONE: Self = Checks {
+            invariant: None,
+            fingerprint: None,
+        };
+    }
+
+    /// Fingerprints a `verify_unchanged` fixture by its hash
+    pub fn hash_fingerprint<T: Hash + ?Sized>(value: &T) -> String {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        value.hash(&mut hasher);
+        format!("hash {:016x}", hasher.finish())
+    }
+
+    /// Fingerprints a `verify_unchanged` fixture by the `Debug` output of a
+    /// function of it
+    pub fn debug_fingerprint<T: ?Sized, F: Debug>(
+        value: &T,
+        fingerprint: impl FnOnce(&T) -> F,
+    ) -> String {
+        format!("{:?}", fingerprint(value))
+    }
+
+    /// Fails a fixture whose `invariant` doesn't hold once it's initialized
+    pub fn check_invariant<T: ?Sized + 'static>(
+        fixture: &'static str,
+        result: Result<Fixture<T>, FixtureError>,
+        invariant: fn(&T) -> bool,
+    ) -> Result<Fixture<T>, FixtureError> {
+        match result {
+            Ok(Fixture(v)) if !invariant(v) => {
+                let test = current_test();
+                let error = InvariantViolated { fixture, test };
+                Err(FixtureError::new(FailureKind::Failed, error))
+            }
+            result => result,
+        }
+    }
+
+    /// Keeps the entire result of a `full` fixture function
+    pub fn flatten_full<T: 'static>(
+        result: &'static FixtureResult<T>,
+        pass_if: impl FnOnce(&T) -> bool,
+    ) -> Result<Fixture<T>, FixtureError> {
+        match result {
+            Ok(v) if pass_if(v) => Ok(Fixture(v)),
+            Ok(_) => Err(FixtureError::new(FailureKind::Failed, Rejected)),
+            Err(e) => Err(FixtureError::from_failure(e)),
+        }
+    }
+
+    /// Flattens the result of a fixture function, reporting the error of its
+    /// outermost layer with `Display`
+    pub fn flatten_display<T: ?Sized + 'static, R, I>(
+        result: &'static FixtureResult<R>,
+    ) -> Result<Fixture<T>, FixtureError>
+    where
+        R: IntoFixtureResult,
+        R::Error: Display + 'static,
+        &'static R::Value: Flatten<T, I>,
+    {
+        match result {
+            Ok(v) => match v.as_fixture_result() {
+                Ok(v) => v.flatten(),
+                Err(e) => {
+                    let error = Rendered::new(DisplayError(e));
+                    Err(FixtureError::new(FailureKind::Returned, error))
+                }
+            },
+            Err(e) => Err(FixtureError::from_failure(e)),
+        }
+    }
+
+    /// Formats a fixture failure, preferring the cause chain of well-known
+    /// error types over their `Debug` output
+    fn describe<E: Debug + 'static>(error: &E) -> String {
+        let any: &dyn Any = error;
+        if let Some(e) = any.downcast_ref::<&FixtureError>() {
+            return e.message();
+        }
+        if let Some(e) = any.downcast_ref::<Rendered>() {
+            return e.message.clone();
+        }
+        if let Some(e) = any.downcast_ref::<Retried>() {
+            return format!("{} (after {} attempts)", e.error.describe(), e.attempts);
+        }
+        #[cfg(feature = "anyhow")]
+        if let Some(e) = any.downcast_ref::<&anyhow::Error>() {
+            return describe_chain(e.chain().map(|e| e as &dyn Display));
+        }
+        #[cfg(feature = "eyre")]
+        if let Some(e) = any.downcast_ref::<&eyre::Report>() {
+            return describe_chain(e.chain().map(|e| e as &dyn Display));
+        }
+        let source: Option<&(dyn Error + 'static)> =
+            if let Some(e) = any.downcast_ref::<&Box<dyn Error + Send + Sync>>() {
+                Some(&***e)
+            } else if let Some(e) = any.downcast_ref::<&Box<dyn Error + Send>>() {
+                Some(&***e)
+            } else if let Some(e) = any.downcast_ref::<&Box<dyn Error>>() {
+                Some(&***e)
+            } else {
+                None
+            };
+        if let Some(source) = source {
+            return describe_chain(
+                successors(Some(source), |&e| e.source()).map(|e| e as &dyn Display),
+            );
+        }
+        format!("{:?}", error)
+    }
+
+    /// Joins an error and its causes into a single line
+    fn describe_chain<'a>(chain: impl Iterator<Item = &'a dyn Display>) -> String {
+        chain
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join(" — caused by: ")
+    }
+
+    /// Applies a fixture's `map` closure, inferring its argument type
+    pub fn map<T, U>(value: T, f: impl FnOnce(T) -> U) -> U {
+        f(value)
+    }
+
+    /// Checks the error type of a fixture function against its annotation
+    pub fn expect_error<R: IntoFixtureResult<Error = E>, E>() {}
+
+    /// Fixtures are stored in statics, and so must be `Sync`
+    ///
+    /// Checked as the type of a constant, which (unlike a call) is checked
+    /// before the bounds of the static storing the fixture, and so is reported
+    /// first.
+    pub struct FixtureMustBeSync<T: Sync + ?Sized>(PhantomData<fn() -> *const T>);
+
+    impl<T: Sync + ?Sized> FixtureMustBeSync<T> {
+        pub const CHECKED: Self = FixtureMustBeSync(PhantomData);
+    }
+
+    /// A synchronous fixture function with a `timeout` or `stack_size` runs
+    /// on a helper thread, and so its result must be `Send`
+    pub const fn helper_thread_requires_send<T: Send>() {}
+
+    /// `arc` fixtures are shared with other threads, and so must be `Send`
+    /// and `Sync`
+    pub const fn arc_fixture_must_be_send_sync<T: Send + Sync + ?Sized>() {}
+
+    /// Excludes generated items from coverage measured on nightly (as by
+    /// `cargo llvm-cov`, which sets `cfg(coverage_nightly)`)
+    #[cfg(feature = "coverage")]
+    #[doc(hidden)]
+    #[macro_export]
+    macro_rules! __coverage_off {
+        ($($item:item)*) => {
+            $(#[cfg_attr(coverage_nightly, coverage(off))] $item)*
+        };
+    }
+
+    #[cfg(not(feature = "coverage"))]
+    #[doc(hidden)]
+    #[macro_export]
+    macro_rules! __coverage_off {
+        ($($item:item)*) => {
+            $($item)*
+        };
+    }
+
+    /// Measures the coverage of a fixture's body, which is nested in items
+    /// excluded by [`coverage_off`]
+    #[cfg(feature = "coverage")]
+    #[doc(hidden)]
+    #[macro_export]
+    macro_rules! __coverage_on {
+        ($($item:item)*) => {
+            $(#[cfg_attr(coverage_nightly, coverage(on))] $item)*
+        };
+    }
+
+    #[cfg(not(feature = "coverage"))]
+    #[doc(hidden)]
+    #[macro_export]
+    macro_rules! __coverage_on {
+        ($($item:item)*) => {
+            $($item)*
+        };
+    }
+
+    /// Registers a constructor which starts initializing an `eager` fixture
+    /// when the test binary launches
+    #[cfg(feature = "eager")]
+    #[doc(hidden)]
+    #[macro_export]
+    macro_rules! __eager {
+        ($fixture:ident) => {
+            $crate::helpers::ctor::declarative::ctor! {
+                #[ctor(unsafe, anonymous)]
+                fn eager() {
+                    $crate::helpers::init_eagerly(|| {
+                        // Stands in for its test, so isn't a strict mode violation
+                        let _ = $fixture.verify();
+                    });
+                }
+            }
+        };
+    }
+
+    #[cfg(not(feature = "eager"))]
+    #[doc(hidden)]
+    #[macro_export]
+    macro_rules! __eager {
+        ($fixture:ident) => {
+            ::std::compile_error!("`eager` requires the `eager` feature of `tested-fixture`");
+        };
+    }
+
+    /// Initializes an `eager` fixture on a background thread, so that it
+    /// overlaps with other tests and its failure is cached like any other
+    #[cfg(feature = "eager")]
+    pub fn init_eagerly(init: fn()) {
+        // If the thread can't be spawned, the first dependent initializes it
+        let _ = std::thread::Builder::new().spawn(init);
+    }
+
+    /// Registers a constructor which adds a fixture to the [`registry`] when
+    /// the test binary launches
+    #[cfg(feature = "registry")]
+    #[doc(hidden)]
+    #[macro_export]
+    macro_rules! __register {
+        ($name:expr, $fixture:ident) => {
+            $crate::helpers::ctor::declarative::ctor! {
+                #[ctor(unsafe, anonymous)]
+                fn register() {
+                    $crate::helpers::register_fixture($crate::helpers::RegisteredFixture::new(
+                        $name,
+                        ::std::module_path!(),
+                        || $fixture.verify().map(::std::mem::drop),
+                        || $crate::helpers::fixture_type(|| $fixture.try_get()),
+                        || $crate::helpers::erase($fixture.try_get()),
+                    ));
+                }
+            }
+        };
+    }
+
+    #[cfg(not(feature = "registry"))]
+    #[doc(hidden)]
+    #[macro_export]
+    macro_rules! __register {
+        ($name:expr, $fixture:ident) => {};
+    }
+
+    /// A fixture in the test binary, as listed by [`registry`]
+    #[cfg(feature = "registry")]
+    #[derive(Clone, Copy, Debug)]
+    pub struct RegisteredFixture {
+        name: &'static str,
+        module_path: &'static str,
+        force: fn() -> Result<(), FixtureError>,
+        /// The type of a `&'static` reference to the fixture
+        ty: fn() -> TypeId,
+        /// Gets a `&'static` reference to the fixture, panicking if it failed
+        get: fn() -> Box<dyn Any>,
+    }
+
+    #[cfg(feature = "registry")]
+    impl RegisteredFixture {
+        #[doc(hidden)]
+        pub const fn new(
+            name: &'static str,
+            module_path: &'static str,
+            force: fn() -> Result<(), FixtureError>,
+            ty: fn() -> TypeId,
+            get: fn() -> Box<dyn Any>,
+        ) -> Self {
+            RegisteredFixture {
+                name,
+                module_path,
+                force,
+                ty,
+                get,
+            }
+        }
+
+        /// The name of the fixture, e.g. `STEP_1`
+        pub fn name(&self) -> &'static str {
+            self.name
+        }
+
+        /// The path of the module declaring the fixture
+        pub fn module_path(&self) -> &'static str {
+            self.module_path
+        }
+
+        /// Initializes the fixture (if it hasn't been already) by running its
+        /// test, returning the failure of its test if it failed
+        pub fn force(&self) -> Result<(), FixtureError> {
+            (self.force)()
+        }
+    }
+
+    /// The fixtures in the test binary
+    #[cfg(feature = "registry")]
+    static REGISTRY: Mutex<Vec<RegisteredFixture>> = Mutex::new(Vec::new());
+
+    /// Adds a fixture to the [`registry`]
+    #[cfg(feature = "registry")]
+    pub fn register_fixture(fixture: RegisteredFixture) {
+        REGISTRY
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(fixture);
+    }
+
+    /// Lists every fixture in the test binary (other than `thread_local`
+    /// and `local` fixtures), ordered by module path and name
+    #[cfg(feature = "registry")]
+    pub fn registry() -> Vec<RegisteredFixture> {
+        let mut fixtures = REGISTRY
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        fixtures.sort_by_key(|fixture| (fixture.module_path, fixture.name));
+        fixtures
+    }
+
+    /// The type of a `&'static` reference to a fixture
+    #[cfg(feature = "registry")]
+    pub fn fixture_type<T: ?Sized + 'static>(
+        _get: fn() -> Result<&'static T, &'static FixtureError>,
+    ) -> TypeId {
+        TypeId::of::<&'static T>()
+    }
+
+    /// Erases the type of a `&'static` reference to a fixture, panicking if it
+    /// failed
+    #[cfg(feature = "registry")]
+    pub fn erase<T: ?Sized + 'static>(
+        fixture: Result<&'static T, &'static FixtureError>,
+    ) -> Box<dyn Any> {
+        match fixture {
+            Ok(v) => Box::new(v),
+            Err(e) => fail(e),
+        }
+    }
+
+    /// Gets the only fixture in the [`registry`] of type `T`, initializing it
+    /// if necessary, or `None` if there isn't one
+    ///
+    /// Fixtures are keyed by the type they dereference to, so for instance a
+    /// `mut` fixture is found as a `Mutex<T>`, and a tuple fixture as the
+    /// whole tuple.
+    ///
+    /// # Panics
+    ///
+    /// Panics if several fixtures have type `T`, since they can't be told
+    /// apart, or if the fixture failed.
+    #[cfg(feature = "registry")]
+    pub fn get<T: ?Sized + 'static>() -> Option<&'static T> {
+        let ty = TypeId::of::<&'static T>();
+        let mut fixtures = registry()
+            .into_iter()
+            .filter(|fixture| (fixture.ty)() == ty);
+        let fixture = fixtures.next()?;
+        if let Some(other) = fixtures.next() {
+            panic!(
+                "fixtures {}::{} and {}::{} both have type {}, so can't be looked up by type",
+                fixture.module_path,
+                fixture.name,
+                other.module_path,
+                other.name,
+                std::any::type_name::<T>(),
+            );
+        }
+        (fixture.get)().downcast().ok().map(|v| *v)
+    }
+
+    /// Initializes every fixture in the [`registry`] on the current thread,
+    /// returning the failures of any which failed
+    ///
+    /// Like any other access, a `no_catch_unwind` fixture which panics
+    /// unwinds through this function.
+    #[cfg(feature = "registry")]
+    pub fn init_all() -> Result<(), Vec<FixtureError>> {
+        let failures = registry()
+            .into_iter()
+            .filter_map(|fixture| fixture.force().err());
+        collect_failures(failures)
+    }
+
+    /// Initializes every fixture in the [`registry`] concurrently, with one
+    /// thread per fixture, returning the failures of any which failed
+    ///
+    /// See [`force_parallel`].
+    #[cfg(feature = "registry")]
+    pub fn warm_up_parallel() -> Result<(), Vec<FixtureError>> {
+        force_parallel(&registry())
+    }
+
+    /// Initializes the given fixtures concurrently, with one thread per
+    /// fixture, returning the failures of any which failed once every thread
+    /// has finished
+    ///
+    /// Fixtures which are already initialized (or being initialized by
+    /// another thread) aren't run again, and like any other access, a
+    /// `no_catch_unwind` fixture which panics unwinds through this function.
+    #[cfg(feature = "registry")]
+    pub fn force_parallel(fixtures: &[RegisteredFixture]) -> Result<(), Vec<FixtureError>> {
+        std::thread::scope(|scope| {
+            let threads: Vec<_> = fixtures
+                .iter()
+                .map(|fixture| scope.spawn(move || fixture.force()))
+                .collect();
+            let failures = threads
+                .into_iter()
+                .filter_map(|thread| match thread.join() {
+                    Ok(result) => result.err(),
+                    Err(payload) => std::panic::resume_unwind(payload),
+                });
+            collect_failures(failures)
+        })
+    }
+
+    #[cfg(feature = "registry")]
+    fn collect_failures(
+        failures: impl Iterator<Item = FixtureError>,
+    ) -> Result<(), Vec<FixtureError>> {
+        let failures: Vec<_> = failures.collect();
+        match failures.is_empty() {
+            true => Ok(()),
+            false => Err(failures),
+        }
+    }
+
+    /// A fixture's teardown, run when the test binary exits
+    type Teardown = Box<dyn FnOnce() + Send>;
+
+    /// The teardowns of successfully initialized fixtures, in the order they
+    /// were initialized
+    static TEARDOWNS: Mutex<Vec<(&'static str, Teardown)>> = Mutex::new(Vec::new());
+
+    /// Registers a fixture's teardown (once, however many times its test is
+    /// run), to be run when the test binary exits
+    pub fn register_teardown<T: ?Sized + Sync, R>(
+        once: &'static Once,
+        name: &'static str,
+        fixture: &'static T,
+        teardown: impl FnOnce(&'static T) -> R + Send + 'static,
+    ) {
+        static AT_EXIT: Once = Once::new();
+        once.call_once(|| {
+            let teardown = Box::new(move || drop(teardown(fixture)));
+            TEARDOWNS
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push((name, teardown));
+            AT_EXIT.call_once(|| {
+                extern "C" {
+                    fn atexit(f: extern "C" fn()) -> std::os::raw::c_int;
+                }
+                // SAFETY: `run_teardowns` is a valid function which doesn't
+                // unwind, and `atexit` is provided by the C runtime
+                unsafe { atexit(run_teardowns) };
+            });
+        });
+    }
+
+    /// Runs the registered teardowns in the reverse order that their fixtures
+    /// were initialized, reporting (rather than propagating) any panics
+    pub(crate) extern "C" fn run_teardowns() {
+        let teardowns =
+            std::mem::take(&mut *TEARDOWNS.lock().unwrap_or_else(PoisonError::into_inner));
+        for (name, teardown) in teardowns.into_iter().rev() {
+            if let Err(failure) = catch_panic(teardown) {
+                eprintln!("teardown of fixture {} panicked: {:?}", name, failure);
+            }
+        }
+    }
+
+    /// A helper function to run fixture functions, remembering which fixture
+    /// and test failed
+    pub fn resolve<T: ?Sized + 'static>(
+        fixture: &'static str,
+        test: &'static str,
+        init: impl FnOnce() -> Result<Fixture<T>, FixtureError>,
+    ) -> Result<&'static T, FixtureError> {
+        INITIALIZING.with(|initializing| initializing.borrow_mut().push(fixture));
+        let _initializing = Initializing;
+        match init() {
+            Ok(Fixture(v)) => Ok(v),
+            Err(e) => Err(FixtureError { fixture, test, ..e }),
+        }
+    }
+
+    /// Moves the result of a fixture function into static storage, which
+    /// fixtures borrow from
+    pub fn leak<T>(value: T) -> &'static T {
+        Box::leak(Box::new(value))
+    }
+
+    thread_local! {
+        /// The names of the fixtures being initialized on this thread, with
+        /// the innermost last
+        static INITIALIZING: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Pops the innermost fixture being initialized when dropped
+    struct Initializing;
+
+    impl Drop for Initializing {
+        fn drop(&mut self) {
+            INITIALIZING.with(|initializing| initializing.borrow_mut().pop());
+        }
+    }
+
+    /// Uses the `fallback` value of a fixture whose function is compiled out
+    /// (or which is declared `default`), noting that it wasn't tested
+    pub fn fallback<T>(fixture: &'static str, value: T) -> Result<&'static T, FixtureError> {
+        eprintln!(
+            "note: fixture {} is using its `fallback` value, rather than the value produced by its test",
+            fixture
+        );
+        Ok(leak(value))
+    }
+
+    /// Runs a `fresh` fixture function, remembering which fixture and test
+    /// failed like [`resolve`]
+    pub fn fresh<T>(
+        fixture: &'static str,
+        test: &'static str,
+        init: impl FnOnce() -> Result<T, FixtureError>,
+    ) -> Result<T, FixtureError> {
+        INITIALIZING.with(|initializing| initializing.borrow_mut().push(fixture));
+        let _initializing = Initializing;
+        init().map_err(|e| FixtureError { fixture, test, ..e })
+    }
+
+    /// Takes the value produced by a `fresh` fixture function
+    pub fn fresh_value<T>(result: FixtureResult<T>) -> Result<T, FixtureError> {
+        result.map_err(FixtureError::from_failure)
+    }
+
+    /// Takes the `Ok` value produced by a `fresh` fixture function
+    pub fn fresh_result<T, E>(result: FixtureResult<Result<T, E>>) -> Result<T, FixtureError>
+    where
+        E: Debug + 'static,
+    {
+        match result {
+            Ok(Ok(v)) => Ok(v),
+            Ok(Err(e)) => Err(FixtureError::new(FailureKind::Returned, Rendered::new(e))),
+            Err(failure) => Err(FixtureError::from_failure(failure)),
+        }
+    }
+
+    /// Drives an `async` fixture function to completion, blocking the current
+    /// thread
+    pub fn run_async<O, F>(
+        block_on: fn(fn() -> F) -> FixtureResult<O>,
+        init: fn() -> F,
+    ) -> FixtureResult<O> {
+        catch_panic(|| block_on(init)).and_then(|v| v)
+    }
+
+    /// The failure of a fixture function which was retried, along with how many
+    /// times it was run
+    struct Retried {
+        attempts: u32,
+        error: Box<dyn Describe + Send + Sync>,
+    }
+
+    impl Debug for Retried {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?} (after {} attempts)", self.error, self.attempts)
+        }
+    }
+
+    /// Runs the `alternative` of a fixture function, whose return type must
+    /// match the function's
+    pub fn alternative<R>(alternative: fn() -> R) -> R {
+        alternative()
+    }
+
+    /// Runs a fixture function declared with `retries` until it succeeds, or
+    /// has failed `retries + 1` times
+    pub fn retry<T: ?Sized + 'static>(
+        fixture: &'static str,
+        retries: u32,
+        delay: Duration,
+        mut attempt: impl FnMut() -> Result<Fixture<T>, FixtureError>,
+    ) -> Result<Fixture<T>, FixtureError> {
+        let mut attempts = 1;
+        loop {
+            match attempt() {
+                Err(e) if attempts <= retries && !e.skipped() => {
+                    eprintln!(
+                        "note: fixture {} failed (attempt {} of {}), retrying: {}",
+                        fixture,
+                        attempts,
+                        retries + 1,
+                        e.message()
+                    );
+                    attempts += 1;
+                    thread::sleep(delay);
+                }
+                Err(e) if attempts > 1 => {
+                    let error = Box::new(Retried {
+                        attempts,
+                        error: e.error,
+                    });
+                    return Err(FixtureError { error, ..e });
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Reports the failure of the fixture another fixture is projected from
+    pub fn forward(fixture: &'static str, error: &'static FixtureError) -> FixtureError {
+        FixtureError {
+            fixture,
+            error: Box::new(error),
+            ..*error
+        }
+    }
+
+    /// A minimal executor used to drive `async` fixtures to completion
+    #[cfg(not(feature = "smol"))]
+    pub fn block_on<F: Future>(future: F) -> F::Output {
+        use std::{
+            sync::Arc,
+            task::{Context, Wake},
+            thread::{self, Thread},
+        };
+
+        struct ThreadWaker(Thread);
+
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        let mut future = Box::pin(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(v) => return v,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    /// Drives `async` fixtures to completion using smol, which also drives its
+    /// reactor
+    #[cfg(feature = "smol")]
+    pub fn block_on<F: Future>(future: F) -> F::Output {
+        ::smol::block_on(future)
+    }
+
+    /// Drives an `async` fixture to completion using a user-provided executor
+    pub fn block_on_with<O, B>(block_on: B, init: fn() -> FixtureFuture<O>) -> FixtureResult<O>
+    where
+        B: FnOnce(FixtureFuture<O>) -> FixtureResult<O>,
+    {
+        block_on(init())
+    }
+
+    /// Wraps the body of an `async` fixture without a timeout
+    pub async fn no_timeout<F: Future>(future: F) -> FixtureResult<F::Output> {
+        Ok(future.await)
+    }
+
+    /// Wraps the body of an `async` fixture, failing with `message` if it
+    /// doesn't complete within `duration`
+    ///
+    /// The deadline is tracked by a helper thread so this works on any
+    /// executor, but a body which blocks without yielding can't be interrupted.
+    pub async fn timeout<F: Future>(
+        duration: Duration,
+        message: &'static str,
+        future: F,
+    ) -> FixtureResult<F::Output> {
+        use std::{
+            sync::{
+                atomic::{AtomicBool, Ordering},
+                Arc,
+            },
+            thread,
+            time::Instant,
+        };
+
+        #[derive(Default)]
+        struct Timer {
+            expired: AtomicBool,
+            done: AtomicBool,
+            waker: Mutex<Option<Waker>>,
+        }
+
+        let timer = Arc::<Timer>::default();
+        let deadline = Instant::now() + duration;
+        let sleeper = {
+            let timer = timer.clone();
+            thread::spawn(move || {
+                while !timer.done.load(Ordering::Acquire) {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        timer.expired.store(true, Ordering::Release);
+                        let waker = timer
+                            .waker
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .take();
+                        if let Some(waker) = waker {
+                            waker.wake();
+                        }
+                        return;
+                    }
+                    thread::park_timeout(deadline - now);
+                }
+            })
+        };
+
+        /// Stops the helper thread once the body completes or is dropped
+        struct Stop<'a>(&'a Timer, &'a thread::Thread);
+
+        impl Drop for Stop<'_> {
+            fn drop(&mut self) {
+                self.0.done.store(true, Ordering::Release);
+                self.1.unpark();
+            }
+        }
+
+        let _stop = Stop(&timer, sleeper.thread());
+        let mut future = Box::pin(future);
+        poll_fn(|cx| {
+            if let Poll::Ready(v) = future.as_mut().poll(cx) {
+                return Poll::Ready(Ok(v));
+            }
+            *timer.waker.lock().unwrap_or_else(PoisonError::into_inner) = Some(cx.waker().clone());
+            if timer.expired.load(Ordering::Acquire) {
+                Poll::Ready(Err(Failure::new(message)))
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Runs `f` outside of any Tokio runtime context
+    ///
+    /// A runtime cannot be started from within another runtime (i.e. when a
+    /// fixture is forced from an async test), so use a fresh thread if needed
+    #[cfg(feature = "tokio")]
+    fn outside_tokio<R: Send>(f: impl FnOnce() -> R + Send) -> R {
+        if ::tokio::runtime::Handle::try_current().is_err() {
+            return f();
+        }
+
+        std::thread::scope(|s| match s.spawn(f).join() {
+            Ok(v) => v,
+            Err(e) => std::panic::resume_unwind(e),
+        })
+    }
+
+    /// The flavor of Tokio runtime used to drive an `async` fixture
+    #[cfg(feature = "tokio")]
+    pub enum TokioFlavor {
+        CurrentThread,
+        MultiThread { worker_threads: Option<usize> },
+    }
+
+    /// Drives an `async` fixture to completion on a fresh Tokio runtime
+    ///
+    /// If the fixture is forced from within a multi-threaded runtime (i.e.
+    /// while initializing another fixture), that runtime is reused instead.
+    #[cfg(feature = "tokio")]
+    pub fn block_on_tokio<F, Fut>(flavor: TokioFlavor, f: F) -> Fut::Output
+    where
+        F: FnOnce() -> Fut + Send,
+        Fut: Future,
+        Fut::Output: Send,
+    {
+        use ::tokio::runtime::{Builder, Handle, RuntimeFlavor};
+
+        match Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                return ::tokio::task::block_in_place(move || handle.block_on(f()));
+            }
+            _ => {}
+        }
+
+        let mut builder = match flavor {
+            TokioFlavor::CurrentThread => Builder::new_current_thread(),
+            TokioFlavor::MultiThread { worker_threads } => {
+                let mut builder = Builder::new_multi_thread();
+                if let Some(worker_threads) = worker_threads {
+                    builder.worker_threads(worker_threads);
+                }
+                builder
+            }
+        };
+        outside_tokio(move || {
+            builder
+                .enable_all()
+                .build()
+                .expect("failed to build Tokio runtime")
+                .block_on(f())
+        })
+    }
+
+    /// Drives a `!Send` `async` fixture to completion on a `LocalSet` of a fresh
+    /// current-thread Tokio runtime
+    #[cfg(feature = "tokio")]
+    pub fn block_on_tokio_local<F, Fut>(f: F) -> Fut::Output
+    where
+        F: FnOnce() -> Fut + Send,
+        Fut: Future,
+        Fut::Output: Send,
+    {
+        outside_tokio(move || {
+            let runtime = ::tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("failed to build Tokio runtime");
+            ::tokio::task::LocalSet::new().block_on(&runtime, f())
+        })
+    }
+
+    /// A Tokio runtime which can be shared between fixtures
+    ///
+    /// Returning a `RuntimeFixture` from a fixture function allows later
+    /// fixtures to spawn long-lived tasks (e.g. servers) onto a reactor which
+    /// outlives any individual test.
+    #[cfg(feature = "tokio")]
+    #[derive(Debug)]
+    pub struct RuntimeFixture(::tokio::runtime::Runtime);
+
+    #[cfg(feature = "tokio")]
+    impl RuntimeFixture {
+        /// Creates a new multi-threaded runtime with all drivers enabled
+        pub fn new() -> std::io::Result<Self> {
+            ::tokio::runtime::Runtime::new().map(RuntimeFixture)
+        }
+
+        /// Returns a handle to the runtime, which can be used to spawn tasks
+        pub fn handle(&self) -> &::tokio::runtime::Handle {
+            self.0.handle()
+        }
+
+        /// Runs a future to completion on the runtime
+        ///
+        /// Unlike `Runtime::block_on`, this can be called from within another
+        /// runtime (i.e. from a `#[tokio::test]`).
+        pub fn block_on<F>(&self, future: F) -> F::Output
+        where
+            F: Future + Send,
+            F::Output: Send,
+        {
+            outside_tokio(move || self.0.block_on(future))
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    impl From<::tokio::runtime::Runtime> for RuntimeFixture {
+        fn from(runtime: ::tokio::runtime::Runtime) -> Self {
+            RuntimeFixture(runtime)
+        }
+    }
+
+    /// Drives an `async` fixture to completion using async-std
+    #[cfg(feature = "async-std")]
+    pub fn block_on_async_std<F: Future>(future: F) -> F::Output {
+        ::async_std::task::block_on(future)
+    }
+
+    /// The state of an in-progress [`AsyncOnceCell`] initialization
+    struct InitState {
+        initializing: bool,
+        wakers: Vec<Waker>,
+    }
+
+    /// A once cell which can be initialized (and waited on) either
+    /// synchronously or asynchronously
+    pub struct AsyncOnceCell<T> {
+        value: OnceCell<T>,
+        state: Mutex<InitState>,
+        condvar: Condvar,
+    }
+
+    /// Marks the end of an [`AsyncOnceCell`] initialization attempt, whether
+    /// it completed, panicked, or was cancelled
+    struct InitGuard<'a, T>(&'a AsyncOnceCell<T>);
+
+    impl<T> Drop for InitGuard<'_, T> {
+        fn drop(&mut self) {
+            let mut state = self.0.lock();
+            state.initializing = false;
+            state.wakers.drain(..).for_each(Waker::wake);
+            drop(state);
+            self.0.condvar.notify_all();
+        }
+    }
+
+    impl<T> Default for AsyncOnceCell<T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T> AsyncOnceCell<T> {
+        pub const fn new() -> Self {
+            AsyncOnceCell {
+                value: OnceCell::new(),
+                state: Mutex::new(InitState {
+                    initializing: false,
+                    wakers: Vec::new(),
+                }),
+                condvar: Condvar::new(),
+            }
+        }
+
+        fn lock(&self) -> MutexGuard<'_, InitState> {
+            self.state.lock().unwrap_or_else(PoisonError::into_inner)
+        }
+
+        pub fn get(&self) -> Option<&T> {
+            self.value.get()
+        }
+
+        /// Gets the value, initializing it with `f` and blocking the current
+        /// thread if necessary
+        pub fn get_or_init(&self, f: impl FnOnce() -> T) -> &T {
+            if let Some(v) = self.value.get() {
+                return v;
+            }
+            let mut state = self.lock();
+            loop {
+                if let Some(v) = self.value.get() {
+                    return v;
+                }
+                if !state.initializing {
+                    break;
+                }
+                state = self
+                    .condvar
+                    .wait(state)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+            state.initializing = true;
+            drop(state);
+
+            let guard = InitGuard(self);
+            let _ = self.value.set(f());
+            drop(guard);
+            self.value.get().unwrap()
+        }
+
+        /// Sets the value, unless it is set or being initialized already
+        pub fn set(&self, value: T) -> Result<(), T> {
+            let state = self.lock();
+            if state.initializing {
+                return Err(value);
+            }
+            let result = self.value.set(value);
+            drop(state);
+            result
+        }
+
+        /// Gets the value, initializing it with `f` and yielding to the
+        /// executor instead of blocking if necessary
+        pub async fn get_or_init_async<F: Future<Output = T>>(&self, f: impl FnOnce() -> F) -> &T {
+            let guard = poll_fn(|cx| {
+                let mut state = self.lock();
+                if self.value.get().is_some() {
+                    Poll::Ready(None)
+                } else if !state.initializing {
+                    state.initializing = true;
+                    Poll::Ready(Some(InitGuard(self)))
+                } else {
+                    state.wakers.push(cx.waker().clone());
+                    Poll::Pending
+                }
+            })
+            .await;
+
+            if let Some(guard) = guard {
+                let _ = self.value.set(f().await);
+                drop(guard);
+            }
+            self.value.get().unwrap()
+        }
+    }
+
+    /// Why a fixture function failed to return, i.e. its panic message or
+    /// timeout
+    pub struct Failure {
+        message: String,
+        panicked: bool,
+        /// Whether the function unwound with a [`Skip`]
+        skipped: bool,
+        origin: Option<PanicOrigin>,
+    }
+
+    /// Where a fixture function panicked
+    struct PanicOrigin {
+        location: String,
+        /// Whether the panic reported the failure of another fixture, so was
+        /// raised where that fixture was accessed rather than where it failed
+        propagated: bool,
+        backtrace: Backtrace,
+        /// Whether the backtrace has been reported, which only happens once
+        reported: AtomicBool,
+    }
+
+    thread_local! {
+        /// Whether panics on this thread are being caught by [`catch_panic`]
+        static CATCHING: Cell<bool> = const { Cell::new(false) };
+        /// The origin of the last panic caught by [`catch_panic`]
+        static ORIGIN: RefCell<Option<PanicOrigin>> = const { RefCell::new(None) };
+        /// Whether the panic being raised reports the failure of another
+        /// fixture
+        static PROPAGATING: Cell<bool> = const { Cell::new(false) };
+    }
+
+    /// Chains a panic hook recording the origin of panics inside
+    /// [`catch_panic`] instead of printing them, since they're reported by the
+    /// fixture's test and its dependents anyway
+    ///
+    /// The hook is installed once and consults a thread local, so concurrent
+    /// fixtures can't clobber each other's hooks.
+    fn install_panic_hook() {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| {
+            let previous = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |info| {
+                if !CATCHING.with(Cell::get) {
+                    return previous(info);
+                }
+                if cfg!(panic = "abort") {
+                    eprintln!(
+                        "note: a fixture panicked while built with `panic = \"abort\"`, so \
+                         the test binary will abort instead of reporting the failure to \
+                         dependent tests"
+                    );
+                    return previous(info);
+                }
+                let origin = PanicOrigin {
+                    location: info.location().map_or_else(String::new, |l| l.to_string()),
+                    propagated: PROPAGATING.with(|p| p.replace(false)),
+                    backtrace: Backtrace::capture(),
+                    reported: AtomicBool::new(false),
+                };
+                ORIGIN.with(|o| *o.borrow_mut() = Some(origin));
+            }));
+        });
+    }
+
+    /// Restores the previous catching state of the thread, even if unwinding
+    struct Catching(bool);
+
+    impl Drop for Catching {
+        fn drop(&mut self) {
+            CATCHING.with(|c| c.set(self.0));
+        }
+    }
+
+    /// Runs part of a fixture function, catching any panic along with its
+    /// location and backtrace
+    ///
+    /// Like an ordinary test, the body isn't required to be `UnwindSafe`, since
+    /// a failed fixture is never used afterwards.
+    #[cfg(not(panic = "abort"))]
+    pub fn catch_panic<R>(f: impl FnOnce() -> R) -> FixtureResult<R> {
+        install_panic_hook();
+        let catching = Catching(CATCHING.with(|c| c.replace(true)));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+        drop(catching);
+        result.map_err(|payload| {
+            let origin = ORIGIN.with(|o| o.borrow_mut().take());
+            Failure {
+                origin,
+                ..Failure::from_panic(payload)
+            }
+        })
+    }
+
+    /// Runs part of a fixture function when panics abort, so they can't be
+    /// caught but are explained before the test binary exits
+    #[cfg(panic = "abort")]
+    pub fn catch_panic<R>(f: impl FnOnce() -> R) -> FixtureResult<R> {
+        install_panic_hook();
+        let catching = Catching(CATCHING.with(|c| c.replace(true)));
+        let result = f();
+        drop(catching);
+        Ok(result)
+    }
+
+    /// Runs a synchronous fixture function on a helper thread with `catch`,
+    /// failing with the `timeout`'s message if it doesn't return in time
+    ///
+    /// The helper thread takes the current thread's name, so it's attributed
+    /// to the same test. A body which times out can't be interrupted, so its
+    /// thread is left running in the background.
+    pub fn run_on_thread<R, F>(
+        stack_size: Option<usize>,
+        timeout: Option<(Duration, &'static str)>,
+        catch: fn(F) -> FixtureResult<R>,
+        body: F,
+    ) -> FixtureResult<R>
+    where
+        R: Send + 'static,
+        F: FnOnce() -> R + Send + 'static,
+    {
+        use std::sync::mpsc::{self, RecvTimeoutError};
+
+        let (sender, receiver) = mpsc::channel();
+        let mut builder = std::thread::Builder::new();
+        if let Some(name) = std::thread::current().name() {
+            builder = builder.name(name.to_owned());
+        }
+        if let Some(stack_size) = stack_size {
+            builder = builder.stack_size(stack_size);
+        }
+        let thread = builder.spawn(move || {
+            let _ = sender.send(catch(body));
+        });
+        let thread = match thread {
+            Ok(thread) => thread,
+            Err(e) => return Err(Failure::new(format!("failed to spawn a thread: {}", e))),
+        };
+        let result = match timeout {
+            Some((duration, message)) => match receiver.recv_timeout(duration) {
+                Err(RecvTimeoutError::Timeout) => return Err(Failure::new(message)),
+                result => result.ok(),
+            },
+            None => receiver.recv().ok(),
+        };
+        match (result, thread.join()) {
+            (Some(result), _) => result,
+            // The body panicked with `no_catch_unwind`, so keeps unwinding
+            (None, Err(payload)) => std::panic::resume_unwind(payload),
+            (None, Ok(())) => unreachable!("fixture thread exited without a result"),
+        }
+    }
+
+    /// Runs a fixture function with `no_catch_unwind`, so any panic unwinds
+    /// through the fixture's initialization
+    pub fn no_catch_panic<R>(f: impl FnOnce() -> R) -> FixtureResult<R> {
+        Ok(f())
+    }
+
+    /// The payload unwound by a fixture which is skipped, or by a dependent
+    /// of a skipped fixture, holding the reason it was skipped
+    pub struct Skip(String);
+
+    /// Unwinds with a [`Skip`], so that a fixture function caching it is
+    /// skipped too, while a dependent test prints just the reason rather
+    /// than a panic message and backtrace
+    pub fn skip(reason: String) -> ! {
+        if !CATCHING.with(Cell::get) {
+            eprintln!("skipped: {}", reason);
+        }
+        std::panic::resume_unwind(Box::new(Skip(reason)))
+    }
+
+    /// Skips a fixture declared with `requires_env` if any of its variables
+    /// isn't set
+    pub fn require_env(vars: &[&str]) {
+        for var in vars {
+            if std::env::var_os(var).is_none() {
+                skip(format!("{} not set", var));
+            }
+        }
+    }
+
+    /// Runs the test of a fixture declared with `requires_env`, which passes
+    /// (noting why) if the fixture was skipped
+    pub fn unless_skipped<T: ?Sized + 'static>(
+        test: impl FnOnce() -> Result<Fixture<T>, FixtureError>,
+    ) -> Result<(), FixtureError> {
+        match test() {
+            Ok(_) => Ok(()),
+            Err(e) if e.skipped() => {
+                eprintln!("skipped: {}", e.message());
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    impl Failure {
+        /// Creates a failure which didn't come from a panic
+        pub fn new(message: impl Into<String>) -> Self {
+            Failure {
+                message: message.into(),
+                panicked: false,
+                skipped: false,
+                origin: None,
+            }
+        }
+
+        /// Extracts the message of a caught panic
+        pub fn from_panic(payload: Box<dyn Any + Send>) -> Self {
+            let payload = match payload.downcast::<Skip>() {
+                Ok(skip) => {
+                    return Failure {
+                        skipped: true,
+                        ..Failure::new(skip.0)
+                    }
+                }
+                Err(payload) => payload,
+            };
+            let message = match payload.downcast::<&'static str>() {
+                Ok(message) => (*message).to_owned(),
+                Err(payload) => match payload.downcast::<String>() {
+                    Ok(message) => *message,
+                    Err(_) => "Box<dyn Any>".to_owned(),
+                },
+            };
+            Failure {
+                panicked: true,
+                ..Failure::new(message)
+            }
+        }
+    }
+
+    impl Debug for Failure {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(&self.message)?;
+            if let Some(origin) = &self.origin {
+                match (origin.location.is_empty(), origin.propagated) {
+                    (true, _) => {}
+                    (false, false) => write!(f, " at {}", origin.location)?,
+                    (false, true) => write!(f, " (accessed at {})", origin.location)?,
+                }
+                if origin.backtrace.status() == BacktraceStatus::Captured
+                    && !origin.reported.swap(true, Ordering::Relaxed)
+                {
+                    write!(f, "\n\nfixture backtrace:\n{}", origin.backtrace)?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// The outcome of running a fixture function
+    pub type FixtureResult<O> = Result<O, Failure>;
+
+    /// The boxed future produced by an `async` fixture function
+    pub type FixtureFuture<O> = Pin<Box<dyn Future<Output = FixtureResult<O>> + Send>>;
+
+    /// The boxed future produced by a `!Send` `async` fixture function
+    pub type LocalFixtureFuture<O> = Pin<Box<dyn Future<Output = FixtureResult<O>>>>;
+
+    /// Whether a fixture has been initialized, as reported by
+    /// [`FixtureHandle::state`]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum FixtureState {
+        /// The fixture function hasn't been run yet
+        Uninitialized,
+        /// The fixture function is running
+        Initializing,
+        /// The fixture function succeeded
+        Ok,
+        /// The fixture function failed
+        Failed,
+    }
+
+    /// A cell which is replaced by an empty one whenever its fixture is reset
+    ///
+    /// Replaced cells are leaked rather than dropped, since dependents may
+    /// still be borrowing their values.
+    pub struct Resettable<C: 'static> {
+        first: C,
+        current: Mutex<Option<(usize, &'static C)>>,
+    }
+
+    impl<C> Resettable<C> {
+        pub const fn new(first: C) -> Self {
+            Resettable {
+                first,
+                current: Mutex::new(None),
+            }
+        }
+    }
+
+    impl<C: Default> Resettable<C> {
+        /// The cell for the given generation of the fixture, or a later one
+        /// if the fixture has been reset since
+        pub fn get(&self, generation: usize) -> &C {
+            if generation == 0 {
+                return &self.first;
+            }
+            let mut current = self.current.lock().unwrap_or_else(PoisonError::into_inner);
+            match *current {
+                Some((g, cell)) if g >= generation => cell,
+                _ => {
+                    let cell = Box::leak(Box::default());
+                    *current = Some((generation, cell));
+                    cell
+                }
+            }
+        }
+    }
+
+    /// Records when, and by which test, a fixture was initialized, along with
+    /// how many times it has been reset
+    pub struct Tracker {
+        generation: AtomicUsize,
+        started: Resettable<OnceCell<(Instant, Option<String>)>>,
+        duration: Resettable<OnceCell<Duration>>,
+        succeeded: Resettable<OnceCell<bool>>,
+    }
+
+    impl Default for Tracker {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl Tracker {
+        pub const fn new() -> Self {
+            Tracker {
+                generation: AtomicUsize::new(0),
+                started: Resettable::new(OnceCell::new()),
+                duration: Resettable::new(OnceCell::new()),
+                succeeded: Resettable::new(OnceCell::new()),
+            }
+        }
+
+        /// The number of times the fixture has been reset
+        pub fn generation(&self) -> usize {
+            self.generation.load(Ordering::SeqCst)
+        }
+
+        /// Runs the fixture function, recording how long it took
+        fn record<R>(&self, generation: usize, f: impl FnOnce() -> R) -> R {
+            let start = self.start(generation);
+            let result = f();
+            self.finish(start);
+            result
+        }
+
+        fn start(&self, generation: usize) -> (usize, Instant) {
+            let start = Instant::now();
+            let _ = self.started.get(generation).set((start, current_test()));
+            (generation, start)
+        }
+
+        fn finish(&self, (generation, start): (usize, Instant)) {
+            let _ = self.duration.get(generation).set(start.elapsed());
+        }
+
+        /// Records whether the fixture function succeeded in the given
+        /// generation
+        fn settle(&self, generation: usize, succeeded: bool) {
+            let _ = self.succeeded.get(generation).set(succeeded);
+        }
+
+        /// Resets the fixture if it failed and `TESTED_FIXTURE_RETRY` is set,
+        /// so that a retried test runs the fixture function again
+        pub fn retry_failed(&self) {
+            let retry = env_flag("TESTED_FIXTURE_RETRY");
+            let generation = self.generation();
+            if retry && self.succeeded.get(generation).get() == Some(&false) {
+                // Concurrent retries of the same failure only reset it once
+                let _ = self.generation.compare_exchange(
+                    generation,
+                    generation + 1,
+                    Ordering::SeqCst,
+                    Ordering::SeqCst,
+                );
+            }
+        }
+
+        fn state(&self) -> FixtureState {
+            let generation = self.generation();
+            match self.succeeded.get(generation).get() {
+                Some(true) => FixtureState::Ok,
+                Some(false) => FixtureState::Failed,
+                None if self.started.get(generation).get().is_some() => FixtureState::Initializing,
+                None => FixtureState::Uninitialized,
+            }
+        }
+    }
+
+    /// Whether the environment variable `name` is set to something other than
+    /// `0`
+    fn env_flag(name: &str) -> bool {
+        std::env::var_os(name).map_or(false, |value| !value.is_empty() && value != "0")
+    }
+
+    /// Whether dependents of a fixture fail rather than initialize it, since
+    /// its own test should have done so first
+    #[doc(hidden)]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Strictness {
+        /// Dependents may always initialize the fixture, e.g. because it has
+        /// no test of its own
+        Exempt,
+        /// Dependents fail if `TESTED_FIXTURE_STRICT` is set
+        Env,
+        /// Dependents always fail
+        Strict,
+    }
+
+    impl Strictness {
+        fn enforced(self) -> bool {
+            match self {
+                Strictness::Exempt => false,
+                Strictness::Env => env_flag("TESTED_FIXTURE_STRICT"),
+                Strictness::Strict => true,
+            }
+        }
+    }
+
+    /// A fixture which is initialized when first dereferenced, either by its
+    /// test or by a dependent
+    ///
+    /// Unlike `Lazy`, a failed initialization is cached rather than poisoned,
+    /// so every dependent reports the underlying failure. The handle also
+    /// records when, and by which test, the fixture was initialized.
+    pub struct FixtureHandle<T: ?Sized + 'static> {
+        cell: Resettable<AsyncOnceCell<Result<&'static T, FixtureError>>>,
+        injected: Resettable<OnceCell<&'static T>>,
+        init: fn() -> Result<&'static T, FixtureError>,
+        tracker: Tracker,
+        /// The tracker of the fixture this one is projected from, if any
+        source: Option<fn() -> &'static Tracker>,
+        name: &'static str,
+        strictness: Strictness,
+        /// Checked on every access, in debug builds
+        checks: Checks<T>,
+        /// The test which first found the `invariant` violated
+        violated: Resettable<OnceCell<Option<String>>>,
+        /// The fingerprint taken when the fixture was first accessed
+        fingerprinted: Resettable<OnceCell<String>>,
+    }
+
+    impl<T: ?Sized> FixtureHandle<T> {
+        #[doc(hidden)]
+        pub const fn new(
+            name: &'static str,
+            strictness: Strictness,
+            checks: Checks<T>,
+            init: fn() -> Result<&'static T, FixtureError>,
+        ) -> Self {
+            FixtureHandle {
+                cell: Resettable::new(AsyncOnceCell::new()),
+                injected: Resettable::new(OnceCell::new()),
+                init,
+                tracker: Tracker::new(),
+                source: None,
+                name,
+                strictness,
+                checks,
+                violated: Resettable::new(OnceCell::new()),
+                fingerprinted: Resettable::new(OnceCell::new()),
+            }
+        }
+
+        /// Creates a fixture projected from another, which shares its tracker
+        #[doc(hidden)]
+        pub const fn projected(
+            init: fn() -> Result<&'static T, FixtureError>,
+            source: fn() -> &'static Tracker,
+        ) -> Self {
+            FixtureHandle {
+                cell: Resettable::new(AsyncOnceCell::new()),
+                injected: Resettable::new(OnceCell::new()),
+                init,
+                tracker: Tracker::new(),
+                source: Some(source),
+                name: "",
+                // Checked by the fixture this one is projected from
+                strictness: Strictness::Exempt,
+                checks: Checks::NONE,
+                violated: Resettable::new(OnceCell::new()),
+                fingerprinted: Resettable::new(OnceCell::new()),
+            }
+        }
+
+        #[doc(hidden)]
+        pub fn tracker(&self) -> &Tracker {
+            match self.source {
+                Some(source) => source(),
+                None => &self.tracker,
+            }
+        }
+
+        /// Gets the fixture for a dependent, which in strict mode mustn't be
+        /// the one to initialize it
+        #[track_caller]
+        fn get(&self) -> &Result<&'static T, FixtureError> {
+            self.check_initialized();
+            self.check(self.init())
+        }
+
+        /// Panics if the fixture's `invariant` no longer holds, or its
+        /// fingerprint has changed since it was first accessed (right after
+        /// its initialization)
+        #[track_caller]
+        fn check<'a>(
+            &self,
+            result: &'a Result<&'static T, FixtureError>,
+        ) -> &'a Result<&'static T, FixtureError> {
+            let value = match result {
+                Ok(value) => *value,
+                Err(_) => return result,
+            };
+            let generation = self.tracker().generation();
+            if let Some(invariant) = self.checks.invariant {
+                if !invariant(value) {
+                    let test = self.violated.get(generation).get_or_init(current_test);
+                    let error = InvariantViolated {
+                        fixture: self.name,
+                        test: test.clone(),
+                    };
+                    panic!("{:?}", error);
+                }
+            }
+            if let Some(fingerprint) = self.checks.fingerprint {
+                let current = fingerprint(value);
+                let initial = self
+                    .fingerprinted
+                    .get(generation)
+                    .get_or_init(|| current.clone());
+                if *initial != current {
+                    let detected = match current_test() {
+                        Some(test) => format!(" (detected in test {})", test),
+                        None => String::new(),
+                    };
+                    panic!(
+                        "{} changed after it was initialized{}\n- {}\n+ {}",
+                        self.name, detected, initial, current
+                    );
+                }
+            }
+            result
+        }
+
+        /// Panics if the fixture hasn't been initialized (or injected) and
+        /// dependents aren't allowed to initialize it
+        #[track_caller]
+        fn check_initialized(&self) {
+            let tracker = self.tracker();
+            let generation = tracker.generation();
+            let initialized = tracker.started.get(generation).get().is_some()
+                || self.cell.get(generation).get().is_some();
+            if !initialized && self.strictness.enforced() {
+                panic!(
+                    "{} was not initialized by its own test; run order/selection problem",
+                    self.name
+                );
+            }
+        }
+
+        fn init(&self) -> &Result<&'static T, FixtureError> {
+            let tracker = self.tracker();
+            let generation = tracker.generation();
+            self.cell.get(generation).get_or_init(|| {
+                if self.source.is_some() {
+                    // Recorded by the fixture this one is projected from
+                    return (self.init)();
+                }
+                let result = tracker.record(generation, self.init);
+                tracker.settle(generation, result.is_ok());
+                result
+            })
+        }
+
+        /// Gets the fixture, returning the failure of its test instead of
+        /// panicking
+        ///
+        /// In strict mode, this still panics if the fixture's own test hasn't
+        /// initialized it yet.
+        #[track_caller]
+        pub fn try_get(&'static self) -> Result<&'static T, &'static FixtureError> {
+            self.get().as_ref().map(|v| *v)
+        }
+
+        /// Gets the fixture as the result of its test, which may initialize
+        /// it even in strict mode
+        #[doc(hidden)]
+        pub fn verify(&'static self) -> Result<Fixture<T>, FixtureError> {
+            match self.check(self.init()) {
+                Ok(v) => Ok(Fixture(*v)),
+                Err(e) => Err(forward(e.fixture, e)),
+            }
+        }
+
+        /// Whether the fixture has been initialized, without initializing it
+        pub fn state(&'static self) -> FixtureState {
+            self.tracker().state()
+        }
+
+        /// How long the fixture function took to run, if it has finished
+        pub fn init_duration(&'static self) -> Option<Duration> {
+            let tracker = self.tracker();
+            tracker.duration.get(tracker.generation()).get().copied()
+        }
+
+        /// The name of the test which triggered the fixture's initialization
+        /// (whether its own test or a dependent), if it has been initialized
+        /// by a named test thread
+        pub fn triggered_by(&'static self) -> Option<&'static str> {
+            let tracker = self.tracker();
+            let (_, test) = tracker.started.get(tracker.generation()).get()?;
+            test.as_deref()
+        }
+
+        /// Clears the fixture's cached result, so that the next access runs
+        /// the fixture function again (e.g. to retry a failed fixture)
+        ///
+        /// Resetting a tuple fixture resets all of its elements. If the
+        /// fixture is being initialized, accesses which have already begun
+        /// still receive the result of that initialization. Previous values
+        /// are leaked rather than dropped, since earlier dependents may still
+        /// be borrowing them.
+        pub fn reset(&'static self) {
+            self.tracker().generation.fetch_add(1, Ordering::SeqCst);
+        }
+
+        /// The value injected with [`FixtureHandle::set`], if any
+        #[doc(hidden)]
+        pub fn injected(&'static self) -> Option<&'static T> {
+            let generation = self.tracker().generation();
+            self.injected.get(generation).get().copied()
+        }
+    }
+
+    impl<T> FixtureHandle<T> {
+        /// Injects the fixture's value (e.g. to point it at an already running
+        /// server while debugging), skipping the fixture function
+        ///
+        /// The fixture's test then passes without running the function. This
+        /// fails if the fixture has already started initializing.
+        pub fn set(&'static self, value: T) -> Result<(), AlreadyInitialized> {
+            let tracker = self.tracker();
+            let generation = tracker.generation();
+            if tracker.started.get(generation).get().is_some() {
+                return Err(AlreadyInitialized);
+            }
+            let value = leak(value);
+            let cell = self.cell.get(generation);
+            cell.set(Ok(value)).map_err(|_| AlreadyInitialized)?;
+            let _ = self.injected.get(generation).set(value);
+            tracker.settle(generation, true);
+            Ok(())
+        }
+    }
+
+    /// The error returned by [`FixtureHandle::set`] when the fixture has
+    /// already started initializing
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct AlreadyInitialized;
+
+    impl Display for AlreadyInitialized {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("fixture already initialized")
+        }
+    }
+
+    impl Error for AlreadyInitialized {}
+
+    /// The error reported when an injected fixture is rejected by its
+    /// `verify_injected` predicate
+    pub struct RejectedInjection;
+
+    impl Debug for RejectedInjection {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("injected value rejected by `verify_injected`")
+        }
+    }
+
+    /// Checks that `exported`, named by `path`, re-exports the fixture rather
+    /// than naming another static of the same type
+    #[track_caller]
+    pub fn assert_exported<T: 'static>(fixture: &'static T, exported: &'static T, path: &str) {
+        assert!(
+            std::ptr::eq(fixture, exported),
+            "`{}` is not a re-export of this fixture",
+            path
+        );
+    }
+
+    /// Passes the test of a fixture whose value was injected, unless it is
+    /// rejected by `verify_injected`
+    pub fn verify_injected<T: ?Sized + 'static>(
+        value: &'static T,
+        verify: impl FnOnce(&T) -> bool,
+    ) -> Result<Fixture<T>, FixtureError> {
+        match verify(value) {
+            true => Ok(Fixture(value)),
+            false => Err(FixtureError::new(FailureKind::Failed, RejectedInjection)),
+        }
+    }
+
+    impl<T: ?Sized> Deref for FixtureHandle<T> {
+        type Target = T;
+        #[track_caller]
+        fn deref(&self) -> &T {
+            match self.get() {
+                Ok(v) => v,
+                Err(e) => fail(e),
+            }
+        }
+    }
+
+    /// A fixture declared with `clone`, which hands each dependent its own
+    /// copy of the cached value
+    pub struct ClonedFixture<T: Clone + 'static> {
+        get: fn() -> Result<&'static T, &'static FixtureError>,
+    }
+
+    impl<T: Clone> ClonedFixture<T> {
+        pub const fn new(get: fn() -> Result<&'static T, &'static FixtureError>) -> Self {
+            ClonedFixture { get }
+        }
+
+        /// Clones the fixture, panicking if it failed
+        #[track_caller]
+        pub fn cloned(&self) -> T {
+            match (self.get)() {
+                Ok(v) => v.clone(),
+                Err(e) => fail(e),
+            }
+        }
+
+        /// Clones the fixture, returning the failure of its test instead of
+        /// panicking
+        pub fn try_cloned(&self) -> Result<T, &'static FixtureError> {
+            (self.get)().cloned()
+        }
+    }
+
+    /// The storage of a fixture declared with `once`, from which its value can
+    /// be taken by a single dependent
+    pub struct TakeCell<T> {
+        value: Mutex<Option<T>>,
+        taker: Mutex<Option<String>>,
+    }
+
+    impl<T> From<T> for TakeCell<T> {
+        fn from(value: T) -> Self {
+            TakeCell {
+                value: Mutex::new(Some(value)),
+                taker: Mutex::new(None),
+            }
+        }
+    }
+
+    /// A fixture declared with `once`, which moves its value to the first
+    /// dependent to take it
+    pub struct TakeFixture<T: 'static> {
+        fixture: &'static str,
+        get: fn() -> Result<&'static TakeCell<T>, &'static FixtureError>,
+    }
+
+    impl<T> TakeFixture<T> {
+        pub const fn new(
+            fixture: &'static str,
+            get: fn() -> Result<&'static TakeCell<T>, &'static FixtureError>,
+        ) -> Self {
+            TakeFixture { fixture, get }
+        }
+
+        /// Takes the fixture, panicking if it failed or was already taken
+        #[track_caller]
+        pub fn take(&self) -> T {
+            let cell = match (self.get)() {
+                Ok(cell) => cell,
+                Err(e) => fail(e),
+            };
+            let mut value = cell.value.lock().unwrap_or_else(PoisonError::into_inner);
+            let mut taker = cell.taker.lock().unwrap_or_else(PoisonError::into_inner);
+            match value.take() {
+                Some(v) => {
+                    *taker = Some(current_test().unwrap_or_else(|| "<unnamed>".to_owned()));
+                    v
+                }
+                None => panic!(
+                    "fixture {} already taken by test {}",
+                    self.fixture,
+                    taker.as_deref().unwrap_or("<unnamed>")
+                ),
+            }
+        }
+    }
+
+    /// The storage of a fixture declared with `releasable`, from which its
+    /// value can be dropped once it is no longer needed
+    pub struct ReleaseCell<T> {
+        value: RwLock<Option<T>>,
+    }
+
+    impl<T> From<T> for ReleaseCell<T> {
+        fn from(value: T) -> Self {
+            ReleaseCell {
+                value: RwLock::new(Some(value)),
+            }
+        }
+    }
+
+    /// A fixture declared with `releasable`, which is borrowed with
+    /// [`ReleasableFixture::read`] until it is dropped by
+    /// [`ReleasableFixture::release`]
+    pub struct ReleasableFixture<T: 'static> {
+        fixture: &'static str,
+        released: AtomicBool,
+        get: fn() -> Result<&'static ReleaseCell<T>, &'static FixtureError>,
+        tracker: fn() -> &'static Tracker,
+    }
+
+    impl<T> ReleasableFixture<T> {
+        pub const fn new(
+            fixture: &'static str,
+            get: fn() -> Result<&'static ReleaseCell<T>, &'static FixtureError>,
+            tracker: fn() -> &'static Tracker,
+        ) -> Self {
+            ReleasableFixture {
+                fixture,
+                released: AtomicBool::new(false),
+                get,
+                tracker,
+            }
+        }
+
+        #[track_caller]
+        fn already_released(&self) -> ! {
+            panic!("fixture {} already released", self.fixture)
+        }
+
+        /// Borrows the fixture, panicking if it failed or was released
+        ///
+        /// The fixture can't be released while it is borrowed.
+        #[track_caller]
+        pub fn read(&self) -> ReleaseGuard<T> {
+            if self.released.load(Ordering::SeqCst) {
+                self.already_released();
+            }
+            let cell = match (self.get)() {
+                Ok(cell) => cell,
+                Err(e) => fail(e),
+            };
+            let value = cell.value.read().unwrap_or_else(PoisonError::into_inner);
+            if value.is_none() {
+                self.already_released();
+            }
+            ReleaseGuard(value)
+        }
+
+        /// Drops the fixture (once any borrows have ended), so that later
+        /// accesses panic rather than keeping it alive for the entire run
+        ///
+        /// A fixture which hasn't been initialized yet is never initialized.
+        pub fn release(&self) {
+            self.released.store(true, Ordering::SeqCst);
+            if (self.tracker)().state() == FixtureState::Uninitialized {
+                return;
+            }
+            if let Ok(cell) = (self.get)() {
+                let value = cell
+                    .value
+                    .write()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .take();
+                drop(value);
+            }
+        }
+    }
+
+    /// A borrow of a fixture declared with `releasable`
+    pub struct ReleaseGuard<T: 'static>(RwLockReadGuard<'static, Option<T>>);
+
+    impl<T> Deref for ReleaseGuard<T> {
+        type Target = T;
+        fn deref(&self) -> &T {
+            self.0.as_ref().unwrap()
+        }
+    }
+
+    /// A fixture declared with `arc`, which dereferences like a
+    /// [`FixtureHandle`] but can also be shared as an `Arc`
+    pub struct ArcFixture<T: ?Sized + 'static> {
+        fixture: FixtureHandle<T>,
+        get: fn() -> Result<&'static Arc<T>, &'static FixtureError>,
+    }
+
+    impl<T: ?Sized> ArcFixture<T> {
+        pub const fn new(
+            resolve: fn() -> Result<&'static T, FixtureError>,
+            get: fn() -> Result<&'static Arc<T>, &'static FixtureError>,
+            tracker: fn() -> &'static Tracker,
+        ) -> Self {
+            ArcFixture {
+                fixture: FixtureHandle::projected(resolve, tracker),
+                get,
+            }
+        }
+
+        /// The fixture's handle, describing its initialization
+        pub fn handle(&'static self) -> &'static FixtureHandle<T> {
+            &self.fixture
+        }
+
+        /// Gets the fixture, returning the failure of its test instead of
+        /// panicking
+        pub fn try_get(&'static self) -> Result<&'static T, &'static FixtureError> {
+            self.fixture.try_get()
+        }
+
+        /// Shares the fixture, e.g. to move it into a spawned thread or task,
+        /// panicking if it failed
+        #[track_caller]
+        pub fn share(&self) -> Arc<T> {
+            match (self.get)() {
+                Ok(v) => Arc::clone(v),
+                Err(e) => fail(e),
+            }
+        }
+    }
+
+    impl<T: ?Sized> Deref for ArcFixture<T> {
+        type Target = T;
+        #[track_caller]
+        fn deref(&self) -> &T {
+            &self.fixture
+        }
+    }
+
+    /// The storage of a fixture declared with `local`, which remembers the
+    /// thread which built it
+    pub struct UnsyncCell<T> {
+        thread: ThreadId,
+        value: T,
+    }
+
+    // SAFETY: the value is only accessed by the thread which built it (see
+    // `UnsyncFixture::try_get`), and fixtures are never dropped
+    unsafe impl<T> Sync for UnsyncCell<T> {}
+
+    impl<T> From<T> for UnsyncCell<T> {
+        fn from(value: T) -> Self {
+            UnsyncCell {
+                thread: thread::current().id(),
+                value,
+            }
+        }
+    }
+
+    /// A fixture declared with `local`, which needn't be `Sync` but can only
+    /// be used by the thread which built it
+    pub struct UnsyncFixture<T: 'static> {
+        fixture: &'static str,
+        get: fn() -> Result<&'static UnsyncCell<T>, &'static FixtureError>,
+    }
+
+    impl<T> UnsyncFixture<T> {
+        pub const fn new(
+            fixture: &'static str,
+            get: fn() -> Result<&'static UnsyncCell<T>, &'static FixtureError>,
+        ) -> Self {
+            UnsyncFixture { fixture, get }
+        }
+
+        /// Gets the fixture, returning the failure of its test instead of
+        /// panicking
+        ///
+        /// This still panics if the fixture was built by another thread.
+        #[track_caller]
+        pub fn try_get(&self) -> Result<&'static T, &'static FixtureError> {
+            let cell = (self.get)()?;
+            if cell.thread != thread::current().id() {
+                panic!(
+                    "fixture {} is `local`, so can only be used by the thread which built it (run tests with `--test-threads=1`)",
+                    self.fixture
+                );
+            }
+            Ok(&cell.value)
+        }
+    }
+
+    impl<T> Deref for UnsyncFixture<T> {
+        type Target = T;
+        #[track_caller]
+        fn deref(&self) -> &T {
+            match self.try_get() {
+                Ok(v) => v,
+                Err(e) => fail(e),
+            }
+        }
+    }
+
+    /// The per-thread storage of a fixture declared with `thread_local`
+    pub struct LocalCell<T> {
+        value: RefCell<Option<Result<T, FixtureError>>>,
+    }
+
+    impl<T> Default for LocalCell<T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T> LocalCell<T> {
+        pub const fn new() -> Self {
+            LocalCell {
+                value: RefCell::new(None),
+            }
+        }
+    }
+
+    /// A fixture declared with `thread_local`, which is built once per thread
+    /// and so can't be borrowed for `'static`
+    pub struct LocalFixture<T: 'static> {
+        key: &'static LocalKey<LocalCell<T>>,
+        init: fn() -> Result<T, FixtureError>,
+    }
+
+    impl<T> LocalFixture<T> {
+        pub const fn new(
+            key: &'static LocalKey<LocalCell<T>>,
+            init: fn() -> Result<T, FixtureError>,
+        ) -> Self {
+            LocalFixture { key, init }
+        }
+
+        /// Calls `f` with this thread's fixture, building it first if
+        /// necessary and panicking if it failed
+        #[track_caller]
+        pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+            // Raised outside of the closure, which can't track its caller
+            let result = self.try_with(|result| match result {
+                Ok(v) => Ok(f(v)),
+                Err(e) => Err(Report::new(e)),
+            });
+            match result {
+                Ok(v) => v,
+                Err(report) => report.raise(),
+            }
+        }
+
+        /// Calls `f` with this thread's fixture, or the failure of its
+        /// function, building it first if necessary
+        pub fn try_with<R>(&self, f: impl FnOnce(Result<&T, &FixtureError>) -> R) -> R {
+            self.key.with(|cell| {
+                if cell.value.borrow().is_none() {
+                    // Built before borrowing, since it may use other fixtures
+                    let value = (self.init)();
+                    cell.value.borrow_mut().get_or_insert(value);
+                }
+                let value = cell.value.borrow();
+                f(value.as_ref().unwrap().as_ref())
+            })
+        }
+
+        /// Verifies this thread's fixture, as the fixture's test
+        pub fn verify(&self) -> Result<(), LocalFailure> {
+            self.try_with(|result| match result {
+                Ok(_) => Ok(()),
+                Err(e) => Err(LocalFailure(format!("{:?}", e))),
+            })
+        }
+    }
+
+    /// The failure of a `thread_local` fixture's test, which can't return the
+    /// thread's own [`FixtureError`]
+    pub struct LocalFailure(String);
+
+    impl Debug for LocalFailure {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(&self.0)
+        }
+    }
+
+    /// A fixture declared with `fresh`, which runs the fixture function again
+    /// for every dependent rather than caching its value
+    pub struct FreshFixture<T: 'static> {
+        init: fn() -> Result<T, FixtureError>,
+    }
+
+    impl<T> FreshFixture<T> {
+        pub const fn new(init: fn() -> Result<T, FixtureError>) -> Self {
+            FreshFixture { init }
+        }
+
+        /// Runs the fixture function, panicking if it fails
+        #[track_caller]
+        pub fn get(&self) -> T {
+            match (self.init)() {
+                Ok(v) => v,
+                Err(e) => fail(&e),
+            }
+        }
+
+        /// Runs the fixture function, returning its failure instead of
+        /// panicking
+        pub fn try_get(&self) -> Result<T, FixtureError> {
+            (self.init)()
+        }
+    }
+
+    /// A fixture produced by an `async` function
+    ///
+    /// Dereferences exactly like a [`FixtureHandle`], blocking until the fixture
+    /// is initialized, but can also be awaited with [`AsyncFixture::get_async`].
+    pub struct AsyncFixture<T: ?Sized + 'static, O: 'static, F: 'static = FixtureFuture<O>> {
+        fixture: FixtureHandle<T>,
+        init: fn() -> F,
+        finish: fn(FixtureResult<O>) -> Result<&'static T, FixtureError>,
+    }
+
+    impl<T: ?Sized, O, F: Future<Output = FixtureResult<O>> + Unpin> AsyncFixture<T, O, F> {
+        pub const fn new(
+            name: &'static str,
+            strictness: Strictness,
+            checks: Checks<T>,
+            init: fn() -> F,
+            finish: fn(FixtureResult<O>) -> Result<&'static T, FixtureError>,
+            resolve: fn() -> Result<&'static T, FixtureError>,
+        ) -> Self {
+            AsyncFixture {
+                fixture: FixtureHandle::new(name, strictness, checks, resolve),
+                init,
+                finish,
+            }
+        }
+
+        /// Gets the fixture, blocking until it is initialized, and returning
+        /// the failure of its test instead of panicking
+        pub fn try_get(&'static self) -> Result<&'static T, &'static FixtureError> {
+            self.fixture.try_get()
+        }
+
+        /// The fixture's handle, describing its initialization
+        pub fn handle(&'static self) -> &'static FixtureHandle<T> {
+            &self.fixture
+        }
+
+        #[doc(hidden)]
+        pub fn tracker(&self) -> &Tracker {
+            self.fixture.tracker()
+        }
+
+        #[doc(hidden)]
+        pub fn verify(&'static self) -> Result<Fixture<T>, FixtureError> {
+            self.fixture.verify()
+        }
+
+        /// Injects the fixture's value, skipping the fixture function (see
+        /// [`FixtureHandle::set`])
+        pub fn set(&'static self, value: T) -> Result<(), AlreadyInitialized>
+        where
+            T: Sized,
+        {
+            self.fixture.set(value)
+        }
+
+        #[doc(hidden)]
+        pub fn injected(&'static self) -> Option<&'static T> {
+            self.fixture.injected()
+        }
+
+        /// Gets the fixture, awaiting its initialization on the current
+        /// executor instead of blocking if necessary
+        pub async fn get_async(&'static self) -> &'static T {
+            let tracker = self.tracker();
+            let generation = tracker.generation();
+            let cell = self.fixture.cell.get(generation);
+            if cell.get().is_none() {
+                self.fixture.check_initialized();
+                cell.get_or_init_async(|| async move {
+                    let start = tracker.start(generation);
+                    let mut future = (self.init)();
+                    let result =
+                        poll_fn(
+                            move |cx| match catch_panic(|| Pin::new(&mut future).poll(cx)) {
+                                Ok(poll) => poll,
+                                Err(failure) => Poll::Ready(Err(failure)),
+                            },
+                        )
+                        .await;
+                    tracker.finish(start);
+                    let result = (self.finish)(result);
+                    tracker.settle(generation, result.is_ok());
+                    result
+                })
+                .await;
+            }
+            &**self
+        }
+    }
+
+    impl<T: ?Sized, O, F> Deref for AsyncFixture<T, O, F> {
+        type Target = T;
+        #[track_caller]
+        fn deref(&self) -> &T {
+            &self.fixture
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        convert::Infallible,
+        fmt::Debug,
+        future::Future,
+        pin::Pin,
+        process::{ExitCode, Termination},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        task::{Context, Poll},
+    };
+
+    struct HeavySetup(u32);
+
+    impl HeavySetup {
+        fn build(v: u32) -> Self {
+            HeavySetup(v)
+        }
+    }
+
+    #[tested_fixture(
+        /// This is a test fixture
+        pub(crate) SETUP_0
+    )]
+    fn setup_with_attributes() -> HeavySetup {
+        HeavySetup::build(1)
+    }
+
+    #[tested_fixture(SETUP_1)]
+    fn setup() -> HeavySetup {
+        HeavySetup::build(1)
+    }
+
+    #[tested_fixture(SETUP_2: HeavySetup)]
+    fn try_setup() -> Result<HeavySetup, &'static str> {
+        Ok(HeavySetup::build(2))
+    }
+
+    #[tested_fixture(SETUP_UNTYPED)]
+    fn untyped_setup() -> Result<HeavySetup, &'static str> {
+        Ok(HeavySetup::build(2))
+    }
+
+    #[tested_fixture(SETUP_QUALIFIED)]
+    fn qualified_setup() -> std::io::Result<HeavySetup> {
+        Ok(HeavySetup::build(2))
+    }
+
+    type SetupResult = Result<HeavySetup, &'static str>;
+
+    #[tested_fixture(SETUP_ALIASED)]
+    fn aliased_setup() -> SetupResult {
+        Ok(HeavySetup::build(2))
+    }
+
+    #[tested_fixture(SETUP_ANNOTATED, error = &'static str)]
+    fn annotated_setup() -> SetupResult {
+        Ok(HeavySetup::build(2))
+    }
+
+    type BoxResult<T, E = Box<dyn std::error::Error + Send + Sync>> = Result<T, E>;
+
+    #[tested_fixture(SETUP_ANNOTATED_ASYNC, error = Box<dyn std::error::Error + Send + Sync>)]
+    async fn annotated_async_setup() -> BoxResult<HeavySetup> {
+        YieldNow(false).await;
+        Ok(HeavySetup::build(2))
+    }
+
+    #[tested_fixture(SETUP_SHARED, store_as = Arc<HeavySetup>)]
+    fn shared_setup() -> Result<HeavySetup, &'static str> {
+        Ok(HeavySetup::build(2))
+    }
+
+    #[tested_fixture(SETUP_WIDENED, store_as = u64)]
+    async fn widened_setup() -> u32 {
+        YieldNow(false).await;
+        2
+    }
+
+    #[tested_fixture(SETUP_MAPPED: u32, map = |setup| setup.0 * 2)]
+    fn mapped_setup() -> Result<HeavySetup, &'static str> {
+        Ok(HeavySetup::build(2))
+    }
+
+    #[tested_fixture(SETUP_MAPPED_ASYNC: String, map = |setup: HeavySetup| setup.0.to_string())]
+    async fn mapped_async_setup() -> std::io::Result<HeavySetup> {
+        YieldNow(false).await;
+        Ok(HeavySetup::build(2))
+    }
+
+    #[tested_fixture(SETUP_MAPPED_PANIC: u32, map = |_| -> u32 { panic!("failed to map") })]
+    #[ignore = "fails"]
+    fn mapped_panic_setup() -> Result<HeavySetup, &'static str> {
+        Ok(HeavySetup::build(2))
+    }
+
+    #[tested_fixture(SETUP_REFCELL)]
+    fn refcell_setup() -> HeavySetup {
+        let total = std::cell::RefCell::new(0);
+        let helper = &total;
+        let add = |v| *helper.borrow_mut() += v;
+        add(1);
+        add(2);
+        HeavySetup::build(total.into_inner())
+    }
+
+    #[tested_fixture(mut SETUP_MUT)]
+    fn mut_setup() -> Result<Vec<u32>, &'static str> {
+        Ok(vec![HeavySetup::build(2).0])
+    }
+
+    #[tested_fixture(mut SETUP_MUT_ASYNC: HeavySetup)]
+    async fn mut_async_setup() -> HeavySetup {
+        YieldNow(false).await;
+        HeavySetup::build(2)
+    }
+
+    #[tested_fixture(mut SETUP_RWLOCK, rwlock)]
+    fn rwlock_setup() -> Result<String, &'static str> {
+        Ok(format!("token-{}", HeavySetup::build(0).0))
+    }
+
+    #[tested_fixture(SETUP_CLONED, clone)]
+    fn cloned_setup() -> Result<Vec<u32>, &'static str> {
+        Ok(vec![HeavySetup::build(2).0])
+    }
+
+    #[tested_fixture(SETUP_CLONED_ASYNC: Vec<u32>, clone)]
+    async fn cloned_async_setup() -> Vec<u32> {
+        YieldNow(false).await;
+        vec![HeavySetup::build(2).0]
+    }
+
+    #[tested_fixture(SETUP_CLONED_FAIL: Vec<u32>, clone)]
+    #[ignore = "fails"]
+    fn cloned_fail_setup() -> Result<Vec<u32>, &'static str> {
+        Err("failed due to a shallow copy")
+    }
+
+    static FRESH_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+    #[tested_fixture(SETUP_FRESH, fresh)]
+    fn fresh_setup() -> Result<Vec<usize>, &'static str> {
+        Ok(vec![FRESH_RUNS.fetch_add(1, Ordering::SeqCst)])
+    }
+
+    #[tested_fixture(SETUP_FRESH_ASYNC, fresh)]
+    async fn fresh_async_setup() -> HeavySetup {
+        YieldNow(false).await;
+        HeavySetup::build(2)
+    }
+
+    #[tested_fixture(SETUP_FRESH_FAIL, fresh)]
+    #[ignore = "fails"]
+    fn fresh_fail_setup() -> Result<HeavySetup, &'static str> {
+        Err("failed due to stale state")
+    }
+
+    #[tested_fixture(SETUP_ONCE, once)]
+    fn once_setup() -> Result<HeavySetup, &'static str> {
+        Ok(HeavySetup::build(2))
+    }
+
+    #[tested_fixture(SETUP_ONCE_TWICE: HeavySetup, once)]
+    async fn once_twice_setup() -> HeavySetup {
+        YieldNow(false).await;
+        HeavySetup::build(2)
+    }
+
+    #[tested_fixture(SETUP_ARC, arc)]
+    fn arc_setup() -> Result<HeavySetup, &'static str> {
+        Ok(HeavySetup::build(2))
+    }
+
+    #[tested_fixture(SETUP_ARC_FAIL: HeavySetup, arc)]
+    #[ignore = "fails"]
+    fn arc_fail_setup() -> Result<HeavySetup, &'static str> {
+        Err("failed due to a dangling reference")
+    }
+
+    static LOCAL_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+    #[tested_fixture(SETUP_LOCAL, thread_local)]
+    fn local_setup() -> Result<std::cell::Cell<u32>, &'static str> {
+        LOCAL_RUNS.fetch_add(1, Ordering::SeqCst);
+        Ok(std::cell::Cell::new(HeavySetup::build(2).0))
+    }
+
+    #[tested_fixture(SETUP_LOCAL_FAIL: std::rc::Rc<u32>, thread_local)]
+    #[ignore = "fails"]
+    fn local_fail_setup() -> Result<std::rc::Rc<u32>, &'static str> {
+        Err("failed due to thread starvation")
+    }
+
+    #[tested_fixture(SETUP_TRACKED)]
+    fn tracked_setup() -> HeavySetup {
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        HeavySetup::build(33)
+    }
+
+    #[tested_fixture(SETUP_TRACKED_ASYNC)]
+    async fn tracked_async_setup() -> HeavySetup {
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        HeavySetup::build(34)
+    }
+
+    static FLAKY_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+    #[tested_fixture(SETUP_FLAKY: HeavySetup)]
+    #[ignore = "fails"]
+    fn flaky_setup() -> Result<HeavySetup, &'static str> {
+        match FLAKY_RUNS.fetch_add(1, Ordering::SeqCst) {
+            0 => Err("failed due to flakiness"),
+            n => Ok(HeavySetup::build(n as u32)),
+        }
+    }
+
+    static RETRIED_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+    #[tested_fixture(SETUP_RETRIED: HeavySetup)]
+    #[ignore = "fails"]
+    fn retried_setup() -> Result<HeavySetup, &'static str> {
+        match RETRIED_RUNS.fetch_add(1, Ordering::SeqCst) {
+            0 => Err("failed due to flakiness"),
+            n => Ok(HeavySetup::build(n as u32)),
+        }
+    }
+
+    static IN_FLIGHT_RUNS: AtomicUsize = AtomicUsize::new(0);
+    static IN_FLIGHT_RELEASED: std::sync::atomic::AtomicBool =
+        std::sync::atomic::AtomicBool::new(false);
+
+    #[tested_fixture(SETUP_IN_FLIGHT)]
+    #[ignore = "run by reset_during_initialization"]
+    fn in_flight_setup() -> u32 {
+        let run = IN_FLIGHT_RUNS.fetch_add(1, Ordering::SeqCst);
+        while run == 0 && !IN_FLIGHT_RELEASED.load(Ordering::SeqCst) {
+            std::thread::yield_now();
+        }
+        run as u32
+    }
+
+    #[tested_fixture(SETUP_INJECTED)]
+    #[ignore = "injected by inject_fixture"]
+    fn injected_setup() -> HeavySetup {
+        panic!("injected fixture was run")
+    }
+
+    #[tested_fixture(SETUP_INJECTED_REJECTED, verify_injected = |v: &HeavySetup| v.0 < 10)]
+    #[ignore = "injected by inject_fixture"]
+    fn injected_rejected_setup() -> HeavySetup {
+        HeavySetup::build(1)
+    }
+
+    #[tested_fixture(SETUP_FALLBACK, fallback = HeavySetup::build(38), cfg(any()))]
+    fn fallback_setup() -> HeavySetup {
+        HeavySetup::build(0)
+    }
+
+    #[tested_fixture(SETUP_DEFAULT, fallback = HeavySetup::build(39), default)]
+    fn default_setup() -> HeavySetup {
+        HeavySetup::build(40)
+    }
+
+    /// A value which points into itself, and so must never move
+    struct SelfRef {
+        value: u32,
+        this: std::sync::atomic::AtomicPtr<u32>,
+        _pinned: std::marker::PhantomPinned,
+    }
+
+    impl SelfRef {
+        fn build(value: u32) -> Pin<Box<Self>> {
+            let v = Box::pin(SelfRef {
+                value,
+                this: std::sync::atomic::AtomicPtr::new(std::ptr::null_mut()),
+                _pinned: std::marker::PhantomPinned,
+            });
+            v.this
+                .store(&v.value as *const u32 as *mut u32, Ordering::SeqCst);
+            v
+        }
+
+        fn is_intact(&self) -> bool {
+            std::ptr::eq(self.this.load(Ordering::SeqCst), &self.value)
+        }
+    }
+
+    #[tested_fixture(SETUP_PINNED)]
+    fn pinned_setup() -> Pin<Box<SelfRef>> {
+        SelfRef::build(42)
+    }
+
+    #[tested_fixture(SETUP_PINNED_TYPED: SelfRef)]
+    fn pinned_typed_setup() -> Result<Pin<Box<SelfRef>>, &'static str> {
+        Ok(SelfRef::build(43))
+    }
+
+    #[tested_fixture(SETUP_UNSYNC, local)]
+    #[ignore = "run by unsync_fixture"]
+    fn unsync_setup() -> Result<std::rc::Rc<HeavySetup>, &'static str> {
+        Ok(std::rc::Rc::new(HeavySetup::build(41)))
+    }
+
+    static RELEASED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Buffer(Vec<u8>);
+
+    impl Drop for Buffer {
+        fn drop(&mut self) {
+            RELEASED.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[tested_fixture(SETUP_RELEASABLE, releasable)]
+    fn releasable_setup() -> Buffer {
+        Buffer(vec![0; 1024])
+    }
+
+    #[tested_fixture(SETUP_RELEASED_UNUSED: Buffer, releasable)]
+    #[ignore = "released by release_fixture"]
+    fn released_unused_setup() -> Result<Buffer, &'static str> {
+        panic!("released fixture was run")
+    }
+
+    static TRANSIENT_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+    #[tested_fixture(SETUP_TRANSIENT: HeavySetup, retries = 2, retry_delay = "1ms")]
+    fn transient_setup() -> Result<HeavySetup, &'static str> {
+        match TRANSIENT_RUNS.fetch_add(1, Ordering::SeqCst) {
+            0 => Err("failed due to a 503"),
+            1 => panic!("failed due to a dropped connection"),
+            n => Ok(HeavySetup::build(n as u32)),
+        }
+    }
+
+    static EXHAUSTED_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+    #[tested_fixture(SETUP_EXHAUSTED: HeavySetup, retries = 1)]
+    #[ignore = "fails"]
+    fn exhausted_setup() -> Result<HeavySetup, &'static str> {
+        EXHAUSTED_RUNS.fetch_add(1, Ordering::SeqCst);
+        Err("failed due to an outage")
+    }
+
+    #[tested_fixture]
+    fn derived_setup() -> HeavySetup {
+        HeavySetup::build(6)
+    }
+
+    #[tested_fixture]
+    fn r#derived_step() -> Result<u32, &'static str> {
+        Ok(DERIVED_SETUP.0 + 1)
+    }
+
+    // Compiled out along with its static, which no longer refers to it
+    #[tested_fixture(SETUP_NEVER: HeavySetup)]
+    #[cfg(any())]
+    fn never_setup() -> HeavySetup {
+        HeavySetup::build(8)
+    }
+
+    #[tested_fixture(SETUP_EAGER_ONLY)]
+    #[cfg_attr(not(feature = "eager"), cfg(any()))]
+    fn eager_only_setup() -> HeavySetup {
+        HeavySetup::build(9)
+    }
+
+    #[tested_fixture((SETUP_TOKIO_LEFT, SETUP_TOKIO_RIGHT))]
+    #[cfg(feature = "tokio")]
+    fn tokio_tuple_setup() -> (HeavySetup, u32) {
+        (HeavySetup::build(10), 11)
+    }
+
+    #[tested_fixture(SETUP_TEST_ATTR, test_attr = std::prelude::v1::test)]
+    fn test_attr_setup() -> HeavySetup {
+        HeavySetup::build(12)
+    }
+
+    static REFERENCE_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+    #[tested_fixture(REFERENCE_DATA: Vec<u32>, no_test, error = &'static str)]
+    fn reference_data() -> Result<Vec<u32>, &'static str> {
+        REFERENCE_RUNS.fetch_add(1, Ordering::SeqCst);
+        Ok(vec![1, 2, 3])
+    }
+
+    #[tested_fixture(REFERENCE_MISSING: Vec<u32>, no_test)]
+    fn reference_missing() -> Result<Vec<u32>, &'static str> {
+        Err("failed due to a missing file")
+    }
+
+    #[tested_fixture(
+        static_attrs(allow(dead_code)),
+        test_attrs(ignore = "run by section_attrs"),
+        SETUP_SECTIONS
+    )]
+    fn sections_setup() -> HeavySetup {
+        HeavySetup::build(13)
+    }
+
+    #[tested_fixture(SETUP_UNUSED, static_attrs(allow(dead_code)))]
+    fn unused_setup() -> HeavySetup {
+        HeavySetup::build(14)
+    }
+
+    #[tested_fixture(SETUP_LEFTOVER_TEST)]
+    #[test]
+    fn leftover_test_setup() -> Result<HeavySetup, &'static str> {
+        Ok(HeavySetup::build(16))
+    }
+
+    /// A crate re-exporting this one, as if it didn't depend on it directly
+    mod facade {
+        pub use crate::*;
+    }
+
+    #[tested_fixture(crate = self::facade, SETUP_FACADE)]
+    fn facade_setup() -> HeavySetup {
+        HeavySetup::build(17)
+    }
+
+    #[tested_fixture(r#final)]
+    fn r#try() -> HeavySetup {
+        HeavySetup::build(18)
+    }
+
+    #[tested_fixture((r#static, r#MATCH: u32))]
+    fn r#loop() -> Result<(HeavySetup, u32), &'static str> {
+        Ok((HeavySetup::build(r#final.0 + 1), 20))
+    }
+
+    #[tested_fixture(r#async, fresh)]
+    fn r#await() -> HeavySetup {
+        HeavySetup::build(21)
+    }
+
+    #[tested_fixture(r#dyn: HeavySetup, r#impl: u32, no_test)]
+    async fn r#yield() -> (HeavySetup, u32) {
+        (HeavySetup::build(22), 23)
+    }
+
+    #[tested_fixture(r#ref: HeavySetup)]
+    #[ignore = "fails"]
+    fn r#match() -> Result<HeavySetup, &'static str> {
+        Err("failed due to a reserved word")
+    }
+
+    #[tested_fixture(pub SETUP_EXPOSED, exposed(feature = "std-sync"))]
+    fn exposed_setup() -> HeavySetup {
+        HeavySetup::build(27)
+    }
+
+    static COMPANION_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+    #[tested_fixture(SETUP_COMPANION, expose_fn = run_companion_setup)]
+    fn companion_setup() -> Result<HeavySetup, &'static str> {
+        let n = COMPANION_RUNS.fetch_add(1, Ordering::SeqCst);
+        Ok(HeavySetup::build(n as u32))
+    }
+
+    #[tested_fixture(SETUP_ASYNC_COMPANION, expose_fn = run_async_companion_setup)]
+    async fn async_companion_setup() -> HeavySetup {
+        YieldNow(false).await;
+        HeavySetup::build(28)
+    }
+
+    #[tested_fixture(SETUP_PLACEHOLDER: _)]
+    fn placeholder_setup() -> Result<std::collections::HashMap<u32, Vec<String>>, &'static str> {
+        Ok(vec![(1, vec!["one".to_owned()])].into_iter().collect())
+    }
+
+    trait Provider {
+        type Output;
+    }
+
+    impl Provider for HeavySetup {
+        type Output = Result<u32, &'static str>;
+    }
+
+    struct Wrapper<T>(T);
+
+    #[tested_fixture(SETUP_PROJECTED_TYPE: <HeavySetup as Provider>::Output)]
+    fn projected_type_setup() -> Result<<HeavySetup as Provider>::Output, &'static str> {
+        Ok(Ok(29))
+    }
+
+    #[tested_fixture(SETUP_LIFETIMES: Vec<std::borrow::Cow<'static, str>>)]
+    fn lifetimes_setup() -> Result<Vec<std::borrow::Cow<'static, str>>, String> {
+        Ok(vec!["borrowed".into(), String::from("owned").into()])
+    }
+
+    #[tested_fixture(SETUP_TURBOFISH: Wrapper::<u8>, no_test)]
+    fn turbofish_setup() -> Result<Wrapper<u8>, &'static str> {
+        Ok(Wrapper(30))
+    }
+
+    #[tested_fixture(SETUP_RENAMED, test_name = "fixture_renamed_setup")]
+    fn renamed_setup() -> HeavySetup {
+        HeavySetup::build(13)
+    }
+
+    #[tested_fixture(SETUP_RENAMED_LOCAL: std::cell::Cell<u32>, thread_local, test_name = "fixture_renamed_local")]
+    fn renamed_local_setup() -> std::cell::Cell<u32> {
+        std::cell::Cell::new(14)
+    }
+
+    #[tested_fixture(SETUP_ORDERED, order = 0)]
+    fn ordered_setup() -> HeavySetup {
+        HeavySetup::build(15)
+    }
+
+    #[tested_fixture(SETUP_STRICT, strict)]
+    #[ignore = "run by strict_fixture"]
+    fn strict_setup() -> HeavySetup {
+        HeavySetup::build(16)
+    }
+
+    #[tested_fixture(SETUP_STRICT_ENV)]
+    #[ignore = "run by strict_env_fixture"]
+    fn strict_env_setup() -> HeavySetup {
+        HeavySetup::build(17)
+    }
+
+    #[test]
+    #[ignore = "run by strict_env_fixture"]
+    fn strict_env_dependent() {
+        assert_eq!(SETUP_STRICT_ENV.0, 17);
+    }
+
+    #[tested_fixture(SETUP_INVARIANT: AtomicUsize, invariant = |v: &AtomicUsize| v.load(Ordering::SeqCst) < 10)]
+    #[ignore = "run by invariant_fixture"]
+    fn invariant_setup() -> AtomicUsize {
+        AtomicUsize::new(1)
+    }
+
+    #[tested_fixture(SETUP_BROKEN_INVARIANT: u32, invariant = |v: &u32| *v % 2 == 0)]
+    #[ignore = "fails"]
+    fn broken_invariant_setup() -> u32 {
+        3
+    }
+
+    /// A fixture which can be mutated through a shared reference
+    struct Sneaky {
+        value: std::cell::Cell<u32>,
+    }
+
+    // Only ever accessed by `unchanged_fixture`
+    unsafe impl Sync for Sneaky {}
+
+    #[tested_fixture(SETUP_SNEAKY: Sneaky, verify_unchanged = |v: &Sneaky| v.value.get())]
+    #[ignore = "run by unchanged_fixture"]
+    fn sneaky_setup() -> Sneaky {
+        Sneaky {
+            value: std::cell::Cell::new(1),
+        }
+    }
+
+    /// Hashes its current count
+    struct Counter(AtomicUsize);
+
+    impl std::hash::Hash for Counter {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            self.0.load(Ordering::SeqCst).hash(state)
+        }
+    }
+
+    #[tested_fixture(SETUP_HASHED: Counter, verify_unchanged)]
+    #[ignore = "run by unchanged_fixture"]
+    fn hashed_setup() -> Counter {
+        Counter(AtomicUsize::new(1))
+    }
+
+    #[tested_fixture(SETUP_UNAVAILABLE, requires_env = "TESTED_FIXTURE_UNSET_VAR")]
+    fn unavailable_setup() -> u32 {
+        unreachable!("skipped")
+    }
+
+    #[tested_fixture(SETUP_AFTER_UNAVAILABLE)]
+    #[ignore = "fails"]
+    fn after_unavailable_setup() -> u32 {
+        *SETUP_UNAVAILABLE + 1
+    }
+
+    #[tested_fixture(
+        SETUP_AVAILABLE,
+        requires_env = "PATH",
+        requires_env = "TESTED_FIXTURE_UNSET_VAR"
+    )]
+    fn available_setup() -> u32 {
+        unreachable!("skipped")
+    }
+
+    #[tested_fixture(SETUP_WITH_PATH, requires_env = "PATH")]
+    fn with_path_setup() -> u32 {
+        1
+    }
+
+    fn in_memory_setup() -> u32 {
+        2
+    }
+
+    #[tested_fixture(SETUP_ALTERNATIVE, alternative(cfg(test), in_memory_setup))]
+    #[ignore = "run by alternative_fixture"]
+    fn alternative_setup() -> u32 {
+        unreachable!("replaced by in_memory_setup")
+    }
+
+    #[tested_fixture(SETUP_UNUSED_ALTERNATIVE, alternative(cfg(any()), || 2))]
+    #[ignore = "run by alternative_fixture"]
+    async fn unused_alternative_setup() -> u32 {
+        1
+    }
+
+    /// Asserts that `value` is even, reporting the caller's location
+    #[track_caller]
+    fn assert_even(value: u32) -> u32 {
+        assert!(value % 2 == 0, "{} is odd", value);
+        value
+    }
+
+    static TRACKED_LINE: AtomicUsize = AtomicUsize::new(0);
+
+    #[tested_fixture(SETUP_ODD)]
+    #[ignore = "fails"]
+    #[track_caller]
+    fn odd_setup() -> u32 {
+        TRACKED_LINE.store(line!() as usize + 1, Ordering::SeqCst);
+        assert_even(1)
+    }
+
+    static CASCADE_LINES: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+
+    #[tested_fixture(SETUP_CASCADE_BASE: u32)]
+    #[ignore = "fails"]
+    fn cascade_base_setup() -> u32 {
+        CASCADE_LINES[0].store(line!() as usize + 1, Ordering::SeqCst);
+        panic!("base exploded")
+    }
+
+    #[tested_fixture(SETUP_CASCADE: u32)]
+    #[ignore = "run by cascaded_failure_location"]
+    fn cascade_setup() -> u32 {
+        CASCADE_LINES[1].store(line!() as usize + 1, Ordering::SeqCst);
+        *SETUP_CASCADE_BASE + 1
+    }
+
+    static CACHED_FAIL_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+    #[tested_fixture(SETUP_CACHED_FAIL: HeavySetup)]
+    #[ignore = "fails"]
+    fn cached_fail_setup() -> Result<HeavySetup, &'static str> {
+        CACHED_FAIL_RUNS.fetch_add(1, Ordering::SeqCst);
+        Err("failed due to cached failure")
+    }
+
+    static TORN_DOWN: std::sync::Mutex<Vec<u32>> = std::sync::Mutex::new(Vec::new());
+
+    #[tested_fixture(SETUP_TEARDOWN_1, teardown = |v| TORN_DOWN.lock().unwrap().push(v.0))]
+    fn teardown_1_setup() -> HeavySetup {
+        HeavySetup::build(31)
+    }
+
+    #[tested_fixture(
+        SETUP_TEARDOWN_2,
+        teardown = |v: &HeavySetup| TORN_DOWN.lock().unwrap().push(v.0)
+    )]
+    fn teardown_2_setup() -> Result<HeavySetup, &'static str> {
+        Ok(HeavySetup::build(SETUP_TEARDOWN_1.0 + 1))
+    }
+
+    #[tested_fixture(SETUP_TEARDOWN_FAIL: HeavySetup, teardown = |v| TORN_DOWN.lock().unwrap().push(v.0))]
+    #[ignore = "fails"]
+    fn teardown_fail_setup() -> Result<HeavySetup, &'static str> {
+        Err("failed due to a leaked socket")
+    }
+
+    #[tested_fixture(mut SETUP_MUT_FAIL: HeavySetup)]
+    #[ignore = "fails"]
+    fn mut_fail_setup() -> Result<HeavySetup, &'static str> {
+        Err("failed due to write contention")
+    }
+
+    #[tested_fixture(SETUP_UNCAUGHT, no_catch_unwind)]
+    fn uncaught_setup() -> Result<HeavySetup, &'static str> {
+        Ok(HeavySetup::build(2))
+    }
+
+    #[tested_fixture(SETUP_UNCAUGHT_PANIC, no_catch_unwind)]
+    #[ignore = "fails"]
+    fn uncaught_panic_setup() -> HeavySetup {
+        panic!("failed due to unwinding through FFI")
+    }
+
+    #[tested_fixture(SETUP_PANIC_MSG, expect_panic)]
+    fn expected_panic_setup() {
+        let setup = HeavySetup::build(2);
+        panic!("rejected setup {}", setup.0)
+    }
+
+    #[tested_fixture(SETUP_PANIC_STR: str, expect_panic)]
+    async fn expected_async_panic_setup() -> HeavySetup {
+        YieldNow(false).await;
+        panic!("rejected async setup")
+    }
+
+    #[tested_fixture(SETUP_NO_PANIC, expect_panic)]
+    #[ignore = "fails"]
+    fn missing_panic_setup() -> HeavySetup {
+        HeavySetup::build(2)
+    }
+
+    #[tested_fixture(SETUP_3: HeavySetup)]
+    #[ignore = "fails"]
+    fn fail_setup() -> Result<HeavySetup, &'static str> {
+        Err("failed due to reticulated splines")
+    }
+
+    #[tested_fixture(SETUP_4)]
+    #[ignore = "fails"]
+    fn panic_setup() -> HeavySetup {
+        panic!("failed due to normalized social network")
+    }
+
+    #[tested_fixture(SETUP_ASSERT)]
+    #[ignore = "fails"]
+    fn assert_setup() -> HeavySetup {
+        let setup = HeavySetup::build(1);
+        assert!(setup.0 == 2, "expected {} to be 2", setup.0);
+        setup
+    }
+
+    #[tested_fixture(SETUP_PAYLOAD)]
+    #[ignore = "fails"]
+    fn payload_setup() -> HeavySetup {
+        std::panic::panic_any(HeavySetup::build(1))
+    }
+
+    /// A future which is pending once before completing
+    struct YieldNow(bool);
+
+    impl Future for YieldNow {
+        type Output = ();
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                return Poll::Ready(());
+            }
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    #[tested_fixture(SETUP_5)]
+    async fn async_setup() -> HeavySetup {
+        YieldNow(false).await;
+        HeavySetup::build(5)
+    }
+
+    #[tested_fixture(SETUP_6: HeavySetup)]
+    async fn try_async_setup() -> Result<HeavySetup, &'static str> {
+        let v = async { Ok::<_, &'static str>(6) }.await?;
+        Ok(HeavySetup::build(v))
+    }
+
+    #[tested_fixture(SETUP_7)]
+    #[ignore = "fails"]
+    async fn async_panic_setup() -> HeavySetup {
+        YieldNow(false).await;
+        panic!("failed due to unresolved futures")
+    }
+
+    #[tested_fixture(SETUP_8)]
+    #[ignore = "forced lazily"]
+    async fn async_lazy_setup() -> HeavySetup {
+        YieldNow(false).await;
+        HeavySetup::build(8)
+    }
+
+    #[tested_fixture(SETUP_TIMEOUT, timeout = "1m")]
+    async fn timeout_setup() -> HeavySetup {
+        YieldNow(false).await;
+        HeavySetup::build(9)
+    }
+
+    #[tested_fixture(SETUP_TIMEOUT_FAIL, timeout = "10ms")]
+    #[ignore = "fails"]
+    async fn timeout_fail_setup() -> HeavySetup {
+        std::future::pending::<()>().await;
+        HeavySetup::build(10)
+    }
+
+    #[tested_fixture(SETUP_SYNC_TIMEOUT, timeout = "1m")]
+    fn sync_timeout_setup() -> HeavySetup {
+        HeavySetup::build(11)
+    }
+
+    #[tested_fixture(SETUP_SYNC_TIMEOUT_FAIL, timeout = "10ms")]
+    #[ignore = "fails"]
+    fn sync_timeout_fail_setup() -> HeavySetup {
+        std::thread::sleep(std::time::Duration::from_secs(60));
+        HeavySetup::build(12)
+    }
+
+    #[tested_fixture(SETUP_SYNC_TIMEOUT_PANIC, timeout = "1m")]
+    #[ignore = "fails"]
+    fn sync_timeout_panic_setup() -> HeavySetup {
+        panic!("failed due to {} timeouts", 0)
+    }
+
+    /// Recurses `depth` times, with a sizeable frame each time
+    fn deep_recursion(depth: u32) -> u32 {
+        let frame = std::hint::black_box([depth as u8; 1024]);
+        match depth {
+            0 => frame[0] as u32,
+            _ => deep_recursion(depth - 1) + 1 + frame[0] as u32 - frame[1] as u32,
+        }
+    }
+
+    #[tested_fixture(SETUP_DEEP_STACK: u32, stack_size = 64 * 1024 * 1024)]
+    fn deep_stack_setup() -> u32 {
+        // Far more than the default test thread's stack
+        deep_recursion(20_000)
+    }
+
+    #[test]
+    fn combine_async_get() {
+        let _ = helpers::block_on(async {
+            HeavySetup::build(SETUP_5.get_async().await.0 + SETUP_8.get_async().await.0)
+        });
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"fixture SETUP_7 (fn async_panic_setup) panicked: failed due to unresolved futures"#
+    )]
+    fn combine_async_get_panic() {
+        let _ = helpers::block_on(async {
+            HeavySetup::build(SETUP_5.get_async().await.0 + SETUP_7.get_async().await.0)
+        });
+    }
+
+    #[cfg(feature = "tokio")]
+    mod tokio_runtime {
+        use super::*;
+        use std::{
+            cell::Cell,
+            rc::Rc,
+            thread::{self, ThreadId},
+            time::Duration,
+        };
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        #[tested_fixture(SETUP_TOKIO, runtime = "tokio")]
+        async fn tokio_setup() -> HeavySetup {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            HeavySetup::build(8)
+        }
+
+        #[tested_fixture(SETUP_TOKIO_LAZY, runtime = "tokio")]
+        #[ignore = "forced lazily"]
+        async fn tokio_lazy_setup() -> HeavySetup {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            HeavySetup::build(9)
+        }
+
+        #[tested_fixture(SETUP_TOKIO_PANIC, runtime = "tokio")]
+        #[ignore = "fails"]
+        async fn tokio_panic_setup() -> HeavySetup {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            panic!("failed due to dropped reactor")
+        }
+
+        #[tested_fixture(
+            SETUP_TOKIO_MULTI,
+            runtime = "tokio",
+            flavor = "multi_thread",
+            worker_threads = 2
+        )]
+        async fn tokio_multi_thread_setup() -> HeavySetup {
+            let metrics = tokio::runtime::Handle::current().metrics();
+            assert_eq!(metrics.num_workers(), 2);
+            // Only supported by the multi-threaded runtime
+            tokio::task::block_in_place(|| HeavySetup::build(14))
+        }
+
+        #[tokio::test]
+        async fn combine_tokio_multi_thread_setup() {
+            let _ = HeavySetup::build(SETUP_TOKIO.0 + SETUP_TOKIO_MULTI.0);
+        }
+
+        #[tested_fixture(CHAIN_1, runtime = "tokio", flavor = "multi_thread")]
+        #[ignore = "forced lazily"]
+        async fn chain_1() -> (HeavySetup, ThreadId) {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            (HeavySetup::build(1), thread::current().id())
+        }
+
+        #[tested_fixture(CHAIN_2, runtime = "tokio", flavor = "multi_thread")]
+        #[ignore = "forced lazily"]
+        async fn chain_2() -> (HeavySetup, ThreadId) {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            let setup = HeavySetup::build(CHAIN_1.0 .0 + 1);
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            (setup, thread::current().id())
+        }
+
+        #[tested_fixture(CHAIN_3, runtime = "tokio")]
+        #[ignore = "forced lazily"]
+        async fn chain_3() -> (HeavySetup, ThreadId) {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            let setup = HeavySetup::build(CHAIN_2.0 .0 + 1);
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            (setup, thread::current().id())
+        }
+
+        #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+        async fn nested_chain() {
+            assert_eq!(CHAIN_3.0 .0, 3);
+            // Every fixture in the chain reused this test's runtime
+            let id = thread::current().id();
+            assert_eq!([CHAIN_1.1, CHAIN_2.1, CHAIN_3.1], [id; 3]);
+        }
+
+        static SHARED_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+        #[tested_fixture(SETUP_TOKIO_SHARED, runtime = "tokio")]
+        #[ignore = "forced lazily"]
+        async fn tokio_shared_setup() -> HeavySetup {
+            SHARED_RUNS.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            HeavySetup::build(13)
+        }
+
+        #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+        async fn combine_tokio_get_async() {
+            let (a, b) = tokio::join!(
+                SETUP_TOKIO_SHARED.get_async(),
+                tokio::spawn(SETUP_TOKIO_SHARED.get_async()),
+            );
+            let _ = HeavySetup::build(a.0 + b.unwrap().0);
+            assert_eq!(SHARED_RUNS.load(Ordering::SeqCst), 1);
+        }
+
+        #[tested_fixture(SETUP_TOKIO_LOCAL, runtime = "tokio-local")]
+        async fn tokio_local_setup() -> HeavySetup {
+            let shared = Rc::new(Cell::new(16));
+            let task = tokio::task::spawn_local({
+                let shared = shared.clone();
+                async move { shared.get() }
+            });
+            tokio::task::yield_now().await;
+            HeavySetup::build(task.await.unwrap())
+        }
+
+        #[tokio::test]
+        async fn combine_tokio_local_setup() {
+            let _ = HeavySetup::build(SETUP_TOKIO.0 + SETUP_TOKIO_LOCAL.0);
+        }
+
+        #[tested_fixture(RUNTIME: RuntimeFixture)]
+        fn runtime() -> std::io::Result<RuntimeFixture> {
+            RuntimeFixture::new()
+        }
+
+        #[tested_fixture(SERVER)]
+        fn server() -> std::net::SocketAddr {
+            let listener = RUNTIME.block_on(tokio::net::TcpListener::bind("127.0.0.1:0"));
+            let listener = listener.expect("failed to bind");
+            let addr = listener.local_addr().unwrap();
+            RUNTIME.handle().spawn(async move {
+                loop {
+                    let (mut stream, _) = listener.accept().await.unwrap();
+                    stream.write_all(b"hello").await.unwrap();
+                }
+            });
+            addr
+        }
+
+        #[tokio::test]
+        async fn server_responds() {
+            let mut stream = tokio::net::TcpStream::connect(*SERVER).await.unwrap();
+            let mut buf = String::new();
+            stream.read_to_string(&mut buf).await.unwrap();
+            assert_eq!(buf, "hello");
+        }
+
+        #[tokio::test]
+        async fn combine_tokio_setup() {
+            let _ = HeavySetup::build(SETUP_TOKIO.0 + SETUP_TOKIO_LAZY.0);
+        }
+
+        #[tokio::test]
+        #[should_panic(
+            expected = r#"fixture SETUP_TOKIO_PANIC (fn tokio_panic_setup) panicked: failed due to dropped reactor"#
+        )]
+        async fn combine_tokio_panic() {
+            let _ = HeavySetup::build(SETUP_TOKIO.0 + SETUP_TOKIO_PANIC.0);
+        }
+    }
+
+    static CUSTOM_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+    fn custom_block_on<F: Future>(future: F) -> F::Output {
+        CUSTOM_RUNS.fetch_add(1, Ordering::SeqCst);
+        helpers::block_on(future)
+    }
+
+    #[tested_fixture(SETUP_CUSTOM, block_on = custom_block_on)]
+    async fn custom_setup() -> HeavySetup {
+        YieldNow(false).await;
+        HeavySetup::build(15)
+    }
+
+    #[test]
+    fn combine_custom_setup() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_CUSTOM.0);
+        assert_eq!(CUSTOM_RUNS.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(feature = "smol")]
+    mod smol_runtime {
+        use super::*;
+        use std::time::Duration;
+
+        #[tested_fixture(SETUP_SMOL)]
+        async fn smol_setup() -> HeavySetup {
+            smol::Timer::after(Duration::from_millis(1)).await;
+            HeavySetup::build(12)
+        }
+
+        #[test]
+        fn combine_smol_setup() {
+            let _ = HeavySetup::build(SETUP_1.0 + SETUP_SMOL.0);
+        }
+    }
+
+    #[cfg(feature = "eager")]
+    mod eager {
+        use super::*;
+        use std::time::{Duration, Instant};
+
+        static EAGER_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+        #[tested_fixture(SETUP_EAGER, eager)]
+        fn eager_setup() -> HeavySetup {
+            EAGER_RUNS.fetch_add(1, Ordering::SeqCst);
+            HeavySetup::build(13)
+        }
+
+        #[tested_fixture(SETUP_EAGER_FAIL: HeavySetup, eager)]
+        #[ignore = "fails"]
+        fn eager_fail_setup() -> Result<HeavySetup, &'static str> {
+            Err("failed due to premature optimization")
+        }
+
+        #[test]
+        fn eager_setup_starts_without_dependents() {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while EAGER_RUNS.load(Ordering::SeqCst) == 0 {
+                assert!(Instant::now() < deadline, "eager fixture wasn't started");
+                std::thread::yield_now();
+            }
+            assert_eq!(SETUP_EAGER.0, 13);
+            assert_eq!(EAGER_RUNS.load(Ordering::SeqCst), 1);
+        }
+
+        #[test]
+        #[should_panic(
+            expected = r#"fixture SETUP_EAGER_FAIL (fn eager_fail_setup) returned an error: "failed due to premature optimization""#
+        )]
+        fn combine_eager_fail() {
+            let _ = HeavySetup::build(SETUP_1.0 + SETUP_EAGER_FAIL.0);
+        }
+    }
+
+    /// Aggregates fixtures declared across other modules
+    mod test_fixtures {
+        pub(crate) use super::exported::*;
+        pub(crate) use super::misexported::shadowing::*;
+    }
+
+    mod exported {
+        use super::*;
+
+        #[tested_fixture(pub(crate) SETUP_EXPORTED, export_to = super::test_fixtures)]
+        fn exported_setup() -> HeavySetup {
+            HeavySetup::build(24)
+        }
+
+        #[tested_fixture(
+            (pub(crate) SETUP_EXPORTED_LEFT, pub(crate) SETUP_EXPORTED_RIGHT),
+            export_to = crate::tests::test_fixtures,
+        )]
+        fn exported_tuple_setup() -> (HeavySetup, u32) {
+            (HeavySetup::build(25), 26)
+        }
+    }
+
+    mod misexported {
+        use super::*;
+
+        #[tested_fixture(pub(crate) SETUP_MISEXPORTED, export_to = super::test_fixtures)]
+        #[ignore = "run by misexported_fixture"]
+        pub(crate) fn misexported_setup() -> HeavySetup {
+            HeavySetup::build(27)
+        }
+
+        /// Re-exported in place of the fixture above
+        pub(crate) mod shadowing {
+            use super::*;
+
+            #[tested_fixture(pub(crate) SETUP_MISEXPORTED)]
+            fn shadowing_setup() -> HeavySetup {
+                HeavySetup::build(28)
+            }
+        }
+    }
+
+    #[test]
+    fn exported_fixtures() {
+        use test_fixtures::*;
+
+        assert_eq!(SETUP_EXPORTED.0, 24);
+        assert_eq!(SETUP_EXPORTED_LEFT.0 + *SETUP_EXPORTED_RIGHT, 51);
+    }
+
+    #[test]
+    fn misexported_fixture() {
+        let err = std::panic::catch_unwind(misexported::misexported_setup)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.downcast_ref::<String>().unwrap(),
+            "`super::test_fixtures::SETUP_MISEXPORTED` is not a re-export of this fixture"
+        );
+    }
+
+    #[deny(warnings)]
+    mod lowercase {
+        use super::*;
+
+        #[tested_fixture(step_1_fixture)]
+        fn step_1() -> HeavySetup {
+            HeavySetup::build(15)
+        }
+
+        #[tested_fixture(step_2_left, STEP_2_RIGHT: u32)]
+        fn step_2() -> (HeavySetup, u32) {
+            (HeavySetup::build(step_1_fixture.0 + 1), 17)
+        }
+
+        #[test]
+        fn lowercase_fixtures() {
+            assert_eq!(step_2_left.0 + *STEP_2_RIGHT, 33);
+        }
+    }
+
+    mod shadowed_names {
+        use super::*;
+
+        /// Named like `std::cell::Cell`, but `Sync`
+        #[derive(Debug, PartialEq)]
+        pub struct Cell(pub u8);
+
+        #[tested_fixture(BOARD)]
+        fn board() -> Vec<Cell> {
+            vec![Cell(1), Cell(2)]
+        }
+
+        #[test]
+        fn sync_type_named_like_cell() {
+            assert_eq!(*BOARD, [Cell(1), Cell(2)]);
+        }
+    }
+
+    /// Generated code can't rely on the prelude, or on `std` naming the
+    /// standard library
+    #[deny(warnings)]
+    mod no_prelude {
+        #![no_implicit_prelude]
+
+        use crate::tested_fixture;
+
+        #[allow(dead_code)]
+        mod std {}
+
+        #[tested_fixture(NO_PRELUDE_STEP_1)]
+        fn step_1() -> ::std::result::Result<u32, &'static str> {
+            ::std::result::Result::Ok(40)
+        }
+
+        #[tested_fixture((NO_PRELUDE_LEFT, NO_PRELUDE_RIGHT))]
+        fn step_2() -> (::std::string::String, u32) {
+            (
+                ::std::string::ToString::to_string("left"),
+                *NO_PRELUDE_STEP_1 + 1,
+            )
+        }
+
+        #[tested_fixture(
+            NO_PRELUDE_CHECKED,
+            requires_env = "PATH",
+            alternative(cfg(any()), || 0),
+        )]
+        fn checked_setup() -> u32 {
+            42
+        }
+
+        #[test]
+        fn no_prelude_fixtures() {
+            ::std::assert_eq!(*NO_PRELUDE_LEFT, "left");
+            ::std::assert_eq!(*NO_PRELUDE_RIGHT, 41);
+            ::std::assert_eq!(*NO_PRELUDE_CHECKED, 42);
+        }
+    }
+
+    /// Declares a prelude-free module of fixtures, like the test suites
+    /// generated by declarative macros
+    macro_rules! fixture_module {
+        ($module:ident, $fixture:ident, $value:expr) => {
+            #[deny(warnings)]
+            mod $module {
+                #![no_implicit_prelude]
+
+                use crate::tested_fixture;
+
+                #[tested_fixture($fixture)]
+                fn setup() -> ::std::result::Result<u32, &'static str> {
+                    ::std::result::Result::Ok($value)
+                }
+
+                #[test]
+                fn generated_fixture() {
+                    ::std::assert_eq!(*$fixture, $value);
+                }
+            }
+        };
+    }
+
+    fixture_module!(generated_1, GENERATED_1, 1);
+    fixture_module!(generated_2, GENERATED_2, 2);
+
+    #[cfg(feature = "registry")]
+    mod registry {
+        use super::*;
+
+        #[tested_fixture(SETUP_REGISTERED)]
+        fn registered_setup() -> HeavySetup {
+            HeavySetup::build(SETUP_1.0 + 1)
+        }
+
+        #[tested_fixture(SETUP_REGISTERED_FAIL: HeavySetup)]
+        #[ignore = "fails"]
+        fn registered_fail_setup() -> Result<HeavySetup, &'static str> {
+            Err("failed due to bureaucracy")
+        }
+
+        #[test]
+        fn registry_lists_fixtures() {
+            let fixtures: Vec<_> = crate::registry()
+                .into_iter()
+                .filter(|fixture| fixture.module_path() == module_path!())
+                .collect();
+            let names: Vec<_> = fixtures.iter().map(|fixture| fixture.name()).collect();
+            assert_eq!(names, ["SETUP_REGISTERED", "SETUP_REGISTERED_FAIL"]);
+            assert!(fixtures[0].force().is_ok());
+            assert_eq!(SETUP_REGISTERED.0, 2);
+            let err = fixtures[1].force().unwrap_err();
+            assert_eq!(err.fixture_name(), "SETUP_REGISTERED_FAIL");
+            assert_eq!(err.message(), r#""failed due to bureaucracy""#);
+        }
+
+        mod by_type {
+            use super::*;
+
+            struct Keyed(u32);
+
+            #[tested_fixture(SETUP_KEYED)]
+            #[ignore = "run by get_fixture_by_type"]
+            fn keyed_setup() -> Keyed {
+                Keyed(SETUP_1.0 + 2)
+            }
+
+            #[test]
+            fn get_fixture_by_type() {
+                let keyed = crate::get::<Keyed>().unwrap();
+                assert!(std::ptr::eq(keyed, &*SETUP_KEYED));
+                assert_eq!(keyed.0, 3);
+
+                struct Unregistered;
+                assert!(crate::get::<Unregistered>().is_none());
+
+                let panic =
+                    std::panic::catch_unwind(|| crate::get::<HeavySetup>().is_some()).unwrap_err();
+                let message = panic.downcast_ref::<String>().unwrap();
+                assert!(message.ends_with(
+                    "both have type tested_fixture::tests::HeavySetup, so can't be looked up by type"
+                ));
+            }
+        }
+
+        mod parallel {
+            use super::*;
+            use std::time::{Duration, Instant};
+
+            static STARTED: AtomicUsize = AtomicUsize::new(0);
+            static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+            /// Waits for both fixtures to start, returning whether they did
+            fn overlap() -> bool {
+                RUNS.fetch_add(1, Ordering::SeqCst);
+                STARTED.fetch_add(1, Ordering::SeqCst);
+                let deadline = Instant::now() + Duration::from_secs(10);
+                while STARTED.load(Ordering::SeqCst) < 2 {
+                    if Instant::now() > deadline {
+                        return false;
+                    }
+                    std::thread::yield_now();
+                }
+                true
+            }
+
+            #[tested_fixture(SETUP_PARALLEL_1)]
+            #[ignore = "run by force_parallel_overlaps"]
+            fn parallel_1_setup() -> bool {
+                overlap()
+            }
+
+            #[tested_fixture(SETUP_PARALLEL_2)]
+            #[ignore = "run by force_parallel_overlaps"]
+            fn parallel_2_setup() -> bool {
+                overlap()
+            }
+
+            #[tested_fixture(SETUP_PARALLEL_FAIL: HeavySetup)]
+            #[ignore = "fails"]
+            fn parallel_fail_setup() -> Result<HeavySetup, &'static str> {
+                Err("failed due to a race")
+            }
+
+            #[test]
+            fn force_parallel_overlaps() {
+                let fixtures: Vec<_> = crate::registry()
+                    .into_iter()
+                    .filter(|fixture| fixture.module_path() == module_path!())
+                    .collect();
+                let errs = crate::force_parallel(&fixtures).unwrap_err();
+                assert_eq!(errs.len(), 1);
+                assert_eq!(errs[0].fixture_name(), "SETUP_PARALLEL_FAIL");
+                assert_eq!(errs[0].message(), r#""failed due to a race""#);
+                assert!(*SETUP_PARALLEL_1 && *SETUP_PARALLEL_2);
+
+                // Already initialized fixtures aren't run again
+                let errs = crate::force_parallel(&fixtures).unwrap_err();
+                assert_eq!(errs.len(), 1);
+                assert_eq!(RUNS.load(Ordering::SeqCst), 2);
+            }
+        }
+
+        #[test]
+        fn registry_skips_thread_local() {
+            let registry = crate::registry();
+            assert!(registry.iter().any(|fixture| fixture.name() == "SETUP_1"));
+            assert!(!registry
+                .iter()
+                .any(|fixture| fixture.name() == "SETUP_LOCAL"));
+        }
+    }
+
+    #[cfg(panic = "abort")]
+    mod panic_abort {
+        use super::*;
+
+        #[tested_fixture(SETUP_ABORT)]
+        fn abort_setup() -> Result<HeavySetup, &'static str> {
+            Ok(HeavySetup::build(2))
+        }
+
+        #[tested_fixture(SETUP_ABORT_ASYNC)]
+        async fn abort_async_setup() -> HeavySetup {
+            YieldNow(false).await;
+            HeavySetup::build(2)
+        }
+
+        #[test]
+        fn combine_abort_setup() {
+            let _ = HeavySetup::build(SETUP_ABORT.0 + SETUP_ABORT_ASYNC.0);
+        }
+    }
+
+    #[cfg(feature = "anyhow")]
+    mod anyhow_errors {
+        use super::*;
+        use anyhow::Context;
+
+        #[tested_fixture(SETUP_ANYHOW: HeavySetup)]
+        fn anyhow_setup() -> anyhow::Result<HeavySetup> {
+            let v = "14".parse().context("parse setup")?;
+            Ok(HeavySetup::build(v))
+        }
+
+        #[tested_fixture(SETUP_ANYHOW_FAIL: HeavySetup)]
+        #[ignore = "fails"]
+        fn anyhow_fail_setup() -> anyhow::Result<HeavySetup> {
+            Err(anyhow::anyhow!("connection refused")).context("connect to db")
+        }
+
+        #[test]
+        fn combine_anyhow_setup() {
+            let _ = HeavySetup::build(SETUP_1.0 + SETUP_ANYHOW.0);
+        }
+
+        #[test]
+        #[should_panic(
+            expected = "fixture SETUP_ANYHOW_FAIL (fn anyhow_fail_setup) returned an error: connect to db — caused by: connection refused"
+        )]
+        fn combine_anyhow_fail() {
+            let _ = HeavySetup::build(SETUP_1.0 + SETUP_ANYHOW_FAIL.0);
+        }
+    }
+
+    #[cfg(feature = "eyre")]
+    mod eyre_errors {
+        use super::*;
+        use eyre::WrapErr;
+
+        #[tested_fixture(SETUP_EYRE: HeavySetup)]
+        fn eyre_setup() -> eyre::Result<HeavySetup> {
+            let v = "15".parse().wrap_err("parse setup")?;
+            Ok(HeavySetup::build(v))
+        }
+
+        #[tested_fixture(SETUP_EYRE_FAIL: HeavySetup)]
+        #[ignore = "fails"]
+        fn eyre_fail_setup() -> eyre::Result<HeavySetup> {
+            Err(eyre::eyre!("disk full")).wrap_err("write snapshot")
+        }
+
+        #[test]
+        fn combine_eyre_setup() {
+            let _ = HeavySetup::build(SETUP_1.0 + SETUP_EYRE.0);
+        }
+
+        #[test]
+        #[should_panic(
+            expected = "fixture SETUP_EYRE_FAIL (fn eyre_fail_setup) returned an error: write snapshot — caused by: disk full"
+        )]
+        fn combine_eyre_fail() {
+            let _ = HeavySetup::build(SETUP_1.0 + SETUP_EYRE_FAIL.0);
+        }
+    }
+
+    #[cfg(feature = "async-std")]
+    mod async_std_runtime {
+        use super::*;
+        use std::time::Duration;
+
+        #[tested_fixture(SETUP_ASYNC_STD, runtime = "async-std")]
+        async fn async_std_setup() -> HeavySetup {
+            async_std::task::sleep(Duration::from_millis(1)).await;
+            HeavySetup::build(10)
+        }
+
+        #[tested_fixture(SETUP_ASYNC_STD_TRY: HeavySetup, runtime = "async-std")]
+        async fn async_std_try_setup() -> Result<HeavySetup, &'static str> {
+            async_std::task::sleep(Duration::from_millis(1)).await;
+            Ok(HeavySetup::build(11))
+        }
+
+        #[tested_fixture(SETUP_ASYNC_STD_FAIL: HeavySetup, runtime = "async-std")]
+        #[ignore = "fails"]
+        async fn async_std_fail_setup() -> Result<HeavySetup, &'static str> {
+            async_std::task::sleep(Duration::from_millis(1)).await;
+            Err("failed due to misaligned channels")
+        }
+
+        #[async_std::test]
+        async fn combine_async_std_setup() {
+            let _ = HeavySetup::build(SETUP_ASYNC_STD.0 + SETUP_ASYNC_STD_TRY.0);
+        }
+
+        #[async_std::test]
+        #[should_panic(
+            expected = r#"fixture SETUP_ASYNC_STD_FAIL (fn async_std_fail_setup) returned an error: "failed due to misaligned channels""#
+        )]
+        async fn combine_async_std_fail() {
+            let _ = HeavySetup::build(SETUP_ASYNC_STD.0 + SETUP_ASYNC_STD_FAIL.0);
+        }
+    }
+
+    #[test]
+    fn combine_setup() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_2.0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"fixture SETUP_3 (fn fail_setup) returned an error: "failed due to reticulated splines""#
+    )]
+    fn combine_fail() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_3.0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"fixture SETUP_4 (fn panic_setup) panicked: failed due to normalized social network"#
+    )]
+    fn combine_panic() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_4.0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "fixture SETUP_ASSERT (fn assert_setup) panicked: expected 1 to be 2"
+    )]
+    fn combine_assert_panic() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_ASSERT.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "fixture SETUP_PAYLOAD (fn payload_setup) panicked: Box<dyn Any>")]
+    fn combine_payload_panic() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_PAYLOAD.0);
+    }
+
+    #[derive(Debug)]
+    struct ConfigError(std::io::Error);
+
+    impl std::fmt::Display for ConfigError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("load config")
+        }
+    }
+
+    impl std::error::Error for ConfigError {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    #[tested_fixture(SETUP_BOXED: HeavySetup)]
+    fn boxed_setup() -> Result<HeavySetup, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(HeavySetup::build("16".parse()?))
+    }
+
+    #[tested_fixture(SETUP_BOXED_FAIL: HeavySetup)]
+    #[ignore = "fails"]
+    fn boxed_fail_setup() -> Result<HeavySetup, Box<dyn std::error::Error + Send + Sync>> {
+        let missing = std::io::Error::new(std::io::ErrorKind::NotFound, "file missing");
+        Err(ConfigError(missing).into())
+    }
+
+    #[tested_fixture(SETUP_UNSYNC_ERROR: HeavySetup)]
+    #[ignore = "fails"]
+    fn unsync_error_setup() -> Result<HeavySetup, Box<dyn std::error::Error>> {
+        let missing = std::io::Error::new(std::io::ErrorKind::NotFound, "file missing");
+        Err(ConfigError(missing).into())
+    }
+
+    #[test]
+    fn combine_boxed_setup() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_BOXED.0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "fixture SETUP_BOXED_FAIL (fn boxed_fail_setup) returned an error: load config — caused by: file missing"
+    )]
+    fn combine_boxed_fail() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_BOXED_FAIL.0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "fixture SETUP_UNSYNC_ERROR (fn unsync_error_setup) returned an error: load config — caused by: file missing"
+    )]
+    fn combine_unsync_error() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_UNSYNC_ERROR.0);
+    }
+
+    type Deep<T> = Result<
+        Result<
+            Result<Result<Result<Result<T, &'static str>, Infallible>, String>, Infallible>,
+            &'static str,
+        >,
+        std::io::Error,
+    >;
+
+    #[tested_fixture(SETUP_DEEP: HeavySetup)]
+    fn deep_setup() -> Deep<HeavySetup> {
+        Ok(Ok(Ok(Ok(Ok(Ok(HeavySetup::build(18)))))))
+    }
+
+    #[tested_fixture(SETUP_DEEP_FAIL: HeavySetup)]
+    #[ignore = "fails"]
+    fn deep_fail_setup() -> Deep<HeavySetup> {
+        Ok(Ok(Ok(Ok(Ok(Err("failed six layers down"))))))
+    }
+
+    #[tested_fixture(SETUP_PARTIAL: Result<HeavySetup, &'static str>)]
+    fn partial_setup() -> Result<Result<Result<HeavySetup, &'static str>, Infallible>, String> {
+        Ok(Ok(Err("kept as part of the fixture")))
+    }
+
+    static TUPLE_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+    #[tested_fixture((SETUP_LEFT, pub(crate) SETUP_RIGHT))]
+    fn tuple_setup() -> Result<(HeavySetup, String), &'static str> {
+        TUPLE_RUNS.fetch_add(1, Ordering::SeqCst);
+        Ok((HeavySetup::build(20), "right".to_owned()))
+    }
+
+    #[tested_fixture((SETUP_ASYNC_LEFT: HeavySetup, SETUP_ASYNC_RIGHT: u32,))]
+    async fn async_tuple_setup() -> (HeavySetup, u32) {
+        YieldNow(false).await;
+        (HeavySetup::build(21), 22)
+    }
+
+    #[tested_fixture(SETUP_BARE_LEFT, pub(crate) SETUP_BARE_RIGHT: Vec<u32>, no_catch_unwind)]
+    fn bare_tuple_setup() -> Result<(HeavySetup, Vec<u32>), &'static str> {
+        Ok((HeavySetup::build(23), vec![24, 25]))
+    }
+
+    #[tested_fixture((SETUP_FAIL_LEFT: HeavySetup, SETUP_FAIL_RIGHT: HeavySetup))]
+    #[ignore = "fails"]
+    fn tuple_fail_setup() -> Result<(HeavySetup, HeavySetup), &'static str> {
+        Err("failed due to split personalities")
+    }
+
+    #[test]
+    fn combine_tuple_setup() {
+        let _ = HeavySetup::build(SETUP_LEFT.0 + SETUP_RIGHT.len() as u32);
+        let _ = HeavySetup::build(SETUP_ASYNC_LEFT.0 + *SETUP_ASYNC_RIGHT);
+        let _ = HeavySetup::build(SETUP_BARE_LEFT.0 + SETUP_BARE_RIGHT.len() as u32);
+        assert_eq!(TUPLE_RUNS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"fixture SETUP_FAIL_LEFT (fn tuple_fail_setup) returned an error: "failed due to split personalities""#
+    )]
+    fn combine_tuple_fail_left() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_FAIL_LEFT.0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"fixture SETUP_FAIL_RIGHT (fn tuple_fail_setup) returned an error: "failed due to split personalities""#
+    )]
+    fn combine_tuple_fail_right() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_FAIL_RIGHT.0);
+    }
+
+    #[test]
+    fn repeated_failure_is_reported_again() {
+        for _ in 0..2 {
+            let err = std::panic::catch_unwind(|| SETUP_4.0).unwrap_err();
+            let message = err.downcast_ref::<String>().unwrap();
+            let expected =
+                "fixture SETUP_4 (fn panic_setup) panicked: failed due to normalized social network at src/lib.rs:";
+            assert!(message.starts_with(expected), "{}", message);
+        }
+    }
+
+    #[tested_fixture(STEP_FAIL_1: HeavySetup)]
+    #[ignore = "fails"]
+    fn step_fail_1() -> Result<HeavySetup, &'static str> {
+        Err("failed due to a missing first step")
+    }
+
+    #[tested_fixture(STEP_FAIL_2: HeavySetup)]
+    #[ignore = "fails"]
+    fn step_fail_2() -> HeavySetup {
+        HeavySetup::build(STEP_FAIL_1.0 + 1)
+    }
+
+    #[tested_fixture(STEP_FAIL_3: HeavySetup)]
+    #[ignore = "fails"]
+    fn step_fail_3() -> HeavySetup {
+        HeavySetup::build(STEP_FAIL_2.0 + 1)
+    }
+
+    #[test]
+    fn transitive_failure_reports_chain() {
+        let err = std::panic::catch_unwind(|| STEP_FAIL_3.0).unwrap_err();
+        let message = err.downcast_ref::<String>().unwrap();
+        let expected = concat!(
+            "fixture STEP_FAIL_1 (fn step_fail_1) returned an error ",
+            "(while initializing STEP_FAIL_2 for STEP_FAIL_3, ",
+            "required by tests::transitive_failure_reports_chain): ",
+            r#""failed due to a missing first step""#,
+        );
+        assert!(message.contains(expected), "{}", message);
+        assert!(message.starts_with("fixture STEP_FAIL_3 (fn step_fail_3) panicked: "));
+    }
+
+    #[test]
+    fn panicking_init_leaves_cell_uninitialized() {
+        let cell = helpers::OnceCell::<u32>::new();
+        std::panic::catch_unwind(|| cell.get_or_init(|| panic!("failed to initialize")))
+            .unwrap_err();
+        assert_eq!(cell.get(), None);
+        assert_eq!(*cell.get_or_init(|| 1), 1);
+    }
+
+    #[cfg(feature = "std-sync")]
+    #[test]
+    fn std_sync_uses_once_lock() {
+        static CELL: std::sync::OnceLock<u32> = helpers::OnceCell::new();
+        assert_eq!(*CELL.get_or_init(|| 2), 2);
+        assert_eq!(SETUP_1.0, 1);
+    }
+
+    #[test]
+    fn try_get_reports_failures() {
+        assert_eq!(SETUP_1.try_get().unwrap().0, 1);
+        assert_eq!(SETUP_5.try_get().unwrap().0, 5);
+
+        let err = SETUP_3.try_get().err().unwrap();
+        assert_eq!(err.fixture_name(), "SETUP_3");
+        assert_eq!(err.test_name(), "tested_fixture::tests::fail_setup");
+        assert!(!err.panicked());
+        assert_eq!(err.message(), r#""failed due to reticulated splines""#);
+        assert_eq!(
+            err.to_string(),
+            r#"fixture SETUP_3 (fn fail_setup) returned an error: "failed due to reticulated splines""#
+        );
+
+        let err = SETUP_7.try_get().err().unwrap();
+        assert_eq!(err.test_name(), "tested_fixture::tests::async_panic_setup");
+        assert!(err.panicked());
+        assert!(err
+            .message()
+            .starts_with("failed due to unresolved futures at src/lib.rs:"));
+
+        let err = SETUP_FAIL_RIGHT.try_get().err().unwrap();
+        assert_eq!(err.fixture_name(), "SETUP_FAIL_RIGHT");
+        assert_eq!(err.test_name(), "tested_fixture::tests::tuple_fail_setup");
+        assert_eq!(err.message(), r#""failed due to split personalities""#);
+    }
+
+    #[test]
+    fn concurrent_panics_are_caught_separately() {
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                std::thread::spawn(move || {
+                    let failure = helpers::catch_panic(|| {
+                        std::thread::yield_now();
+                        panic!("failed in thread {}", i)
+                    })
+                    .unwrap_err();
+                    (i, format!("{:?}", failure))
+                })
+            })
+            .collect();
+        for thread in threads {
+            let (i, message) = thread.join().unwrap();
+            let expected = format!("failed in thread {} at src/lib.rs:", i);
+            assert!(message.starts_with(&expected), "{}", message);
+        }
+    }
+
+    static MIGRATIONS: AtomicUsize = AtomicUsize::new(0);
+
+    #[tested_fixture(MIGRATED)]
+    fn migrate() {
+        MIGRATIONS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[tested_fixture(MIGRATED_ASYNC)]
+    async fn migrate_async() -> Result<(), &'static str> {
+        YieldNow(false).await;
+        Ok(())
+    }
+
+    #[tested_fixture(MIGRATED_FAIL)]
+    #[ignore = "fails"]
+    fn migrate_fail() -> Result<(), &'static str> {
+        Err("failed due to conflicting migrations")
+    }
+
+    #[test]
+    fn gated_on_migration_1() {
+        MIGRATED.ensure();
+        assert_eq!(MIGRATIONS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn gated_on_migration_2() {
+        *MIGRATED;
+        assert_eq!(MIGRATIONS.load(Ordering::SeqCst), 1);
+        assert_eq!(std::mem::size_of_val(&MIGRATED), 0);
+    }
+
+    #[test]
+    fn gated_on_migration_3() {
+        helpers::block_on(async {
+            MIGRATED.ensure();
+            MIGRATED_ASYNC.ensure_async().await;
+        });
+        MIGRATED_ASYNC.ensure();
+        assert_eq!(MIGRATIONS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"fixture MIGRATED_FAIL (fn migrate_fail) returned an error: "failed due to conflicting migrations""#
+    )]
+    fn gated_on_migration_fail() {
+        MIGRATED_FAIL.ensure();
+    }
+
+    #[tested_fixture(SETUP_GREETER)]
+    fn greeter_setup() -> impl Fn(&str) -> String {
+        let greeting = "hello";
+        move |name| format!("{}, {}", greeting, name)
+    }
+
+    #[tested_fixture(SETUP_DISPLAY_IMPL)]
+    fn display_impl_setup() -> Result<impl std::fmt::Display + Send, &'static str> {
+        Ok(HeavySetup::build(23).0)
+    }
+
+    #[tested_fixture(SETUP_DEBUG_IMPL)]
+    async fn debug_impl_setup() -> impl Debug {
+        YieldNow(false).await;
+        Some(24)
+    }
+
+    #[test]
+    fn combine_impl_setup() {
+        let greeter: &'static (dyn Fn(&str) -> String + Send + Sync) = &**SETUP_GREETER;
+        assert_eq!(greeter("world"), "hello, world");
+        assert_eq!(SETUP_DISPLAY_IMPL.to_string(), "23");
+        assert_eq!(format!("{:?}", *SETUP_DEBUG_IMPL), "Some(24)");
+    }
+
+    trait Service: Send + Sync {
+        fn name(&self) -> &str;
+    }
+
+    struct EchoService;
+
+    impl Service for EchoService {
+        fn name(&self) -> &str {
+            "echo"
+        }
+    }
+
+    #[tested_fixture(SETUP_STR: str)]
+    fn str_setup() -> String {
+        HeavySetup::build(25).0.to_string()
+    }
+
+    #[tested_fixture(SETUP_BYTES: [u8])]
+    async fn bytes_setup() -> Result<Vec<u8>, Infallible> {
+        YieldNow(false).await;
+        Ok(vec![2, 6])
+    }
+
+    #[tested_fixture(SETUP_SERVICE: dyn Service)]
+    fn service_setup() -> Box<dyn Service> {
+        Box::new(EchoService)
+    }
+
+    #[tested_fixture(SETUP_LEAKED)]
+    fn leaked_setup() -> &'static str {
+        Box::leak(HeavySetup::build(26).0.to_string().into_boxed_str())
+    }
+
+    #[tested_fixture(SETUP_COW)]
+    async fn cow_setup() -> Result<std::borrow::Cow<'static, str>, &'static str> {
+        YieldNow(false).await;
+        Ok("27".into())
+    }
+
+    fn parse_str(s: &str) -> u32 {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn combine_static_setup() {
+        let leaked: &'static str = &SETUP_LEAKED;
+        assert_eq!(parse_str(&SETUP_LEAKED) + parse_str(&SETUP_COW), 53);
+        assert_eq!(leaked.len(), 2);
+    }
+
+    #[test]
+    fn combine_unsized_setup() {
+        let s: &'static str = &SETUP_STR;
+        assert_eq!(s, "25");
+        let bytes: &'static [u8] = &SETUP_BYTES;
+        assert_eq!(bytes, [2, 6]);
+        assert_eq!(SETUP_SERVICE.name(), "echo");
+    }
+
+    #[tested_fixture(SETUP_FULL, full)]
+    fn full_setup() -> Result<u32, std::num::ParseIntError> {
+        "not a number".parse()
+    }
+
+    #[tested_fixture(SETUP_FULL_TYPED: Result<u32, std::num::ParseIntError>, full, pass_if = Result::is_err)]
+    fn full_typed_setup() -> Result<u32, std::num::ParseIntError> {
+        "not a number either".parse()
+    }
+
+    #[tested_fixture(SETUP_FULL_REJECTED, full, pass_if = |v| v.is_ok())]
+    #[ignore = "fails"]
+    fn full_rejected_setup() -> Result<u32, std::num::ParseIntError> {
+        "still not a number".parse()
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum ConnectError {
+        Refused { attempts: u32 },
+    }
+
+    #[tested_fixture(SETUP_ERR, capture = "err")]
+    fn err_setup() -> Result<HeavySetup, ConnectError> {
+        Err(ConnectError::Refused { attempts: 3 })
+    }
+
+    #[tested_fixture(SETUP_ERR_TYPED: std::num::ParseIntError, capture = "err")]
+    fn err_typed_setup() -> Result<u32, std::num::ParseIntError> {
+        "not a number".parse()
+    }
+
+    #[tested_fixture(SETUP_ERR_OK, capture = "err")]
+    #[ignore = "fails"]
+    fn err_ok_setup() -> Result<HeavySetup, ConnectError> {
+        Ok(HeavySetup::build(19))
+    }
+
+    #[test]
+    fn combine_err_setup() {
+        let err: &'static ConnectError = &SETUP_ERR;
+        assert_eq!(err, &ConnectError::Refused { attempts: 3 });
+        assert_eq!(
+            SETUP_ERR_TYPED.kind(),
+            &std::num::IntErrorKind::InvalidDigit
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "fixture SETUP_ERR_OK (fn err_ok_setup) failed: unexpectedly returned Ok"
+    )]
+    fn combine_err_ok() {
+        let _ = &*SETUP_ERR_OK;
+    }
+
+    #[test]
+    fn combine_full_setup() {
+        assert!(SETUP_FULL.is_err());
+        assert!(SETUP_FULL_TYPED.is_err());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "fixture SETUP_FULL_REJECTED (fn full_rejected_setup) failed: rejected by `pass_if`"
+    )]
+    fn combine_full_rejected() {
+        assert!(SETUP_FULL_REJECTED.is_err());
+    }
+
+    #[test]
+    fn combine_untyped_setup() {
+        let aliased: &'static SetupResult = &SETUP_ALIASED;
+        let _ =
+            HeavySetup::build(SETUP_UNTYPED.0 + SETUP_QUALIFIED.0 + aliased.as_ref().unwrap().0);
+        let _ = HeavySetup::build(SETUP_ANNOTATED.0 + SETUP_ANNOTATED_ASYNC.0);
+    }
+
+    #[test]
+    fn combine_refcell_setup() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_REFCELL.0);
+    }
+
+    #[test]
+    fn combine_mut_setup() {
+        SETUP_MUT.lock().unwrap().push(SETUP_1.0);
+        assert!(SETUP_MUT.lock().unwrap().contains(&1));
+
+        let mut setup = SETUP_MUT_ASYNC.lock().unwrap();
+        setup.0 += SETUP_1.0;
+        assert_eq!(setup.0, 3);
+    }
+
+    #[test]
+    fn combine_cloned_setup() {
+        let mut setup = SETUP_CLONED.cloned();
+        setup.push(SETUP_1.0);
+        assert_eq!(setup, [2, 1]);
+        assert_eq!(SETUP_CLONED.cloned(), [2]);
+        assert_eq!(SETUP_CLONED_ASYNC.try_cloned().unwrap(), [2]);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"fixture SETUP_CLONED_FAIL (fn cloned_fail_setup) returned an error: "failed due to a shallow copy""#
+    )]
+    fn combine_cloned_fail() {
+        let _ = SETUP_CLONED_FAIL.cloned();
+    }
+
+    #[test]
+    fn combine_fresh_setup() {
+        let mut first = SETUP_FRESH.get();
+        let second = SETUP_FRESH.get();
+        assert_ne!(first, second);
+        first.push(SETUP_1.0 as usize);
+        assert_eq!(first.len(), 2);
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_FRESH_ASYNC.get().0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"fixture SETUP_FRESH_FAIL (fn fresh_fail_setup) returned an error: "failed due to stale state""#
+    )]
+    fn combine_fresh_fail() {
+        let _ = SETUP_FRESH_FAIL.get();
+    }
+
+    #[test]
+    fn combine_once_setup() {
+        let setup = SETUP_ONCE.take();
+        let _ = HeavySetup::build(SETUP_1.0 + setup.0);
+    }
+
+    #[test]
+    fn once_setup_is_taken_once() {
+        let _ = SETUP_ONCE_TWICE.take();
+        let err = std::panic::catch_unwind(|| SETUP_ONCE_TWICE.take())
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.downcast_ref::<String>().unwrap(),
+            "fixture SETUP_ONCE_TWICE already taken by test tests::once_setup_is_taken_once"
+        );
+    }
+
+    #[test]
+    fn combine_arc_setup() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_ARC.0);
+        let setup = SETUP_ARC.share();
+        let thread = std::thread::spawn(move || setup.0);
+        assert_eq!(thread.join().unwrap(), 2);
+        assert!(std::ptr::eq(&*SETUP_ARC, &*SETUP_ARC.share()));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"fixture SETUP_ARC_FAIL (fn arc_fail_setup) returned an error: "failed due to a dangling reference""#
+    )]
+    fn combine_arc_fail() {
+        let _ = SETUP_ARC_FAIL.share();
+    }
+
+    #[test]
+    fn combine_local_setup() {
+        SETUP_LOCAL.with(|setup| setup.set(setup.get() + SETUP_1.0));
+        assert_eq!(SETUP_LOCAL.with(|setup| setup.get()), 3);
+        let runs = LOCAL_RUNS.load(Ordering::SeqCst);
+        let other = std::thread::spawn(|| SETUP_LOCAL.with(|setup| setup.get()));
+        assert_eq!(other.join().unwrap(), 2);
+        assert!(LOCAL_RUNS.load(Ordering::SeqCst) > runs);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"fixture SETUP_LOCAL_FAIL (fn local_fail_setup) returned an error: "failed due to thread starvation""#
+    )]
+    fn combine_local_fail() {
+        SETUP_LOCAL_FAIL.with(|_| ());
+    }
+
+    #[test]
+    fn introspect_fixture() {
+        assert_eq!(SETUP_TRACKED.0, 33);
+        assert_eq!(SETUP_TRACKED.state(), FixtureState::Ok);
+        assert!(SETUP_TRACKED.init_duration().unwrap() >= std::time::Duration::from_millis(5));
+        assert!(matches!(
+            SETUP_TRACKED.triggered_by(),
+            Some("tests::tracked_setup" | "tests::introspect_fixture")
+        ));
+
+        assert_eq!(SETUP_TRACKED_ASYNC.0, 34);
+        let handle = SETUP_TRACKED_ASYNC.handle();
+        assert_eq!(handle.state(), FixtureState::Ok);
+        assert!(handle.init_duration().unwrap() >= std::time::Duration::from_millis(5));
+
+        // Projected fixtures share the state of the fixture they're projected from
+        assert!(SETUP_FAIL_LEFT.try_get().is_err());
+        assert_eq!(SETUP_FAIL_LEFT.state(), FixtureState::Failed);
+        assert_eq!(SETUP_FAIL_RIGHT.state(), FixtureState::Failed);
+        let _ = SETUP_ARC.share();
+        assert_eq!(SETUP_ARC.handle().state(), FixtureState::Ok);
+    }
+
+    #[test]
+    fn inject_fixture() {
+        SETUP_INJECTED.set(HeavySetup::build(35)).unwrap();
+        let again = SETUP_INJECTED.set(HeavySetup::build(36));
+        assert_eq!(again, Err(AlreadyInitialized));
+        assert_eq!(SETUP_INJECTED.0, 35);
+        assert_eq!(SETUP_INJECTED.state(), FixtureState::Ok);
+        // The producing test passes without running the function
+        assert_eq!(injected_setup().unwrap().0 .0, 35);
+
+        SETUP_INJECTED_REJECTED.set(HeavySetup::build(37)).unwrap();
+        match injected_rejected_setup() {
+            Ok(_) => panic!("injected value should be rejected"),
+            Err(e) => assert_eq!(e.message(), "injected value rejected by `verify_injected`"),
+        }
+
+        assert_eq!(SETUP_1.0, 1);
+        assert_eq!(SETUP_1.set(HeavySetup::build(2)), Err(AlreadyInitialized));
+    }
+
+    #[test]
+    fn unsync_fixture() {
+        assert!(unsync_setup().is_ok());
+        assert_eq!(SETUP_UNSYNC.0, 41);
+        assert_eq!(std::rc::Rc::strong_count(&SETUP_UNSYNC), 1);
+
+        let panic = std::thread::spawn(|| SETUP_UNSYNC.try_get().is_ok())
+            .join()
+            .unwrap_err();
+        let message = panic.downcast_ref::<String>().unwrap();
+        assert!(message.starts_with("fixture SETUP_UNSYNC is `local`"));
+    }
+
+    #[test]
+    fn pinned_fixture() {
+        let pinned: &'static SelfRef = &SETUP_PINNED;
+        assert_eq!(pinned.value, 42);
+        assert!(pinned.is_intact());
+        assert_eq!(SETUP_PINNED_TYPED.value, 43);
+        assert!(SETUP_PINNED_TYPED.is_intact());
+    }
+
+    #[test]
+    fn release_fixture() {
+        assert_eq!(SETUP_RELEASABLE.read().0.len(), 1024);
+        assert_eq!(RELEASED.load(Ordering::SeqCst), 0);
+        SETUP_RELEASABLE.release();
+        assert_eq!(RELEASED.load(Ordering::SeqCst), 1);
+        let panic = std::panic::catch_unwind(|| SETUP_RELEASABLE.read().0.len()).unwrap_err();
+        let message = panic.downcast_ref::<String>().unwrap();
+        assert_eq!(message, "fixture SETUP_RELEASABLE already released");
+
+        // Releasing an uninitialized fixture doesn't initialize it
+        SETUP_RELEASED_UNUSED.release();
+        assert!(std::panic::catch_unwind(|| SETUP_RELEASED_UNUSED.read().0.len()).is_err());
+        assert_eq!(RELEASED.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn retry_fixture() {
+        assert_eq!(SETUP_TRANSIENT.0, 2);
+        assert_eq!(TRANSIENT_RUNS.load(Ordering::SeqCst), 3);
+
+        let err = SETUP_EXHAUSTED.try_get().err().unwrap();
+        assert_eq!(
+            err.message(),
+            r#""failed due to an outage" (after 2 attempts)"#
+        );
+        assert!(!err.panicked());
+        assert_eq!(EXHAUSTED_RUNS.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn derived_fixture_name() {
+        assert_eq!(DERIVED_SETUP.0, 6);
+        assert_eq!(*DERIVED_STEP, 7);
+    }
+
+    #[test]
+    #[cfg(feature = "eager")]
+    fn eager_only_fixture() {
+        assert_eq!(SETUP_EAGER_ONLY.0, 9);
+    }
+
+    #[test]
+    #[cfg(feature = "tokio")]
+    fn tokio_only_fixtures() {
+        assert_eq!(SETUP_TOKIO_LEFT.0 + *SETUP_TOKIO_RIGHT, 21);
+    }
+
+    #[test]
+    fn custom_test_attr() {
+        assert_eq!(SETUP_TEST_ATTR.0, 12);
+    }
+
+    #[test]
+    fn untested_fixture() {
+        assert_eq!(REFERENCE_DATA.iter().sum::<u32>(), 6);
+        assert_eq!(REFERENCE_DATA.len(), 3);
+        assert_eq!(REFERENCE_RUNS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"fixture REFERENCE_MISSING (fn reference_missing) returned an error: "failed due to a missing file""#
+    )]
+    fn untested_fixture_fail() {
+        let _ = REFERENCE_MISSING.len();
+    }
+
+    #[test]
+    fn section_attrs() {
+        // The generated test is ignored, but dependents still force it
+        assert_eq!(SETUP_SECTIONS.0, 13);
+    }
+
+    #[test]
+    fn leftover_test_attr() {
+        assert_eq!(SETUP_LEFTOVER_TEST.0, 16);
+    }
+
+    #[test]
+    fn reexported_crate_path() {
+        assert_eq!(SETUP_FACADE.0, 17);
+    }
+
+    #[test]
+    fn raw_identifiers() {
+        assert_eq!(r#final.0, 18);
+        assert_eq!(r#static.0 + *r#MATCH, 39);
+        assert_eq!(r#async.get().0, 21);
+        assert_eq!(r#dyn.0 + *r#impl, 45);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"fixture ref (fn match) returned an error: "failed due to a reserved word""#
+    )]
+    fn raw_identifiers_fail() {
+        let _ = r#ref.0;
+    }
+
+    #[test]
+    fn exposed_fixture() {
+        assert_eq!(SETUP_EXPOSED.0, 27);
+    }
+
+    #[test]
+    fn companion_fn() {
+        let cached = SETUP_COMPANION.0;
+        let first = run_companion_setup().unwrap();
+        let second = run_companion_setup().unwrap();
+        assert_ne!(first.0, second.0);
+        assert_eq!(SETUP_COMPANION.0, cached);
+        assert_eq!(COMPANION_RUNS.load(Ordering::SeqCst), 3);
+
+        let value = crate::helpers::block_on(run_async_companion_setup());
+        assert_eq!(value.0, SETUP_ASYNC_COMPANION.0);
+    }
+
+    #[test]
+    fn placeholder_type() {
+        assert_eq!(SETUP_PLACEHOLDER[&1], ["one"]);
+    }
+
+    #[test]
+    fn complex_types() {
+        assert_eq!(*SETUP_PROJECTED_TYPE, Ok(29));
+        assert_eq!(SETUP_LIFETIMES.concat(), "borrowedowned");
+        assert_eq!(SETUP_TURBOFISH.0, 30);
+    }
+
+    #[test]
+    fn renamed_test() {
+        assert_eq!(SETUP_RENAMED.0, 13);
+        // The function keeps its name, running the body uncached
+        assert_eq!(renamed_setup().0, 13);
+        assert_eq!(SETUP_RENAMED_LOCAL.with(|cell| cell.get()), 14);
+        assert_eq!(renamed_local_setup().get(), 14);
+    }
+
+    #[test]
+    fn ordered_test() {
+        assert_eq!(SETUP_ORDERED.0, 15);
+        assert_eq!(ordered_setup().0, 15);
+        assert!(_000_ordered_setup().is_ok());
+    }
+
+    #[test]
+    fn strict_fixture() {
+        let err = std::panic::catch_unwind(|| SETUP_STRICT.0).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<String>().unwrap(),
+            "SETUP_STRICT was not initialized by its own test; run order/selection problem"
+        );
+        assert_eq!(SETUP_STRICT.state(), FixtureState::Uninitialized);
+        assert!(strict_setup().is_ok());
+        assert_eq!(SETUP_STRICT.0, 16);
+    }
+
+    #[test]
+    fn strict_env_fixture() {
+        // Run in a separate process, since the environment variable applies
+        // to every fixture
+        let run = |strict| {
+            let mut command = std::process::Command::new(std::env::current_exe().unwrap());
+            command.args(["--ignored", "--exact", "tests::strict_env_dependent"]);
+            match strict {
+                true => command.env("TESTED_FIXTURE_STRICT", "1"),
+                false => command.env_remove("TESTED_FIXTURE_STRICT"),
+            };
+            command.output().unwrap()
+        };
+        let output = run(true);
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).contains(
+            "SETUP_STRICT_ENV was not initialized by its own test; run order/selection problem"
+        ));
+        assert!(run(false).status.success());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn invariant_fixture() {
+        assert!(invariant_setup().is_ok());
+        // A buggy test mutating the fixture through a borrow it kept
+        let value: &'static AtomicUsize = &SETUP_INVARIANT;
+        value.store(10, Ordering::SeqCst);
+        for _ in 0..2 {
+            let err = std::panic::catch_unwind(|| SETUP_INVARIANT.load(Ordering::SeqCst));
+            assert_eq!(
+                err.unwrap_err().downcast_ref::<String>().unwrap(),
+                "SETUP_INVARIANT invariant violated (first detected in test tests::invariant_fixture)"
+            );
+        }
+        assert!(std::panic::catch_unwind(invariant_setup).is_err());
+        value.store(2, Ordering::SeqCst);
+        assert!(invariant_setup().is_ok());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn broken_invariant() {
+        let err = SETUP_BROKEN_INVARIANT.try_get().err().unwrap();
+        assert!(err.to_string().starts_with(
+            "fixture SETUP_BROKEN_INVARIANT (fn broken_invariant_setup) failed: SETUP_BROKEN_INVARIANT invariant violated (first detected in test tests::"
+        ));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn unchanged_fixture() {
+        assert!(sneaky_setup().is_ok());
+        let sneaky: &'static Sneaky = &SETUP_SNEAKY;
+        sneaky.value.set(2);
+        let err = std::panic::catch_unwind(|| SETUP_SNEAKY.value.get()).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<String>().unwrap(),
+            "SETUP_SNEAKY changed after it was initialized (detected in test tests::unchanged_fixture)\n- 1\n+ 2"
+        );
+        sneaky.value.set(1);
+        assert_eq!(SETUP_SNEAKY.value.get(), 1);
+
+        assert!(hashed_setup().is_ok());
+        let counter: &'static Counter = &SETUP_HASHED;
+        counter.0.store(2, Ordering::SeqCst);
+        let err = std::panic::catch_unwind(|| SETUP_HASHED.0.load(Ordering::SeqCst)).unwrap_err();
+        let message = err.downcast_ref::<String>().unwrap();
+        assert!(message.starts_with("SETUP_HASHED changed after it was initialized"));
+        assert!(message.contains("\n- hash "));
+        counter.0.store(1, Ordering::SeqCst);
+        assert!(hashed_setup().is_ok());
+    }
+
+    #[test]
+    fn skipped_fixture() {
+        // The fixture's test passes, while its dependents fail with one line
+        assert!(unavailable_setup().is_ok());
+        let err = SETUP_UNAVAILABLE.try_get().unwrap_err();
+        assert!(err.skipped());
+        assert_eq!(err.message(), "TESTED_FIXTURE_UNSET_VAR not set");
+        assert_eq!(
+            format!("{:?}", err),
+            "skipped: TESTED_FIXTURE_UNSET_VAR not set"
+        );
+        assert_eq!(
+            err.to_string(),
+            "fixture SETUP_UNAVAILABLE (fn unavailable_setup) was skipped: TESTED_FIXTURE_UNSET_VAR not set"
+        );
+        let err = std::panic::catch_unwind(|| *SETUP_UNAVAILABLE).unwrap_err();
+        assert!(err.is::<helpers::Skip>());
+
+        // Fixtures which depend on it are skipped along with it
+        let err = after_unavailable_setup().err().unwrap();
+        assert!(err.skipped());
+        assert_eq!(
+            format!("{:?}", err),
+            "skipped: TESTED_FIXTURE_UNSET_VAR not set"
+        );
+
+        assert!(available_setup().is_ok());
+        assert_eq!(
+            SETUP_AVAILABLE.try_get().unwrap_err().message(),
+            "TESTED_FIXTURE_UNSET_VAR not set"
+        );
+        assert!(with_path_setup().is_ok());
+        assert_eq!(*SETUP_WITH_PATH, 1);
+    }
+
+    #[test]
+    fn alternative_fixture() {
+        assert!(alternative_setup().is_ok());
+        assert_eq!(*SETUP_ALTERNATIVE, 2);
+        assert!(unused_alternative_setup().is_ok());
+        assert_eq!(*SETUP_UNUSED_ALTERNATIVE, 1);
+    }
+
+    #[test]
+    fn panic_locations() {
+        // A failed assertion is reported where the fixture's body called it
+        let err = odd_setup().err().unwrap();
+        let location = format!("{}:{}:", file!(), TRACKED_LINE.load(Ordering::SeqCst));
+        assert!(err.message().starts_with("1 is odd at "));
+        assert!(err.message().contains(&location), "{}", err.message());
+
+        // While a dependent's panic is reported where it used the fixture
+        let line = line!() + 1;
+        let failure = helpers::catch_panic(|| *SETUP_ODD).err().unwrap();
+        let location = format!(" (accessed at {}:{}:", file!(), line);
+        assert!(format!("{:?}", failure).contains(&location));
+        let line = line!() + 1;
+        let failure = helpers::catch_panic(|| SETUP_CLONED_FAIL.cloned())
+            .err()
+            .unwrap();
+        let location = format!(" (accessed at {}:{}:", file!(), line);
+        assert!(format!("{:?}", failure).contains(&location));
+    }
+
+    #[test]
+    fn cascaded_failure_location() {
+        // The failure is located where the base fixture panicked, and only
+        // labels where its dependent accessed it
+        let err = cascade_setup().err().unwrap();
+        let line = |i: usize| CASCADE_LINES[i].load(Ordering::SeqCst);
+        let failed = format!(
+            "fixture SETUP_CASCADE (fn cascade_setup) panicked: fixture SETUP_CASCADE_BASE \
+             (fn cascade_base_setup) panicked (while initializing SETUP_CASCADE, required by \
+             tests::cascaded_failure_location): base exploded at {}:{}:9",
+            file!(),
+            line(0),
+        );
+        let accessed = format!(" (accessed at {}:{}:9)", file!(), line(1));
+        let message = err.to_string();
+        // Backtraces (if enabled) follow each location
+        match std::backtrace::Backtrace::capture().status() {
+            std::backtrace::BacktraceStatus::Captured => {
+                assert!(message.starts_with(&failed), "{}", message);
+                assert!(message.contains(&accessed), "{}", message);
+            }
+            _ => assert_eq!(message, failed + &accessed),
+        }
+    }
+
+    #[test]
+    fn deref_fixture() {
+        fn borrow<T: ?Sized>(v: &T) -> &T {
+            v
+        }
+
+        // The test, the handle and dependents all share the same value
+        let value: &'static HeavySetup = &SETUP_1;
+        assert!(std::ptr::eq(value, &*SETUP_1));
+        assert!(std::ptr::eq(value, SETUP_1.try_get().unwrap()));
+        assert!(std::ptr::eq(value, setup().unwrap().0));
+        assert_eq!(SETUP_1.0, 1);
+        assert_eq!(*borrow(&*SETUP_MAPPED), 4);
+        assert_eq!(borrow(&*SETUP_PANIC_MSG), "rejected setup 2");
+    }
+
+    #[test]
+    fn cache_failed_fixture() {
+        for _ in 0..2 {
+            let err = cached_fail_setup().err().unwrap();
+            assert_eq!(err.message(), r#""failed due to cached failure""#);
+            let err = SETUP_CACHED_FAIL.try_get().err().unwrap();
+            assert_eq!(err.test_name(), "tested_fixture::tests::cached_fail_setup");
+            assert!(std::panic::catch_unwind(|| SETUP_CACHED_FAIL.0).is_err());
+        }
+        assert_eq!(SETUP_CACHED_FAIL.state(), FixtureState::Failed);
+        assert_eq!(CACHED_FAIL_RUNS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn fallback_fixture() {
+        assert_eq!(SETUP_FALLBACK.0, 38);
+        assert_eq!(SETUP_DEFAULT.0, 39);
+        assert_eq!(default_setup().unwrap().0 .0, 40);
+    }
+
+    #[test]
+    fn reset_failed_fixture() {
+        let err = SETUP_FLAKY.try_get().err().unwrap();
+        assert_eq!(err.message(), r#""failed due to flakiness""#);
+        assert_eq!(SETUP_FLAKY.state(), FixtureState::Failed);
+        SETUP_FLAKY.reset();
+        assert_eq!(SETUP_FLAKY.state(), FixtureState::Uninitialized);
+        assert_eq!(SETUP_FLAKY.0, 1);
+        assert_eq!(SETUP_FLAKY.state(), FixtureState::Ok);
+    }
+
+    #[test]
+    fn retry_failed_fixture() {
+        assert!(retried_setup().is_err());
+        assert!(retried_setup().is_err());
+        std::env::set_var("TESTED_FIXTURE_RETRY", "1");
+        let retried = retried_setup();
+        std::env::remove_var("TESTED_FIXTURE_RETRY");
+        assert_eq!(retried.unwrap().0 .0, 1);
+        assert_eq!(SETUP_RETRIED.0, 1);
+    }
+
+    #[test]
+    fn reset_during_initialization() {
+        let first = std::thread::spawn(|| *SETUP_IN_FLIGHT);
+        while SETUP_IN_FLIGHT.state() != FixtureState::Initializing {
+            std::thread::yield_now();
+        }
+        SETUP_IN_FLIGHT.reset();
+        IN_FLIGHT_RELEASED.store(true, Ordering::SeqCst);
+        // The access which began before the reset gets the original result
+        assert_eq!(first.join().unwrap(), 0);
+        assert_eq!(*SETUP_IN_FLIGHT, 1);
+        assert_eq!(SETUP_IN_FLIGHT.state(), FixtureState::Ok);
+        assert_eq!(IN_FLIGHT_RUNS.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn teardown_in_reverse_order() {
+        let _ = HeavySetup::build(SETUP_TEARDOWN_2.0);
+        assert!(SETUP_TEARDOWN_FAIL.try_get().is_err());
+        helpers::run_teardowns();
+        assert_eq!(*TORN_DOWN.lock().unwrap(), [32, 31]);
+    }
+
+    #[test]
+    fn concurrent_rwlock_access() {
+        let readers: Vec<_> = (0..8)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    for _ in 0..100 {
+                        assert!(SETUP_RWLOCK.read().unwrap().starts_with("token-"));
+                    }
+                })
+            })
+            .collect();
+        let writer = std::thread::spawn(|| {
+            for i in 0..10 {
+                *SETUP_RWLOCK.write().unwrap() = format!("token-{}", i);
+            }
+        });
+        for thread in readers.into_iter().chain(Some(writer)) {
+            thread.join().unwrap();
+        }
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"fixture SETUP_MUT_FAIL (fn mut_fail_setup) returned an error: "failed due to write contention""#
+    )]
+    fn combine_mut_fail() {
+        let _setup = SETUP_MUT_FAIL.lock();
+    }
+
+    #[test]
+    fn combine_uncaught_setup() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_UNCAUGHT.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "failed due to unwinding through FFI")]
+    fn combine_uncaught_panic() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_UNCAUGHT_PANIC.0);
+    }
+
+    #[test]
+    fn combine_expected_panic() {
+        let message: &'static String = &SETUP_PANIC_MSG;
+        assert_eq!(message, "rejected setup 2");
+        assert_eq!(&*SETUP_PANIC_STR, "rejected async setup");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "fixture SETUP_NO_PANIC (fn missing_panic_setup) failed: returned without panicking"
+    )]
+    fn combine_missing_panic() {
+        let _ = SETUP_NO_PANIC.len();
+    }
+
+    #[test]
+    fn combine_mapped_setup() {
+        assert_eq!(*SETUP_MAPPED, 4);
+        assert_eq!(SETUP_MAPPED_ASYNC.as_str(), "2");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"fixture SETUP_MAPPED_PANIC (fn mapped_panic_setup) panicked: failed to map"#
+    )]
+    fn combine_mapped_panic() {
+        let _ = HeavySetup::build(*SETUP_MAPPED_PANIC);
+    }
+
+    #[test]
+    fn combine_stored_setup() {
+        let shared: Arc<HeavySetup> = Arc::clone(&SETUP_SHARED);
+        let widened: &'static u64 = &SETUP_WIDENED;
+        assert_eq!(u64::from(shared.0), *widened);
+    }
+
+    #[test]
+    fn combine_deep_setup() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_DEEP.0);
+        assert_eq!(
+            SETUP_PARTIAL.as_ref().err(),
+            Some(&"kept as part of the fixture")
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"fixture SETUP_DEEP_FAIL (fn deep_fail_setup) returned an error: "failed six layers down""#
+    )]
+    fn combine_deep_fail() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_DEEP_FAIL.0);
+    }
+
+    /// An error which intentionally doesn't implement `Debug`
+    struct OpaqueError;
+
+    impl std::fmt::Display for OpaqueError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("failed due to opaque reasons")
+        }
+    }
+
+    #[tested_fixture(SETUP_DISPLAY: HeavySetup, error_display)]
+    fn display_setup() -> Result<HeavySetup, OpaqueError> {
+        Ok(HeavySetup::build(17))
+    }
+
+    #[tested_fixture(SETUP_DISPLAY_FAIL: HeavySetup, error_display)]
+    #[ignore = "fails"]
+    fn display_fail_setup() -> Result<HeavySetup, OpaqueError> {
+        Err(OpaqueError)
+    }
+
+    #[test]
+    fn combine_display_setup() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_DISPLAY.0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "fixture SETUP_DISPLAY_FAIL (fn display_fail_setup) returned an error: failed due to opaque reasons"
+    )]
+    fn combine_display_fail() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_DISPLAY_FAIL.0);
+    }
+
+    #[derive(Debug)]
+    enum TestOutcome<T> {
+        Passed(T),
+        Failed(&'static str),
+    }
+
+    impl<T> Termination for TestOutcome<T> {
+        fn report(self) -> ExitCode {
+            match self {
+                TestOutcome::Passed(_) => ExitCode::SUCCESS,
+                TestOutcome::Failed(_) => ExitCode::FAILURE,
+            }
+        }
+    }
+
+    impl<T> IntoFixtureResult for TestOutcome<T> {
+        type Value = T;
+        type Error = &'static str;
+        fn as_fixture_result(&self) -> Result<&T, &&'static str> {
+            match self {
+                TestOutcome::Passed(v) => Ok(v),
+                TestOutcome::Failed(e) => Err(e),
+            }
+        }
+    }
+
+    #[tested_fixture(SETUP_OUTCOME: HeavySetup)]
+    fn outcome_setup() -> TestOutcome<HeavySetup> {
+        TestOutcome::Passed(HeavySetup::build(11))
+    }
+
+    #[tested_fixture(SETUP_OUTCOME_TRY: HeavySetup)]
+    fn outcome_try_setup() -> Result<TestOutcome<HeavySetup>, &'static str> {
+        Ok(TestOutcome::Passed(HeavySetup::build(12)))
+    }
+
+    #[tested_fixture(SETUP_OUTCOME_FAIL: HeavySetup)]
+    #[ignore = "fails"]
+    fn outcome_fail_setup() -> TestOutcome<HeavySetup> {
+        TestOutcome::Failed("failed due to inverted outcomes")
+    }
+
+    #[tested_fixture(SETUP_SOME: HeavySetup)]
+    fn some_setup() -> Option<HeavySetup> {
+        Some(HeavySetup::build(13))
+    }
+
+    #[tested_fixture(SETUP_NONE: HeavySetup)]
+    #[ignore = "fails"]
+    fn none_setup() -> Option<HeavySetup> {
+        None
+    }
+
+    #[test]
+    fn combine_option_setup() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_SOME.0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "fixture SETUP_NONE (fn none_setup) returned an error: returned None"
+    )]
+    fn combine_option_none() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_NONE.0);
+    }
+
+    #[test]
+    fn combine_outcome_setup() {
+        let _ = HeavySetup::build(SETUP_OUTCOME.0 + SETUP_OUTCOME_TRY.0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"fixture SETUP_OUTCOME_FAIL (fn outcome_fail_setup) returned an error: "failed due to inverted outcomes""#
+    )]
+    fn combine_outcome_fail() {
+        let _ = HeavySetup::build(SETUP_1.0 + SETUP_OUTCOME_FAIL.0);
+    }
+
+    #[test]
+    fn combine_async_setup() {
+        let _ = HeavySetup::build(SETUP_5.0 + SETUP_6.0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"fixture SETUP_7 (fn async_panic_setup) panicked: failed due to unresolved futures"#
+    )]
+    fn combine_async_panic() {
+        let _ = HeavySetup::build(SETUP_5.0 + SETUP_7.0);
+    }
+
+    #[test]
+    fn combine_timeout_setup() {
+        let _ = HeavySetup::build(SETUP_5.0 + SETUP_TIMEOUT.0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"fixture SETUP_TIMEOUT_FAIL (fn timeout_fail_setup) failed: SETUP_TIMEOUT_FAIL timed out after 10ms"#
+    )]
+    fn combine_timeout_fail() {
+        let _ = HeavySetup::build(SETUP_5.0 + SETUP_TIMEOUT_FAIL.0);
+    }
+
+    #[test]
+    fn combine_sync_timeout_setup() {
+        let _ = HeavySetup::build(SETUP_5.0 + SETUP_SYNC_TIMEOUT.0);
+        // The helper thread is attributed to the test which triggered it
+        let triggered_by = SETUP_SYNC_TIMEOUT.triggered_by();
+        assert!(triggered_by.map_or(true, |test| test.ends_with("sync_timeout_setup")));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"fixture SETUP_SYNC_TIMEOUT_FAIL (fn sync_timeout_fail_setup) failed: SETUP_SYNC_TIMEOUT_FAIL timed out after 10ms"#
+    )]
+    fn combine_sync_timeout_fail() {
+        let _ = HeavySetup::build(SETUP_5.0 + SETUP_SYNC_TIMEOUT_FAIL.0);
+    }
+
+    #[test]
+    fn large_stack_size() {
+        assert_eq!(*SETUP_DEEP_STACK, 20_000);
+    }
+
+    #[test]
+    fn sync_timeout_panic() {
+        let err = SETUP_SYNC_TIMEOUT_PANIC.try_get().err().unwrap();
+        assert!(err.panicked());
+        // Reported like a panic on the test's own thread, with its origin
+        assert!(err
+            .message()
+            .starts_with("failed due to 0 timeouts at src/lib.rs"));
     }
 }